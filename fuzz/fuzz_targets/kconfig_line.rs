@@ -0,0 +1,43 @@
+#![no_main]
+
+use auto_script::core::utils::get_filed;
+use auto_script::KconfigCounter;
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+
+// every keyword the Kconfig parser hands to `get_filed`
+const KEYWORDS: [&str; 9] = [
+    "source",
+    "if",
+    "prompt",
+    "default",
+    "config",
+    "depends on",
+    "select",
+    "def_bool",
+    "def_tristate",
+];
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    for line in text.lines() {
+        for keyword in KEYWORDS {
+            get_filed(line, keyword);
+            get_filed(line.trim(), keyword);
+        }
+    }
+
+    // `source` lines are recorded but never opened, nothing touches the disk
+    let kconfig = Path::new("Kconfig");
+    let mut kc = KconfigCounter::builder()
+        .arch("riscv")
+        .version("6.9.5")
+        .kconfig_path(kconfig)
+        .recurse_source(false)
+        .build()
+        .expect("no defconfig to check");
+    // invalid UTF-8 is an error, not a panic
+    let _ = kc.parse_kconfig_reader(data, kconfig);
+    kc.find_default_conflicts();
+    let _ = kc.parse_code_reader(data, Path::new("arch/riscv/kernel/smp.c"));
+});
@@ -0,0 +1,486 @@
+//! Command line arguments of the `auto_script` binary.
+//!
+//! Options shared by every subcommand (kernel tree, arches, logging and
+//! output) are global and may be given before or after the subcommand.
+//!
+//! ```
+//! use auto_script::cli::{Cli, Command};
+//! use clap::Parser;
+//!
+//! let cli = Cli::try_parse_from(["auto_script", "-a", "riscv,arm64", "count", "--spdx"]).unwrap();
+//! assert_eq!(cli.global.arch.len(), 2);
+//! assert!(matches!(cli.command, Command::Count(count) if count.spdx));
+//! ```
+
+use crate::core::baseline::parse_tolerance;
+use crate::core::kconfig_counter::{ComponentGroup, DiffFormat};
+use crate::core::log::LogFormat;
+use crate::core::pipeline::Options;
+use crate::core::report::{ColorChoice, MIN_WIDTH};
+use crate::core::trend::TrendFormat;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 统计各架构目录（可选整个内核源码树）的代码行数
+    Count(CountArgs),
+    /// 解析各架构的Kconfig组件，默认输出统计表后进入交互模式
+    Kconfig(KconfigArgs),
+    /// 解析Kconfig组件并统计受`CONFIG_`保护的代码
+    Analyze(AnalyzeArgs),
+    /// 对比Kconfig组件：与另一个内核源码树或导出文件对比，或对比两个defconfig
+    Diff(DiffArgs),
+    /// 统计代码及Kconfig（含受保护的代码）并写入或检查基线，便于CI检查
+    Validate(ValidateArgs),
+    /// 依次统计某目录下的多个内核源码树，按版本顺序输出长格式的趋势数据
+    Trend(TrendArgs),
+    /// 追踪某个Kconfig组件在多个内核源码树中的属性变化，按内核版本先后输出
+    Trace(TraceArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct GlobalArgs {
+    /// 指定需要解析的模块架构
+    #[arg(
+        long,
+        short = 'a',
+        value_delimiter = ',',
+        default_value = "riscv",
+        global = true
+    )]
+    pub arch: Vec<PathBuf>,
+
+    /// 指定各架构目录相对于内核根目录的路径模板，`{arch}`会被替换为架构名，架构的Kconfig位于该目录下
+    #[arg(long, default_value = "arch/{arch}", value_parser = parse_arch_template, global = true)]
+    pub arch_template: String,
+
+    /// 指定需要解析的内核位置
+    #[arg(long, short = 'p', default_value = "/opt/linux-6.9.5", global = true)]
+    pub kernel_path: PathBuf,
+
+    /// 指定存放多个内核源码树的目录，与`kernel`一起使用，从其直接子目录（含顶层Makefile）中按版本选择源码树
+    #[arg(long, requires = "kernel", global = true)]
+    pub workspace: Option<PathBuf>,
+
+    /// 从`workspace`中选择版本匹配的源码树代替`kernel_path`，按前缀匹配（如`6.9`匹配6.9.5），多个匹配时选择最新的版本；未指定`workspace`时使用配置文件`$XDG_CONFIG_HOME/auto_script/config`中的`workspace = DIR`
+    #[arg(
+        long,
+        value_name = "VERSION",
+        conflicts_with = "kernel_path",
+        global = true
+    )]
+    pub kernel: Option<String>,
+
+    /// 从`kernel_path`处git仓库的指定修订（标签、分支或提交）读取Makefile、Kconfig及源码，而不是工作区中的文件；`kernel_path`也可以是裸仓库
+    #[cfg(feature = "git")]
+    #[arg(long, global = true)]
+    pub git_rev: Option<String>,
+
+    /// 从kernel.org下载指定版本（如`6.9.5`）的源码包，校验大小及sha256后解压到`$XDG_CACHE_HOME/auto_script/`并代替`kernel_path`使用，同一版本再次运行时直接复用缓存
+    #[cfg(feature = "fetch")]
+    #[arg(
+        long,
+        value_name = "VERSION",
+        conflicts_with_all = ["kernel_path", "kernel"],
+        global = true
+    )]
+    #[cfg_attr(feature = "git", arg(conflicts_with = "git_rev"))]
+    pub fetch: Option<String>,
+
+    /// 与`fetch`一起使用，只使用缓存中已有的源码，不访问网络
+    #[cfg(feature = "fetch")]
+    #[arg(long, requires = "fetch", global = true)]
+    pub offline: bool,
+
+    /// 指定内核的.config（或defconfig）文件，其中的`CONFIG_LOCALVERSION`与源码树根目录下的`localversion*`文件一起追加到报告及导出文件中的内核版本
+    #[arg(long, global = true)]
+    pub dotconfig: Option<PathBuf>,
+
+    /// 指定统计表格的总宽度，默认跟随终端宽度，输出不是终端时代码表为70列、Kconfig表为90列，过长的名称会被截断并以`~`结尾
+    #[arg(long, value_parser = clap::value_parser!(u16).range(MIN_WIDTH as i64..), global = true)]
+    pub width: Option<u16>,
+
+    /// 当任一分析结果为空（未统计到文件或未解析到组件）时以错误退出，便于CI检查
+    #[arg(long, global = true)]
+    pub fail_on_empty: bool,
+
+    /// 将无法读取的目录或文件视为致命错误
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// 输出更详细的统计信息（文件字节数及各语言最大的文件），重复指定可提高日志级别（-vv为info，-vvv为debug）
+    #[arg(long, short = 'v', action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// 将日志写入指定文件（按大小轮转），warn及以上级别同时输出到stderr；未指定时日志输出到stderr，级别可由RUST_LOG覆盖（同时覆盖`log_filter`）
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// 不再逐个文件输出日志，每个阶段结束时只输出一行统计；选择机器可读的输出格式时默认开启
+    #[arg(long, short = 'q', global = true)]
+    pub quiet: bool,
+
+    /// 按模块指定日志级别，格式同RUST_LOG，例如`auto_script::core::kconfig_counter=debug,auto_script::core::file_counter=warn`，叠加在`verbose`的级别之上
+    #[arg(long, global = true)]
+    pub log_filter: Option<String>,
+
+    /// 指定日志格式，json为每行一个JSON对象，包含时间、级别、模块、消息及`arch`等上下文字段
+    #[arg(long, value_enum, default_value = "human", global = true)]
+    pub log_format: LogFormat,
+
+    /// 遍历目录时按名称排序，使不同机器上的遍历顺序与日志保持一致
+    #[arg(long, global = true)]
+    pub sorted: bool,
+
+    /// 输出中的路径（警告、组件定义位置、代码块来源、最大文件等）显示为相对于`kernel_path`的路径
+    #[arg(long, global = true)]
+    pub relative: bool,
+
+    /// 将JSON、Markdown、CSV及DOT格式的报告写入指定文件而不是标准输出，扩展名为`.gz`或`.xz`时自动压缩；文本表格仍输出到标准输出
+    #[arg(long, short = 'o', global = true)]
+    pub output: Option<PathBuf>,
+
+    /// 指定对比结果（`diff`及多架构对比）及`count`统计结果的输出格式，`count`只区分text与json
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        alias = "diff-format",
+        global = true
+    )]
+    pub format: DiffFormat,
+
+    /// 何时为统计表格标题和交互模式中的代码片段着色（预处理指令、所查询组件的`CONFIG_`符号、C关键字与注释），auto为输出是终端时着色
+    #[arg(long, value_enum, default_value = "auto", global = true)]
+    pub color: ColorChoice,
+}
+
+// how files are found and classified, shared by `count` and `validate`
+#[derive(Args, Debug)]
+pub struct CodeArgs {
+    /// 指定额外的扩展名到语言的映射（如`.dtso=DeviceTree,.foo=Foo`），优先于内置规则，未知的语言名会新建分类
+    #[arg(long, value_delimiter = ',', value_parser = parse_lang_mapping)]
+    pub lang_map: Vec<(String, String)>,
+
+    /// 是否需要统计整个内核源码树（公共部分加上各架构部分）
+    #[arg(long)]
+    pub whole_tree: bool,
+
+    /// 指定增量统计缓存文件，未改变（修改时间与大小相同）的文件直接复用缓存结果
+    #[arg(long)]
+    pub cache: Option<PathBuf>,
+
+    /// 忽略`cache`的设定，不读取也不写入缓存
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// 重复统计硬链接到同一文件的路径（默认每个物理文件只统计一次）
+    #[arg(long)]
+    pub count_duplicates: bool,
+
+    /// 按文件内容哈希统计内容完全相同的文件所占的行数
+    #[arg(long)]
+    pub dedup_by_hash: bool,
+
+    /// 额外统计内核根目录下的`Documentation`目录，文档（`.rst`、`.txt`、`.md`）的非空行按正文计入代码列
+    #[arg(long)]
+    pub include_docs: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct CountArgs {
+    #[command(flatten)]
+    pub code: CodeArgs,
+
+    /// 统计注释中的TODO/FIXME/XXX标记，并列出标记最多的前N个文件（默认10个）
+    #[arg(long, num_args = 0..=1, default_missing_value = "10")]
+    pub todos: Option<usize>,
+
+    /// 指定用于对比的另一个内核源码树，输出两者各架构代码统计的差值
+    #[arg(long)]
+    pub compare: Option<PathBuf>,
+
+    /// 是否需要统计SPDX许可证标识
+    #[arg(long)]
+    pub spdx: bool,
+
+    /// 统计C头文件的防重复包含方式（`#pragma once`、`#ifndef`保护宏或两者皆无），并列出未受保护的头文件
+    #[arg(long)]
+    pub header_guards: bool,
+
+    /// 按语言统计没有代码行的文件数，分为空文件（只有空行或没有内容）和只有注释的文件
+    #[arg(long)]
+    pub file_health: bool,
+}
+
+// which Kconfig files are followed, shared by every Kconfig subcommand
+#[derive(Args, Debug)]
+pub struct KconfigParseArgs {
+    /// 是否需要解析全部Kconfig
+    #[arg(long, short = 'f')]
+    pub full: bool,
+
+    /// 指定`source`递归时允许进入的路径前缀（相对于内核根目录），`full`会忽略该限制
+    #[arg(long, value_delimiter = ',', default_value = "arch/")]
+    pub source_include: Vec<String>,
+
+    /// 只解析架构顶层的Kconfig，不进入任何`source`引用的文件，用于快速浏览
+    #[arg(long, conflicts_with = "full")]
+    pub no_recurse_source: bool,
+}
+
+// where guarded code is searched, shared by the subcommands counting it
+#[derive(Args, Debug)]
+pub struct GuardedCodeArgs {
+    /// 指定需要解析的代码目录（相对于内核根目录），替代由Kconfig推导的目录
+    #[arg(long, value_delimiter = ',')]
+    pub code_dir: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct KconfigArgs {
+    #[command(flatten)]
+    pub parse: KconfigParseArgs,
+
+    /// 指定Kconfig组件列表的分组方式（按首字母、按定义所在的Kconfig文件或按其所在的顶层目录，drivers/下再细分一级）
+    #[arg(long, value_enum)]
+    pub group_by: Option<ComponentGroup>,
+
+    /// 以非交互方式逐行输出解析到的全部Kconfig组件名称后退出
+    #[arg(long)]
+    pub list_components: bool,
+
+    /// 在`list_components`的输出中追加以制表符分隔的类型列
+    #[arg(long, requires = "list_components")]
+    pub list_types: bool,
+
+    /// 以非交互方式输出指定Kconfig组件的详细信息后退出，名称不区分大小写且可带`CONFIG_`前缀，可用逗号分隔多个；含`*`或`?`时输出全部匹配的组件，超过50个时需确认
+    #[arg(long, value_delimiter = ',')]
+    pub query: Vec<String>,
+
+    /// 在多架构的Kconfig对比中列出各架构独有的组件
+    #[arg(long)]
+    pub show_exclusive: bool,
+
+    /// 列出依赖（depends on）、选择（select）或默认值（default）引用了指定组件的全部组件
+    #[arg(long)]
+    pub reverse_deps: Option<String>,
+
+    /// 输出Kconfig组件依赖图中的循环依赖
+    #[arg(long)]
+    pub cycles: bool,
+
+    /// 输出`depends on`引用了未定义符号的Kconfig组件，这些组件永远无法被选中
+    #[arg(long)]
+    pub dangling_depends: bool,
+
+    /// 以Graphviz DOT格式输出Kconfig组件依赖图，default引用以蓝色虚线表示
+    #[arg(long)]
+    pub dot: bool,
+
+    /// 将解析到的Kconfig组件导出为JSON文件，可作为`diff`的另一侧
+    #[arg(long)]
+    pub export_kconfig: Option<PathBuf>,
+
+    /// 将交互模式中输入过的组件名保存到`~/.auto_script_history`，下次进入时可用方向键翻阅
+    #[arg(long)]
+    pub history: bool,
+
+    /// 分析完成后进入全屏浏览界面（左侧为可过滤的组件列表，右侧为属性、依赖树和代码片段），需要在终端中运行
+    #[arg(long, conflicts_with = "history")]
+    pub tui: bool,
+
+    /// 只解析Kconfig并输出汇总表和合计后退出，不统计受保护的代码，也不进入交互模式
+    #[arg(long, conflicts_with_all = ["history", "tui"])]
+    pub summary_only: bool,
+
+    /// 从标准输入读取Kconfig（按第一个架构解析），不需要完整的内核源码树，其中的`source`只记录不展开
+    #[arg(long, conflicts_with = "tui")]
+    pub stdin: bool,
+
+    /// 按目录输出受Kconfig组件控制的代码（代码块所在文件和Makefile中`obj-`规则的目标），目录按相关组件数从多到少排列
+    #[arg(long)]
+    pub by_dir: bool,
+
+    /// 按子系统（定义组件的Kconfig所在的顶层目录，drivers/下细分到第二级）输出组件数、类型分布，统计代码时还输出受保护的代码行数
+    #[arg(long, conflicts_with = "by_dir")]
+    pub by_subsystem: bool,
+
+    /// 根据内核根目录下的`MAINTAINERS`文件为每个组件查找所属子系统及其维护者，显示在组件详情中并写入导出文件
+    #[arg(long, conflicts_with = "stdin")]
+    pub maintainers: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    #[command(flatten)]
+    pub kconfig: KconfigArgs,
+
+    #[command(flatten)]
+    pub code: GuardedCodeArgs,
+
+    /// 输出受`CONFIG_`保护的最大的N个代码块及其组件和所在文件
+    #[arg(long, value_name = "N")]
+    pub top_snippets: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+#[command(group(clap::ArgGroup::new("side").required(true).args(["other", "defconfig"])))]
+pub struct DiffArgs {
+    #[command(flatten)]
+    pub parse: KconfigParseArgs,
+
+    #[command(flatten)]
+    pub code: GuardedCodeArgs,
+
+    /// 另一个内核源码树或`kconfig --export-kconfig`导出的JSON文件，输出两者Kconfig组件的增删与属性变化
+    #[arg(conflicts_with = "defconfig")]
+    pub other: Option<PathBuf>,
+
+    /// 同时统计两侧受保护的代码行数
+    #[arg(long)]
+    pub guarded_code: bool,
+
+    /// 改为对比两侧各组件受保护代码行数的变化，按变化量排序
+    #[arg(long, conflicts_with = "defconfig")]
+    pub code_delta: bool,
+
+    /// 指定一个defconfig（或.config）文件，与`compare_defconfig`对比启用状态不同的组件
+    #[arg(long, requires = "compare_defconfig")]
+    pub defconfig: Option<PathBuf>,
+
+    /// 指定与`defconfig`对比的另一个defconfig文件，输出翻转的组件及其受保护的代码行数（配合`guarded_code`）
+    #[arg(long, requires = "defconfig")]
+    pub compare_defconfig: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+#[command(group(clap::ArgGroup::new("baseline").required(true).multiple(true)))]
+pub struct ValidateArgs {
+    #[command(flatten)]
+    pub code: CodeArgs,
+
+    #[command(flatten)]
+    pub parse: KconfigParseArgs,
+
+    #[command(flatten)]
+    pub guarded: GuardedCodeArgs,
+
+    /// 将本次代码与Kconfig统计的各项指标写入基线文件
+    #[arg(long, group = "baseline")]
+    pub write_baseline: Option<PathBuf>,
+
+    /// 将本次统计结果与基线文件对比，输出各项指标的偏差，任一偏差超过`tolerance`时以错误退出
+    #[arg(long, group = "baseline")]
+    pub check_baseline: Option<PathBuf>,
+
+    /// 指定`check_baseline`允许的偏差百分比，例如`5%`
+    #[arg(long, value_parser = parse_tolerance, default_value = "0", requires = "check_baseline")]
+    pub tolerance: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum TrendMetrics {
+    // the code table of every arch
+    Code,
+    // Kconfig components and types
+    Kconfig,
+    // Kconfig components together with their guarded code lines
+    Analyze,
+}
+
+#[derive(Args, Debug)]
+pub struct TrendArgs {
+    /// 存放多个内核源码树的目录
+    pub dir: PathBuf,
+
+    /// 指定统计的内容
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "code")]
+    pub metrics: Vec<TrendMetrics>,
+
+    #[command(flatten)]
+    pub parse: KconfigParseArgs,
+
+    /// 指定趋势数据的输出格式
+    #[arg(long, value_enum, default_value = "csv")]
+    pub trend_format: TrendFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct TraceArgs {
+    /// 需要追踪的Kconfig组件
+    pub symbol: String,
+
+    /// 需要对比的内核源码树
+    #[arg(required = true)]
+    pub kernel_paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub parse: KconfigParseArgs,
+}
+
+impl CodeArgs {
+    pub fn apply_to(&self, options: &mut Options) {
+        options.lang_map = self.lang_map.clone();
+        options.whole_tree = self.whole_tree;
+        options.cache = self.cache.clone().filter(|_| !self.no_cache);
+        options.count_duplicates = self.count_duplicates;
+        options.dedup_by_hash = self.dedup_by_hash;
+        options.include_docs = self.include_docs;
+    }
+}
+
+impl KconfigParseArgs {
+    pub fn apply_to(&self, options: &mut Options) {
+        options.full = self.full;
+        options.source_include = self.source_include.clone();
+        options.no_recurse_source = self.no_recurse_source;
+    }
+}
+
+impl GuardedCodeArgs {
+    pub fn apply_to(&self, options: &mut Options) {
+        options.code_dir = self.code_dir.clone();
+    }
+}
+
+fn parse_arch_template(value: &str) -> Result<String, String> {
+    if value.contains("{arch}") {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "arch template {:?} has no {{arch}} placeholder",
+            value
+        ))
+    }
+}
+
+// `.dtso=DeviceTree`, the leading dot is optional
+fn parse_lang_mapping(value: &str) -> Result<(String, String), String> {
+    let (extension, language) = value.split_once('=').ok_or_else(|| {
+        format!(
+            "language mapping {:?} is not `<extension>=<language>`",
+            value
+        )
+    })?;
+    let extension = extension.trim().trim_start_matches('.');
+    let language = language.trim();
+    if extension.is_empty() || language.is_empty() {
+        return Err(format!(
+            "language mapping {:?} needs both an extension and a language",
+            value
+        ));
+    }
+    Ok((extension.to_string(), language.to_string()))
+}
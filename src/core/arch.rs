@@ -0,0 +1,45 @@
+use crate::core::utils::edit_distance;
+
+// "did you mean" candidates offered for an arch without a directory
+const MAX_SUGGESTIONS: usize = 3;
+
+// `um` runs on top of a host arch, its Kconfig sources `arch/$(HEADER_ARCH)/um/Kconfig`
+pub const UM_HOST_ARCH: &str = "x86";
+
+/// The directory below `arch/` holding `arch`, as the kernel's top level Makefile
+/// derives `SRCARCH` from `ARCH`.
+///
+/// ```
+/// use auto_script::core::arch::srcarch;
+///
+/// assert_eq!(srcarch("x86_64"), "x86");
+/// assert_eq!(srcarch("sparc64"), "sparc");
+/// assert_eq!(srcarch("riscv"), "riscv");
+/// ```
+pub fn srcarch(arch: &str) -> &str {
+    match arch {
+        "i386" | "x86_64" => "x86",
+        "sparc32" | "sparc64" => "sparc",
+        "sh64" => "sh",
+        "parisc64" => "parisc",
+        // user mode Linux keeps its own directory, the host arch only fills `HEADER_ARCH`
+        "um" => "um",
+        _ => arch,
+    }
+}
+
+// the closest few of `known` for an arch that was not found, nearest first
+pub fn suggestions<'a>(arch: &str, known: &'a [String]) -> Vec<&'a str> {
+    let limit = (arch.len() / 3).max(1);
+    let mut candidates: Vec<(usize, &str)> = known
+        .iter()
+        .map(|name| (edit_distance(arch, name), name.as_str()))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
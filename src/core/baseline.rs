@@ -0,0 +1,120 @@
+use crate::core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// bump whenever metric names or their meaning change
+pub const BASELINE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Baseline {
+    version: u32,
+    kernel_version: String,
+    metrics: BTreeMap<String, u64>,
+}
+
+// `--tolerance 5%` and `--tolerance 5` are the same
+pub fn parse_tolerance(value: &str) -> std::result::Result<f64, String> {
+    let percent = value.trim().trim_end_matches('%');
+    match percent.parse::<f64>() {
+        Ok(percent) if percent >= 0.0 => Ok(percent),
+        _ => Err(format!("invalid tolerance {:?}, expected e.g. 5%", value)),
+    }
+}
+
+impl Baseline {
+    pub fn new(kernel_version: String) -> Self {
+        Baseline {
+            version: BASELINE_VERSION,
+            kernel_version,
+            metrics: BTreeMap::new(),
+        }
+    }
+
+    // metrics are stored as `<prefix>.<name>`, e.g. `code.riscv.C.code`
+    pub fn record(&mut self, prefix: &str, metrics: BTreeMap<String, u64>) {
+        for (name, value) in metrics {
+            self.metrics.insert(format!("{}.{}", prefix, name), value);
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(Error::io(path))?;
+        let baseline: Baseline = serde_json::from_str(&content).map_err(Error::json(path))?;
+        if baseline.version > BASELINE_VERSION {
+            return Err(Error::NewerVersion {
+                what: "baseline",
+                found: baseline.version,
+                supported: BASELINE_VERSION,
+            });
+        }
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(Error::json(path))?;
+        fs::write(path, json).map_err(Error::write(path))
+    }
+
+    // the drift of every metric either side measured against `current`, unchanged ones included
+    pub fn check(&self, current: &Baseline, tolerance: f64) -> BaselineReport {
+        let mut names: Vec<&String> = self.metrics.keys().chain(current.metrics.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        let mut metrics = Vec::new();
+        for name in names {
+            let baseline = self.metrics.get(name).copied();
+            let current = current.metrics.get(name).copied();
+            // a metric only one side measured is listed but never fails the check
+            let drift = match (baseline, current) {
+                (Some(baseline), Some(current)) if baseline == current => Some(0.0),
+                (Some(0), Some(_)) => Some(f64::INFINITY),
+                (Some(baseline), Some(current)) => {
+                    Some((current as f64 - baseline as f64) / baseline as f64 * 100.0)
+                }
+                _ => None,
+            };
+            metrics.push(MetricDrift {
+                name: name.clone(),
+                baseline,
+                current,
+                drift,
+                over: drift.is_some_and(|drift| drift.abs() > tolerance),
+            });
+        }
+
+        BaselineReport {
+            old_version: self.kernel_version.clone(),
+            new_version: current.kernel_version.clone(),
+            tolerance,
+            metrics,
+        }
+    }
+}
+
+pub struct MetricDrift {
+    pub name: String,
+    // `None` on the side that did not measure the metric
+    pub baseline: Option<u64>,
+    pub current: Option<u64>,
+    // percent, only when both sides measured it
+    pub drift: Option<f64>,
+    pub over: bool,
+}
+
+// see `Baseline::check`
+pub struct BaselineReport {
+    pub old_version: String,
+    pub new_version: String,
+    pub tolerance: f64,
+    pub metrics: Vec<MetricDrift>,
+}
+
+impl BaselineReport {
+    // how many metrics exceed the tolerance
+    pub fn exceeded(&self) -> usize {
+        self.metrics.iter().filter(|metric| metric.over).count()
+    }
+}
@@ -0,0 +1,101 @@
+use crate::core::error::{Error, Result};
+use crate::core::file_counter::{FileLines, FileType};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// bump whenever the file classification or line counting rules change
+pub const CACHE_VERSION: u32 = 6;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    mtime: (u64, u32),
+    size: u64,
+    pub file_type: FileType,
+    pub lines: FileLines,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScanCache {
+    version: u32,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        ScanCache {
+            version: CACHE_VERSION,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+pub fn mtime_of(metadata: &fs::Metadata) -> (u64, u32) {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| (since.as_secs(), since.subsec_nanos()))
+        .unwrap_or((0, 0))
+}
+
+impl ScanCache {
+    // a missing, unreadable or outdated cache simply starts out empty
+    pub fn load(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => {
+                info!("no scan cache found at {:?}", path);
+                return ScanCache::default();
+            }
+        };
+
+        match serde_json::from_str::<ScanCache>(&content) {
+            Ok(cache) if cache.version == CACHE_VERSION => cache,
+            Ok(cache) => {
+                warn!(
+                    "discard scan cache {:?}: version {} != {}",
+                    path, cache.version, CACHE_VERSION
+                );
+                ScanCache::default()
+            }
+            Err(err) => {
+                warn!("discard unreadable scan cache {:?}: {}", path, err);
+                ScanCache::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(Error::json(path))?;
+        fs::write(path, json).map_err(Error::write(path))
+    }
+
+    pub fn get(&self, path: &Path, mtime: (u64, u32), size: u64) -> Option<&CacheEntry> {
+        self.entries
+            .get(path)
+            .filter(|entry| entry.mtime == mtime && entry.size == size)
+    }
+
+    pub fn insert(
+        &mut self,
+        path: PathBuf,
+        mtime: (u64, u32),
+        size: u64,
+        file_type: FileType,
+        lines: FileLines,
+    ) {
+        self.entries.insert(
+            path,
+            CacheEntry {
+                mtime,
+                size,
+                file_type,
+                lines,
+            },
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use crate::core::error::{Error, Result};
+use crate::core::utils::strip_bom;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+// the symbol values of a `.config` or defconfig, keyed without the `CONFIG_` prefix
+pub struct DotConfig {
+    values: BTreeMap<String, String>,
+}
+
+impl DotConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(Error::io(path))?;
+        let reader = io::BufReader::new(file);
+
+        let mut values = BTreeMap::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::io(path))?;
+            let line = if index == 0 { strip_bom(&line) } else { &line };
+            let line = line.trim();
+            // `# CONFIG_FOO is not set` is the only meaningful comment
+            if let Some(name) = line
+                .strip_prefix("# CONFIG_")
+                .and_then(|rest| rest.strip_suffix(" is not set"))
+            {
+                values.insert(name.to_string(), "n".to_string());
+                continue;
+            }
+            if let Some((name, value)) = line
+                .strip_prefix("CONFIG_")
+                .and_then(|rest| rest.split_once('='))
+            {
+                values.insert(name.to_string(), value.trim_matches('"').to_string());
+            }
+        }
+        Ok(DotConfig { values })
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.as_str())
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !matches!(self.value(name), None | Some("n"))
+    }
+}
+
+pub struct ConfigDiff {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    // symbols enabled on both sides with a different value, e.g. `y` -> `m`
+    pub changed: Vec<(String, String, String)>,
+}
+
+pub fn diff_configs(a: &DotConfig, b: &DotConfig) -> ConfigDiff {
+    let names: BTreeSet<&String> = a.values.keys().chain(b.values.keys()).collect();
+    let mut diff = ConfigDiff {
+        only_in_a: Vec::new(),
+        only_in_b: Vec::new(),
+        changed: Vec::new(),
+    };
+    for name in names {
+        match (a.is_enabled(name), b.is_enabled(name)) {
+            (true, false) => diff.only_in_a.push(name.clone()),
+            (false, true) => diff.only_in_b.push(name.clone()),
+            (true, true) if a.value(name) != b.value(name) => {
+                diff.changed
+                    .push((name.clone(), a.values[name].clone(), b.values[name].clone()))
+            }
+            _ => {}
+        }
+    }
+    diff
+}
@@ -0,0 +1,164 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+// every variant names the file or option it is about, the message alone must be actionable
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no VERSION, PATCHLEVEL and SUBLEVEL found in {}", makefile.display())]
+    KernelVersionNotFound { makefile: PathBuf },
+
+    #[error("{} sources {}, which does not exist", referenced_from.display(), path.display())]
+    KconfigSourceMissing {
+        referenced_from: PathBuf,
+        path: PathBuf,
+    },
+
+    #[error("arch {arch:?} not found: {} does not exist", path.display())]
+    ArchNotFound { arch: String, path: PathBuf },
+
+    #[error("kconfig counter is missing `{0}`, set it on the builder before `build()`")]
+    MissingOption(&'static str),
+
+    #[error("failed to read {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to write {}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("invalid JSON in {}", path.display())]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("{what} version {found} is newer than the supported version {supported}")]
+    NewerVersion {
+        what: &'static str,
+        found: u32,
+        supported: u32,
+    },
+
+    // `--workspace` with `--kernel`, the candidates list every tree found
+    #[error("no kernel tree under {} matches {query:?}, found {candidates}", workspace.display())]
+    KernelNotFound {
+        workspace: PathBuf,
+        query: String,
+        candidates: String,
+    },
+
+    #[error("{query:?} matches several trees of the same release under {}: {candidates}", workspace.display())]
+    AmbiguousKernel {
+        workspace: PathBuf,
+        query: String,
+        candidates: String,
+    },
+
+    // a line of the config file, see `workspace::Config`
+    #[error("{}:{line}: {reason}", path.display())]
+    InvalidConfig {
+        path: PathBuf,
+        line: usize,
+        reason: &'static str,
+    },
+
+    // a `depends on`, `if` or `default ... if` condition `kconfig_expr` cannot read
+    #[error("invalid Kconfig expression {expr:?}: {reason}")]
+    InvalidExpr { expr: String, reason: &'static str },
+
+    // `--strict`, the message is the scan summary listing the unreadable paths
+    #[error("{0}")]
+    UnreadablePaths(String),
+
+    // `--fail-on-empty`, e.g. "code analysis of arch \"riscv\" counted no files"
+    #[error("{0}")]
+    EmptyAnalysis(String),
+
+    // `--git-rev`, the repository could not be opened or has no such revision
+    #[cfg(feature = "git")]
+    #[error("failed to read revision {rev:?} of {}", path.display())]
+    Git {
+        path: PathBuf,
+        rev: String,
+        #[source]
+        source: git2::Error,
+    },
+
+    // `--fetch`, the tarball or its checksums could not be downloaded
+    #[cfg(feature = "fetch")]
+    #[error("failed to download {url}")]
+    Download {
+        url: String,
+        #[source]
+        source: Box<ureq::Error>,
+    },
+
+    #[cfg(feature = "fetch")]
+    #[error("{name} is not in the cache at {}, run without --offline to download it", cache_dir.display())]
+    NotCached { name: String, cache_dir: PathBuf },
+
+    // the download is removed again, the next fetch starts over
+    #[cfg(feature = "fetch")]
+    #[error("{} has the wrong {what}: expected {expected}, found {found}", path.display())]
+    Corrupted {
+        path: PathBuf,
+        what: &'static str,
+        expected: String,
+        found: String,
+    },
+
+    #[cfg(feature = "cli")]
+    #[error("{0} needs an interactive terminal, use --query or the prompt instead")]
+    NotATerminal(&'static str),
+
+    #[cfg(feature = "cli")]
+    #[error("failed to draw on the terminal")]
+    Terminal(#[source] io::Error),
+
+    #[cfg(feature = "cli")]
+    #[error("failed to read from the prompt")]
+    Prompt(#[from] rustyline::error::ReadlineError),
+
+    // `--log-file` or `--log-filter` could not be applied
+    #[cfg(feature = "cli")]
+    #[error("{message}")]
+    Logging {
+        message: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // for `map_err`, e.g. `File::open(path).map_err(Error::io(path))?`
+    pub fn io(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn write(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |source| Error::Write {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn json(path: &Path) -> impl FnOnce(serde_json::Error) -> Error + '_ {
+        move |source| Error::Json {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
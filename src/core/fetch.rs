@@ -0,0 +1,239 @@
+//! Release tarballs from kernel.org, downloaded once and unpacked into a cache directory.
+//!
+//! `6.9.5` is looked up as `v6.x/linux-6.9.5.tar.xz` below [`CDN`] and unpacked to
+//! `<cache>/linux-6.9.5`; a later fetch of the same version reuses that tree.
+
+use crate::core::error::{Error, Result};
+use crate::core::version::KernelVersion;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use xz2::read::XzDecoder;
+
+pub const CDN: &str = "https://cdn.kernel.org/pub/linux/kernel";
+
+// `$XDG_CACHE_HOME/auto_script`, falling back to `~/.cache/auto_script`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache.join("auto_script"))
+}
+
+pub struct Fetcher {
+    cache_dir: PathBuf,
+    base_url: String,
+    offline: bool,
+}
+
+impl Fetcher {
+    pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+        Fetcher {
+            cache_dir: cache_dir.into(),
+            base_url: CDN.to_string(),
+            offline: false,
+        }
+    }
+
+    // a mirror laid out like `CDN`
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    // only what is already in the cache, never the network
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// The unpacked tree of `version`, downloaded and unpacked first unless the cache has it.
+    ///
+    /// The size the server announces is always checked, the sha256 only when
+    /// `sha256sums.asc` of the release series lists the tarball. A tarball already in the
+    /// cache is checked again before it is unpacked.
+    pub fn fetch(&self, version: &KernelVersion) -> Result<PathBuf> {
+        let name = version.tarball_dir();
+        let tree = self.cache_dir.join(&name);
+        if tree.join("Makefile").is_file() {
+            info!("reuse cached Linux-{} at {:?}", version.plain(), tree);
+            return Ok(tree);
+        }
+
+        let tarball = self.cache_dir.join(format!("{}.tar.xz", name));
+        if tarball.is_file() {
+            self.verify_cached(version, &tarball)?;
+        } else {
+            if self.offline {
+                return Err(Error::NotCached {
+                    name,
+                    cache_dir: self.cache_dir.clone(),
+                });
+            }
+            fs::create_dir_all(&self.cache_dir).map_err(Error::write(&self.cache_dir))?;
+            self.download(version, &tarball)?;
+        }
+        self.unpack(&tarball, &name)?;
+        Ok(tree)
+    }
+
+    fn series_url(&self, version: &KernelVersion) -> String {
+        format!("{}/v{}.x", self.base_url, version.major)
+    }
+
+    // written next to the final name first, an interrupted download never looks complete
+    fn download(&self, version: &KernelVersion, tarball: &Path) -> Result<()> {
+        let file_name = tarball.file_name().unwrap_or_default().to_string_lossy();
+        let url = format!("{}/{}", self.series_url(version), file_name);
+        info!("download {}", url);
+        let response = get(&url)?;
+        let expected_len: Option<u64> = response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok());
+
+        let partial = tarball.with_extension("xz.part");
+        let mut out = File::create(&partial).map_err(Error::write(&partial))?;
+        let mut reader = response.into_reader();
+        let mut hasher = Sha256::new();
+        let mut len = 0u64;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buf).map_err(|source| Error::Download {
+                url: url.clone(),
+                source: Box::new(source.into()),
+            })?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+            out.write_all(&buf[..read])
+                .map_err(Error::write(&partial))?;
+            len += read as u64;
+        }
+        drop(out);
+        let digest = hex(&hasher.finalize());
+
+        let checks = [
+            (
+                "size",
+                expected_len.map(|len| len.to_string()),
+                len.to_string(),
+            ),
+            (
+                "sha256",
+                self.expected_sha256(version, &file_name),
+                digest.clone(),
+            ),
+        ];
+        let mut verified = false;
+        for (what, expected, found) in checks {
+            match expected {
+                Some(expected) if expected != found => {
+                    let _ = fs::remove_file(&partial);
+                    return Err(Error::Corrupted {
+                        path: tarball.to_path_buf(),
+                        what,
+                        expected,
+                        found,
+                    });
+                }
+                Some(_) => verified |= what == "sha256",
+                None => warn!("no {} announced for {}, not verified", what, url),
+            }
+        }
+        // a later run trusts the record, so only a digest the release sums confirmed is kept
+        if verified {
+            let record = digest_record(tarball);
+            fs::write(&record, format!("{}\n", digest)).map_err(Error::write(&record))?;
+        }
+        fs::rename(&partial, tarball).map_err(Error::write(tarball))
+    }
+
+    /// Checks the sha256 of a tarball left in the cache by an earlier run.
+    ///
+    /// The digest `download` verified and recorded next to it is used, the sums of the release series
+    /// when there is none; offline and without a record the tarball is unpacked unverified.
+    /// A mismatching tarball is removed so the next fetch downloads it again.
+    fn verify_cached(&self, version: &KernelVersion, tarball: &Path) -> Result<()> {
+        let record = digest_record(tarball);
+        let expected = match fs::read_to_string(&record) {
+            Ok(digest) => Some(digest.trim().to_lowercase()),
+            Err(_) if self.offline => None,
+            Err(_) => {
+                let file_name = tarball.file_name().unwrap_or_default().to_string_lossy();
+                self.expected_sha256(version, &file_name)
+            }
+        };
+        let Some(expected) = expected else {
+            warn!("no sha256 recorded for {:?}, not verified", tarball);
+            return Ok(());
+        };
+        let mut file = File::open(tarball).map_err(Error::io(tarball))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).map_err(Error::io(tarball))?;
+        let found = hex(&hasher.finalize());
+        if expected != found {
+            let _ = fs::remove_file(tarball);
+            let _ = fs::remove_file(&record);
+            return Err(Error::Corrupted {
+                path: tarball.to_path_buf(),
+                what: "sha256",
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    // `sha256sums.asc` is clearsigned, the digest lines sit between the PGP header and signature
+    fn expected_sha256(&self, version: &KernelVersion, file_name: &str) -> Option<String> {
+        let url = format!("{}/sha256sums.asc", self.series_url(version));
+        let reader = BufReader::new(get(&url).ok()?.into_reader());
+        reader.lines().map_while(|line| line.ok()).find_map(|line| {
+            let (digest, name) = line.split_once(char::is_whitespace)?;
+            (name.trim() == file_name).then(|| digest.to_lowercase())
+        })
+    }
+
+    // unpacked into a staging directory and moved into place once complete
+    fn unpack(&self, tarball: &Path, name: &str) -> Result<()> {
+        info!("unpack {:?}", tarball);
+        let staging = self.cache_dir.join(format!(".{}.unpack", name));
+        let _ = fs::remove_dir_all(&staging);
+        let file = File::open(tarball).map_err(Error::io(tarball))?;
+        tar::Archive::new(XzDecoder::new(BufReader::new(file)))
+            .unpack(&staging)
+            .map_err(Error::io(tarball))?;
+
+        // kernel.org tarballs hold a single `linux-x.y.z` directory
+        let unpacked = staging.join(name);
+        let unpacked = if unpacked.is_dir() {
+            unpacked
+        } else {
+            staging.clone()
+        };
+        let tree = self.cache_dir.join(name);
+        fs::rename(&unpacked, &tree).map_err(Error::write(&tree))?;
+        let _ = fs::remove_dir_all(&staging);
+        Ok(())
+    }
+}
+
+fn get(url: &str) -> Result<ureq::Response> {
+    ureq::get(url).call().map_err(|source| Error::Download {
+        url: url.to_string(),
+        source: Box::new(source),
+    })
+}
+
+// `linux-6.9.5.tar.xz.sha256`, the digest of the tarball as downloaded
+fn digest_record(tarball: &Path) -> PathBuf {
+    tarball.with_extension("xz.sha256")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
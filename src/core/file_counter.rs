@@ -1,222 +1,1304 @@
-use log::{error, warn};
-use std::collections::HashMap;
-use std::io::BufRead;
-use std::path::PathBuf;
-use std::{fs, io};
-
-#[derive(Eq, Hash, PartialEq, Debug)]
-enum FileType {
+use crate::core::cache::ScanCache;
+use crate::core::error::{Error, Result};
+use crate::core::progress::PhaseCounts;
+use crate::core::report::{
+    AnalysisResult, CodeSummary, FileHealthReport, HeaderGuardReport, LanguageDeltaReport,
+    LanguageDeltaRow, LanguageRow, MarkerRow, Renderer, SpdxReport, TodoReport, WholeTreeReport,
+};
+use crate::core::source::{read_dir, FsTree, SourceTree};
+use crate::core::utils::{display_path, strip_bom};
+use crate::core::version::KernelVersion;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, Read};
+use std::path::{Display, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64;
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FileType {
     TypeC,
     TypeH,
+    TypeCpp,
+    TypeHpp,
     TypeM,
     TypeK,
     TypeRust,
     TypeAsm,
     TypePython,
+    TypeShell,
+    TypePerl,
+    TypeAwk,
+    // `.rst`, `.txt` and `.md` prose, every non-blank line is text
+    TypeDoc,
     TypeOther,
+    // a NUL byte in the first chunk, only files and bytes are counted
+    TypeBinary,
+    // a language named by `--lang-map`, see `FileCounter::language`
+    TypeCustom(u16),
 }
 
 impl FileType {
+    const BUILTIN: [FileType; 15] = [
+        FileType::TypeC,
+        FileType::TypeH,
+        FileType::TypeCpp,
+        FileType::TypeHpp,
+        FileType::TypeM,
+        FileType::TypeK,
+        FileType::TypeRust,
+        FileType::TypeAsm,
+        FileType::TypePython,
+        FileType::TypeShell,
+        FileType::TypePerl,
+        FileType::TypeAwk,
+        FileType::TypeDoc,
+        FileType::TypeOther,
+        FileType::TypeBinary,
+    ];
+
     fn from_extension(extension: &str) -> Self {
         match extension {
-            "c" | "cpp" | "cc" => FileType::TypeC,
-            "h" | "hpp" => FileType::TypeH,
+            "c" => FileType::TypeC,
+            "h" => FileType::TypeH,
+            "cpp" | "cc" | "cxx" => FileType::TypeCpp,
+            "hpp" | "hh" | "hxx" => FileType::TypeHpp,
             "rs" => FileType::TypeRust,
             "S" | "s" | "asm" => FileType::TypeAsm,
             "py" => FileType::TypePython,
+            "sh" => FileType::TypeShell,
+            "pl" | "pm" => FileType::TypePerl,
+            "awk" => FileType::TypeAwk,
+            "rst" | "txt" | "md" => FileType::TypeDoc,
             _ => FileType::TypeOther,
         }
     }
 
+    // `#!/bin/sh`, `#!/usr/bin/env python3`, `#!/usr/bin/awk -f`
+    fn from_shebang(line: &str) -> Option<Self> {
+        let mut words = line.strip_prefix("#!")?.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+        if interpreter == "env" {
+            interpreter = words.find(|word| !word.starts_with('-'))?;
+        }
+
+        match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+            "sh" | "bash" | "dash" | "ksh" | "zsh" => Some(FileType::TypeShell),
+            "python" => Some(FileType::TypePython),
+            "perl" => Some(FileType::TypePerl),
+            "awk" | "gawk" | "mawk" | "nawk" => Some(FileType::TypeAwk),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            FileType::TypeC => "C",
+            FileType::TypeH => "C Header",
+            FileType::TypeCpp => "C++",
+            FileType::TypeHpp => "C++ Header",
+            FileType::TypeRust => "Rust",
+            FileType::TypeAsm => "Assembly",
+            FileType::TypePython => "Python",
+            FileType::TypeShell => "Shell",
+            FileType::TypePerl => "Perl",
+            FileType::TypeAwk => "Awk",
+            FileType::TypeM => "Makefile",
+            FileType::TypeK => "kconfig",
+            FileType::TypeDoc => "Documentation",
+            FileType::TypeOther | FileType::TypeCustom(_) => "Other",
+            FileType::TypeBinary => "Binary",
+        }
+    }
+
+    fn comment_syntax(&self) -> CommentSyntax {
+        match self {
+            FileType::TypeC
+            | FileType::TypeH
+            | FileType::TypeCpp
+            | FileType::TypeHpp
+            | FileType::TypeRust => CommentSyntax {
+                line: &["//"],
+                block: Some(("/*", "*/")),
+            },
+            // kernel `.S` files go through cpp, so C comments are allowed as well
+            FileType::TypeAsm => CommentSyntax {
+                line: &["//", "#", ";"],
+                block: Some(("/*", "*/")),
+            },
+            FileType::TypePython
+            | FileType::TypeShell
+            | FileType::TypePerl
+            | FileType::TypeAwk
+            | FileType::TypeM
+            | FileType::TypeK => CommentSyntax {
+                line: &["#"],
+                block: None,
+            },
+            // prose has no comments, its text lines land in the code column
+            FileType::TypeDoc => CommentSyntax {
+                line: &[],
+                block: None,
+            },
+            // binaries are never read line by line
+            FileType::TypeOther | FileType::TypeBinary | FileType::TypeCustom(_) => CommentSyntax {
+                line: &["//", "#", ";"],
+                block: Some(("/*", "*/")),
+            },
+        }
+    }
+
     fn from_filename(filename: &str) -> Self {
         match filename {
-            "Makfile" => FileType::TypeM,
+            "Makefile" => FileType::TypeM,
             "Kconfig" => FileType::TypeK,
             _ => FileType::TypeOther,
         }
     }
 }
 
-#[derive(Default)]
-struct FileStat {
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+impl CommentSyntax {
+    fn is_line_comment(&self, line: &str) -> bool {
+        self.line.iter().any(|marker| line.starts_with(marker))
+            || self.block.is_some_and(|(open, _)| line.starts_with(open))
+    }
+
+    // whether a block comment is still open once `line` ends
+    fn in_block_after(&self, line: &str, mut in_block: bool) -> bool {
+        let Some((open, close)) = self.block else {
+            return false;
+        };
+        if !in_block && self.line.iter().any(|marker| line.starts_with(marker)) {
+            return false;
+        }
+        let mut rest = line;
+        loop {
+            let (marker, next) = if in_block {
+                (close, false)
+            } else {
+                (open, true)
+            };
+            match rest.find(marker) {
+                Some(index) => {
+                    rest = &rest[index + marker.len()..];
+                    in_block = next;
+                }
+                None => return in_block,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileStat {
     files: usize,
     blank: usize,
     comment: usize,
     code: usize,
+    bytes: u64,
+    max_code: usize,
+    max_path: Option<PathBuf>,
+    markers: Markers,
+    // files without code lines, split by whether they hold any comment
+    empty_files: usize,
+    comment_only_files: usize,
+}
+
+impl FileStat {
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    pub fn blank(&self) -> usize {
+        self.blank
+    }
+
+    pub fn comment(&self) -> usize {
+        self.comment
+    }
+
+    pub fn code(&self) -> usize {
+        self.code
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    // code lines of the largest file
+    pub fn max_code(&self) -> usize {
+        self.max_code
+    }
+
+    pub fn max_path(&self) -> Option<&PathBuf> {
+        self.max_path.as_ref()
+    }
+
+    // files with neither code nor comment lines, blank lines only or no content at all
+    pub fn empty_files(&self) -> usize {
+        self.empty_files
+    }
+
+    // files with comment lines but no code lines
+    pub fn comment_only_files(&self) -> usize {
+        self.comment_only_files
+    }
+
+    fn add(&mut self, other: &FileStat) {
+        self.files += other.files;
+        self.blank += other.blank;
+        self.comment += other.comment;
+        self.code += other.code;
+        self.bytes += other.bytes;
+        self.markers.add(&other.markers);
+        self.empty_files += other.empty_files;
+        self.comment_only_files += other.comment_only_files;
+        if other.max_path.is_some() && (self.max_path.is_none() || other.max_code > self.max_code) {
+            self.max_code = other.max_code;
+            self.max_path = other.max_path.clone();
+        }
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.files == 0 {
+            0.0
+        } else {
+            self.code as f64 / self.files as f64
+        }
+    }
+
+    fn export(&self) -> StatExport {
+        StatExport {
+            files: self.files,
+            blank: self.blank,
+            comment: self.comment,
+            code: self.code,
+            bytes: self.bytes,
+            average: self.average(),
+            max_code: self.max_code,
+            max_path: self.max_path.clone(),
+            markers: self.markers,
+        }
+    }
+}
+
+// the columns of one row of `count --format json`
+#[derive(Serialize)]
+pub struct StatExport {
+    pub files: usize,
+    pub blank: usize,
+    pub comment: usize,
+    pub code: usize,
+    pub bytes: u64,
+    // code lines per file
+    pub average: f64,
+    // code lines of the largest file and where it is
+    pub max_code: usize,
+    pub max_path: Option<PathBuf>,
+    // TODO/FIXME/XXX in comments
+    pub markers: Markers,
+}
+
+#[derive(Serialize)]
+pub struct LanguageExport {
+    pub language: String,
+    #[serde(flatten)]
+    pub stat: StatExport,
+}
+
+// one counter of `count --format json`, see `FileCounter::export`
+#[derive(Serialize)]
+pub struct CodeExport {
+    pub arch: String,
+    pub version: String,
+    // ordered like the text table, by code lines (descending), then by name
+    pub languages: Vec<LanguageExport>,
+    pub total: StatExport,
+    // files per SPDX license expression, and files without an identifier
+    pub spdx: BTreeMap<String, usize>,
+    pub spdx_missing: usize,
+    // the files with the most markers, only with `--todos`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo_files: Option<Vec<MarkerFile>>,
+}
+
+#[derive(Serialize)]
+pub struct MarkerFile {
+    pub path: PathBuf,
+    pub markers: usize,
+}
+
+// the document `count --format json` writes
+#[derive(Serialize)]
+pub struct CountReport {
+    pub version: String,
+    pub arches: Vec<CodeExport>,
+    // every arch merged, only when more than one arch was counted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_arches: Option<CodeExport>,
+    // the tree outside the arch directories, only with `--whole-tree`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub common: Option<CodeExport>,
+    // `Documentation/`, only with `--include-docs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<CodeExport>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileLines {
+    blank: usize,
+    comment: usize,
+    code: usize,
+    spdx: Option<String>,
+    shebang: Option<FileType>,
+    markers: Markers,
+    header_guard: Option<HeaderGuard>,
+}
+
+impl FileLines {
+    pub fn blank(&self) -> usize {
+        self.blank
+    }
+
+    pub fn comment(&self) -> usize {
+        self.comment
+    }
+
+    pub fn code(&self) -> usize {
+        self.code
+    }
+
+    pub fn spdx(&self) -> Option<&str> {
+        self.spdx.as_deref()
+    }
+
+    pub fn shebang(&self) -> Option<FileType> {
+        self.shebang
+    }
+
+    /// Counts the lines read from `reader` with the comment syntax of `file_type`.
+    ///
+    /// ```
+    /// use auto_script::core::file_counter::FileLines;
+    /// use auto_script::FileType;
+    ///
+    /// let source = "// SPDX-License-Identifier: GPL-2.0\n\nint smp;\n";
+    /// let lines = FileLines::count(source.as_bytes(), FileType::TypeC).unwrap();
+    /// assert_eq!((lines.blank(), lines.comment(), lines.code()), (1, 1, 1));
+    /// assert_eq!(lines.spdx(), Some("GPL-2.0"));
+    /// ```
+    pub fn count(reader: impl BufRead, file_type: FileType) -> io::Result<FileLines> {
+        let mut lines = FileLines::default();
+        let mut syntax = file_type.comment_syntax();
+        let mut in_block = false;
+        let mut first_code = Vec::new();
+        let mut last_code = None;
+
+        // kernel sources are not guaranteed to be UTF-8, so read raw lines
+        for (index, line) in reader.split(b'\n').enumerate() {
+            let line = line?;
+            let line = String::from_utf8_lossy(&line);
+            let line = if index == 0 { strip_bom(&line) } else { &line };
+            let trimmed = line.trim();
+            if index == 0 {
+                lines.shebang = FileType::from_shebang(trimmed);
+                // an extensionless script is counted with its interpreter's syntax
+                if let (FileType::TypeOther, Some(shebang)) = (file_type, lines.shebang) {
+                    syntax = shebang.comment_syntax();
+                }
+            }
+            if index < SPDX_SCAN_LINES && lines.spdx.is_none() {
+                lines.spdx = parse_spdx(trimmed);
+            }
+
+            if trimmed.is_empty() {
+                lines.blank += 1;
+            } else if in_block || syntax.is_line_comment(trimmed) {
+                lines.comment += 1;
+                lines.markers.add(&Markers::scan(trimmed));
+            } else {
+                lines.code += 1;
+                if file_type == FileType::TypeH {
+                    if first_code.len() < 2 {
+                        first_code.push(trimmed.to_string());
+                    }
+                    last_code = Some(trimmed.to_string());
+                }
+            }
+            in_block = syntax.in_block_after(trimmed, in_block);
+        }
+
+        if file_type == FileType::TypeH {
+            lines.header_guard = Some(HeaderGuard::classify(&first_code, last_code.as_deref()));
+        }
+        Ok(lines)
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum HeaderGuard {
+    PragmaOnce,
+    IncludeGuard,
+    // neither, the header may be included twice
+    Unguarded,
+}
+
+// `#  ifndef FOO` is as valid as `#ifndef FOO`
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('#')?.trim_start();
+    let (name, argument) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((name, argument.trim()))
+}
+
+impl HeaderGuard {
+    // decided by the first two and the last code lines of the header
+    fn classify(first: &[String], last: Option<&str>) -> Self {
+        let first_directive = first.first().and_then(|line| directive(line));
+        if first_directive == Some(("pragma", "once")) {
+            return HeaderGuard::PragmaOnce;
+        }
+
+        let guard = match first_directive {
+            Some(("ifndef", guard)) => Some(guard),
+            Some(("if", condition)) => condition.strip_prefix("!defined").map(|guard| {
+                guard
+                    .trim()
+                    .trim_start_matches('(')
+                    .trim_end_matches(')')
+                    .trim()
+            }),
+            _ => None,
+        };
+        let defined = first
+            .get(1)
+            .and_then(|line| directive(line))
+            .and_then(|(name, argument)| (name == "define").then_some(argument));
+        let closed = last
+            .and_then(directive)
+            .is_some_and(|(name, _)| name == "endif");
+        match (guard, defined) {
+            (Some(guard), Some(defined))
+                if closed && defined.split_whitespace().next() == Some(guard) =>
+            {
+                HeaderGuard::IncludeGuard
+            }
+            _ => HeaderGuard::Unguarded,
+        }
+    }
+}
+
+#[derive(Default)]
+struct HeaderGuards {
+    pragma_once: usize,
+    include_guard: usize,
+    unguarded: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Markers {
+    todo: usize,
+    fixme: usize,
+    xxx: usize,
+}
+
+impl Markers {
+    fn add(&mut self, other: &Markers) {
+        self.todo += other.todo;
+        self.fixme += other.fixme;
+        self.xxx += other.xxx;
+    }
+
+    fn total(&self) -> usize {
+        self.todo + self.fixme + self.xxx
+    }
+
+    // only whole words count, so `TODOS` or `MY_TODO_LIST` are ignored
+    fn scan(line: &str) -> Self {
+        let mut markers = Markers::default();
+        let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let words = line.split(|c: char| !is_ident(c));
+        for word in words {
+            match word {
+                "TODO" => markers.todo += 1,
+                "FIXME" => markers.fixme += 1,
+                "XXX" => markers.xxx += 1,
+                _ => {}
+            }
+        }
+        markers
+    }
+}
+
+// the SPDX tag sits on the first line (second one for scripts with a shebang)
+const SPDX_SCAN_LINES: usize = 5;
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+
+fn parse_spdx(line: &str) -> Option<String> {
+    let start = line.find(SPDX_TAG)? + SPDX_TAG.len();
+    let mut expr = line[start..].trim();
+    expr = expr.strip_suffix("*/").unwrap_or(expr).trim();
+    while expr.starts_with('(') && closing_paren(expr) == Some(expr.len() - 1) {
+        expr = expr[1..expr.len() - 1].trim();
+    }
+    if expr.is_empty() {
+        return None;
+    }
+
+    let normalized: Vec<String> = expr
+        .split_whitespace()
+        .map(|token| match token.to_lowercase().as_str() {
+            "or" | "and" | "with" => token.to_uppercase(),
+            _ => token.to_string(),
+        })
+        .collect();
+    Some(normalized.join(" "))
+}
+
+// the index of the `)` matching the `(` that opens `expr`
+fn closing_paren(expr: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (index, c) in expr.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[derive(Serialize)]
+pub struct ScanFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Default, Serialize)]
+pub struct ScanReport {
+    // only used to tag the log records of each failure
+    #[serde(skip)]
+    arch: String,
+    pub unreadable_dirs: Vec<ScanFailure>,
+    pub unreadable_files: Vec<ScanFailure>,
+    // the scan was stopped before it walked the whole directory
+    pub interrupted: bool,
+}
+
+impl ScanReport {
+    pub fn is_empty(&self) -> bool {
+        self.unreadable_dirs.is_empty() && self.unreadable_files.is_empty()
+    }
+
+    fn dir_failed(&mut self, path: PathBuf, err: &io::Error) {
+        error!(arch = self.arch.as_str(); "{:?} dir error: {}", path, err);
+        self.unreadable_dirs.push(ScanFailure {
+            path,
+            reason: err.to_string(),
+        });
+    }
+
+    fn file_failed(&mut self, path: PathBuf, err: &io::Error) {
+        error!(arch = self.arch.as_str(); "{:?} file error: {}", path, err);
+        self.unreadable_files.push(ScanFailure {
+            path,
+            reason: err.to_string(),
+        });
+    }
 }
 
 pub struct FileCounter {
     arch: String,
-    version: String,
+    version: KernelVersion,
     dir_path: PathBuf,
     file_count: HashMap<FileType, FileStat>,
+    spdx: HashMap<String, usize>,
+    spdx_missing: usize,
+    exclude: Vec<PathBuf>,
+    verbose: bool,
+    sorted: bool,
+    cache: Option<ScanCache>,
+    cache_enabled: bool,
+    cache_hits: usize,
+    // files opened for counting or hashing, cache hits that are not hashed are never opened
+    files_read: usize,
+    count_duplicates: bool,
+    seen_inodes: HashSet<(u64, u64)>,
+    hardlinks: usize,
+    dedup_by_hash: bool,
+    content_hashes: HashMap<u64, usize>,
+    duplicate_files: usize,
+    duplicate_lines: usize,
+    marker_files: Vec<(PathBuf, usize)>,
+    header_guards: HeaderGuards,
+    // extension (without the dot) -> language, consulted before the built-in rules
+    lang_map: HashMap<String, FileType>,
+    lang_names: Vec<String>,
+    // per-file messages are only counted, see `set_summarize`
+    summarize: bool,
+    progress: PhaseCounts,
+    // printed paths are shown below this root, see `set_relative_to`
+    relative_to: Option<PathBuf>,
+    tree: Rc<dyn SourceTree>,
+    // raised from outside, e.g. by Ctrl-C, to end `search` early
+    stop: Arc<AtomicBool>,
 }
 
 impl FileCounter {
-    pub fn new(arch: String, version: String, dir_path: PathBuf) -> Self {
+    pub fn new(arch: String, version: KernelVersion, dir_path: PathBuf) -> Self {
         FileCounter {
             arch,
             version,
             dir_path,
             file_count: HashMap::new(),
+            spdx: HashMap::new(),
+            spdx_missing: 0,
+            exclude: Vec::new(),
+            verbose: false,
+            sorted: false,
+            cache: None,
+            cache_enabled: false,
+            cache_hits: 0,
+            files_read: 0,
+            count_duplicates: false,
+            seen_inodes: HashSet::new(),
+            hardlinks: 0,
+            dedup_by_hash: false,
+            content_hashes: HashMap::new(),
+            duplicate_files: 0,
+            duplicate_lines: 0,
+            marker_files: Vec::new(),
+            header_guards: HeaderGuards::default(),
+            lang_map: HashMap::new(),
+            lang_names: Vec::new(),
+            summarize: false,
+            progress: PhaseCounts::default(),
+            relative_to: None,
+            tree: Rc::new(FsTree),
+            stop: Arc::default(),
         }
     }
 
-    pub fn search(&mut self) {
-        let _ = self.search_dir(&self.dir_path.clone());
-    }
-
-    pub fn search_dir(&mut self, path: &PathBuf) -> io::Result<()> {
-        warn!("start to seach dir -> {:?}", path);
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries {
-                match entry {
-                    Ok(entry) => {
-                        let path = entry.path();
-                        if path.is_dir() {
-                            let _ = self.search_dir(&path);
-                        } else if let Some(file_name) = path.file_name() {
-                            let file_name_str = file_name.to_string_lossy();
-                            let file_type = if file_name_str == "Makefile" {
-                                FileType::TypeM
-                            } else if file_name_str == "Kconfig" {
-                                FileType::TypeK
-                            } else if let Some(extension) = path.extension() {
-                                FileType::from_extension(extension.to_str().unwrap_or(""))
-                            } else {
-                                FileType::TypeOther
-                            };
-
-                            let stats = self.file_count.entry(file_type).or_default();
-                            stats.files += 1;
-
-                            let file = fs::File::open(path)?;
-                            let reader = io::BufReader::new(file);
-
-                            let mut blank = 0;
-                            let mut comment = 0;
-                            let mut code = 0;
-
-                            for line in reader.lines() {
-                                let line = line?;
-                                let trimmed = line.trim();
-                                if trimmed.is_empty() {
-                                    blank += 1;
-                                } else if trimmed.starts_with("//")
-                                    || trimmed.starts_with("/*")
-                                    || trimmed.starts_with('*')
-                                    || trimmed.starts_with('#')
-                                    || trimmed.starts_with(';')
+    pub fn set_verbose(&mut self) {
+        self.verbose = true;
+    }
+
+    pub fn set_sorted(&mut self) {
+        self.sorted = true;
+    }
+
+    pub fn set_summarize(&mut self) {
+        self.summarize = true;
+    }
+
+    // a built-in language name maps onto its own type, anything else becomes a new language
+    pub fn set_lang_map(&mut self, mapping: &[(String, String)]) {
+        for (extension, language) in mapping {
+            let file_type = self.language_type(language);
+            self.lang_map.insert(extension.clone(), file_type);
+        }
+    }
+
+    fn language_type(&mut self, language: &str) -> FileType {
+        if let Some(file_type) = FileType::BUILTIN
+            .iter()
+            .find(|file_type| file_type.name().eq_ignore_ascii_case(language))
+        {
+            return *file_type;
+        }
+        let index = match self.lang_names.iter().position(|name| name == language) {
+            Some(index) => index,
+            None => {
+                self.lang_names.push(language.to_string());
+                self.lang_names.len() - 1
+            }
+        };
+        FileType::TypeCustom(index as u16)
+    }
+
+    /// Display name of `file_type`, including the languages added by `set_lang_map`.
+    pub fn language(&self, file_type: FileType) -> &str {
+        match file_type {
+            FileType::TypeCustom(index) => self
+                .lang_names
+                .get(index as usize)
+                .map_or("Other", String::as_str),
+            file_type => file_type.name(),
+        }
+    }
+
+    fn stats_of(&self, language: &str) -> Option<&FileStat> {
+        self.file_count
+            .iter()
+            .find(|(file_type, _)| self.language(**file_type) == language)
+            .map(|(_, stats)| stats)
+    }
+
+    fn mapped_type(&self, path: &Path) -> Option<FileType> {
+        let extension = path.extension()?.to_str()?;
+        self.lang_map.get(extension).copied()
+    }
+
+    /// Per-language statistics gathered by `search`.
+    ///
+    /// ```
+    /// use auto_script::{FileCounter, FileType};
+    /// use std::fs;
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// fs::write(dir.path().join("smp.c"), "// smp\nint smp;\n").unwrap();
+    ///
+    /// let mut fc = FileCounter::new("riscv".into(), "6.9.5".into(), dir.path().into());
+    /// let report = fc.search().unwrap();
+    /// assert!(report.is_empty());
+    /// let c = &fc.stats()[&FileType::TypeC];
+    /// assert_eq!((c.files(), c.comment(), c.code()), (1, 1, 1));
+    /// ```
+    pub fn stats(&self) -> &HashMap<FileType, FileStat> {
+        &self.file_count
+    }
+
+    pub fn set_cache(&mut self, cache: ScanCache) {
+        self.cache = Some(cache);
+        self.cache_enabled = true;
+    }
+
+    pub fn take_cache(&mut self) -> Option<ScanCache> {
+        self.cache.take()
+    }
+
+    // how many files were opened, a fully cached scan without `dedup_by_hash` opens none
+    pub fn files_read(&self) -> usize {
+        self.files_read
+    }
+
+    pub fn set_count_duplicates(&mut self) {
+        self.count_duplicates = true;
+    }
+
+    pub fn set_dedup_by_hash(&mut self) {
+        self.dedup_by_hash = true;
+    }
+
+    // only the printed paths change, the counted ones stay absolute
+    pub fn set_relative_to(&mut self, root: PathBuf) {
+        self.relative_to = Some(root);
+    }
+
+    fn display_path<'a>(&self, path: &'a Path) -> Display<'a> {
+        display_path(path, self.relative_to.as_deref())
+    }
+
+    // where `dir_path` and everything below it is read from, the disk by default
+    pub fn set_source_tree(&mut self, tree: Rc<dyn SourceTree>) {
+        self.tree = tree;
+    }
+
+    pub fn exclude(&mut self, path: PathBuf) {
+        self.exclude.push(path);
+    }
+
+    // once `stop` is raised `search` counts no further file, every counted one is complete
+    pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    // unreadable entries below the root are collected in the report, only the root is fatal
+    pub fn search(&mut self) -> Result<ScanReport> {
+        if !self.tree.is_dir(&self.dir_path) {
+            return Err(Error::ArchNotFound {
+                arch: self.arch.clone(),
+                path: self.dir_path.clone(),
+            });
+        }
+        let mut report = ScanReport {
+            arch: self.arch.clone(),
+            ..Default::default()
+        };
+        let root = self.dir_path.clone();
+        self.search_dir(&root, &mut report)
+            .map_err(Error::io(&root))?;
+        info!(arch = self.arch.as_str(); "{}", self.progress);
+        Ok(report)
+    }
+
+    fn search_dir(&mut self, path: &PathBuf, report: &mut ScanReport) -> io::Result<()> {
+        if !self.summarize {
+            debug!(arch = self.arch.as_str(); "start to search dir -> {:?}", path);
+        }
+        let tree = self.tree.clone();
+        for entry in read_dir(tree.as_ref(), path, self.sorted)? {
+            if self.stop.load(Ordering::Relaxed) {
+                report.interrupted = true;
+                return Ok(());
+            }
+            let path = entry.path;
+            if self.exclude.contains(&path) {
+                if !self.summarize {
+                    info!(arch = self.arch.as_str(); "skip excluded path -> {:?}", path);
+                }
+                self.progress.skipped += 1;
+            } else if entry.is_dir {
+                if let Err(err) = self.search_dir(&path, report) {
+                    report.dir_failed(path, &err);
+                }
+            } else if path.file_name().is_some() {
+                let bytes = entry.len;
+
+                if let (false, Some(inode)) = (self.count_duplicates, entry.inode) {
+                    if !self.seen_inodes.insert(inode) {
+                        if !self.summarize {
+                            debug!(arch = self.arch.as_str(); "skip hardlinked file -> {:?}", path);
+                        }
+                        self.hardlinks += 1;
+                        self.progress.skipped += 1;
+                        continue;
+                    }
+                }
+
+                // mapped languages depend on the command line, keep them out of the cache
+                let mapped = self.mapped_type(&path).is_some();
+                let cached = self
+                    .cache
+                    .as_ref()
+                    .filter(|_| !mapped)
+                    .zip(entry.mtime)
+                    .and_then(|(cache, mtime)| cache.get(&path, mtime, bytes))
+                    .cloned();
+                let (file_type, lines) = match cached {
+                    Some(entry) => {
+                        self.cache_hits += 1;
+                        (entry.file_type, entry.lines)
+                    }
+                    None => {
+                        self.files_read += 1;
+                        match self.classify(&path) {
+                            Ok((file_type, lines)) => {
+                                if let (false, Some(cache), Some(mtime)) =
+                                    (mapped, self.cache.as_mut(), entry.mtime)
                                 {
-                                    comment += 1;
-                                } else {
-                                    code += 1;
+                                    cache.insert(
+                                        path.clone(),
+                                        mtime,
+                                        bytes,
+                                        file_type,
+                                        lines.clone(),
+                                    );
                                 }
+                                (file_type, lines)
+                            }
+                            Err(err) => {
+                                report.file_failed(path, &err);
+                                self.progress.skipped += 1;
+                                continue;
                             }
-
-                            // let (blank, comment, code) = self.count_lines(&path).unwrap_or((0, 0, 0));
-
-                            stats.blank += blank;
-                            stats.comment += comment;
-                            stats.code += code;
                         }
                     }
-                    Err(err) => {
-                        error!("{:?} dir error", path);
+                };
+
+                if self.dedup_by_hash {
+                    self.files_read += 1;
+                    let mut content = Vec::new();
+                    match tree
+                        .read(&path)
+                        .and_then(|mut reader| reader.read_to_end(&mut content))
+                    {
+                        Ok(_) => {
+                            let total_lines = lines.blank + lines.comment + lines.code;
+                            self.record_content(xxh3_64(&content), total_lines);
+                        }
+                        Err(err) => {
+                            error!(arch = self.arch.as_str(); "{} file error: {}", self.display_path(&path), err)
+                        }
                     }
                 }
+
+                self.progress.code_files += 1;
+                let binary = file_type == FileType::TypeBinary;
+                let stats = self.file_count.entry(file_type).or_default();
+                stats.add(&FileStat {
+                    files: 1,
+                    blank: lines.blank,
+                    comment: lines.comment,
+                    code: lines.code,
+                    bytes,
+                    max_code: lines.code,
+                    max_path: Some(path.clone()),
+                    markers: lines.markers,
+                    empty_files: usize::from(!binary && lines.code == 0 && lines.comment == 0),
+                    comment_only_files: usize::from(lines.code == 0 && lines.comment > 0),
+                });
+                if lines.markers.total() > 0 {
+                    self.marker_files
+                        .push((path.clone(), lines.markers.total()));
+                }
+
+                match lines.spdx {
+                    Some(license) => *self.spdx.entry(license).or_default() += 1,
+                    None if binary => {}
+                    None => self.spdx_missing += 1,
+                }
+
+                match lines.header_guard {
+                    Some(HeaderGuard::PragmaOnce) => self.header_guards.pragma_once += 1,
+                    Some(HeaderGuard::IncludeGuard) => self.header_guards.include_guard += 1,
+                    Some(HeaderGuard::Unguarded) => self.header_guards.unguarded.push(path),
+                    None => {}
+                }
             }
         }
         Ok(())
     }
 
-    pub fn count_lines(&mut self, path: &PathBuf) -> io::Result<(usize, usize, usize)> {
-        let file = fs::File::open(path)?;
-        let reader = io::BufReader::new(file);
+    fn record_content(&mut self, hash: u64, lines: usize) {
+        if self.content_hashes.insert(hash, lines).is_some() {
+            self.duplicate_files += 1;
+            self.duplicate_lines += lines;
+        }
+    }
 
-        let mut blank = 0;
-        let mut comment = 0;
-        let mut code = 0;
+    fn classify(&self, path: &Path) -> io::Result<(FileType, FileLines)> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let file_type = match (self.mapped_type(path), FileType::from_filename(&file_name)) {
+            (Some(file_type), _) => file_type,
+            (None, FileType::TypeOther) => match path.extension() {
+                Some(extension) => FileType::from_extension(extension.to_str().unwrap_or("")),
+                None => FileType::TypeOther,
+            },
+            (None, file_type) => file_type,
+        };
 
-        for line in reader.lines() {
-            let line = line?;
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                blank += 1;
-            } else if trimmed.starts_with("//")
-                || trimmed.starts_with("/*")
-                || trimmed.starts_with('*')
-                || trimmed.starts_with('#')
-                || trimmed.starts_with(';')
-            {
-                comment += 1;
-            } else {
-                code += 1;
-            }
+        let mut reader = self.tree.read(path)?;
+        if reader.fill_buf()?.contains(&0) {
+            return Ok((FileType::TypeBinary, FileLines::default()));
         }
+        let lines = FileLines::count(reader, file_type)?;
+        let file_type = match file_type {
+            FileType::TypeOther => lines.shebang.unwrap_or(FileType::TypeOther),
+            file_type => file_type,
+        };
+        Ok((file_type, lines))
+    }
 
-        Ok((blank, comment, code))
+    pub fn merge(&mut self, other: &FileCounter) {
+        if self.version != other.version {
+            warn!(
+                arch = self.arch.as_str();
+                "merge Linux-{} arch {} into Linux-{} arch {}: kernel versions mismatch",
+                other.version, other.arch, self.version, self.arch
+            );
+        }
+
+        for (file_type, other_stats) in &other.file_count {
+            // custom languages are numbered per counter, match them up by name
+            let file_type = match file_type {
+                FileType::TypeCustom(_) => self.language_type(other.language(*file_type)),
+                file_type => *file_type,
+            };
+            self.file_count
+                .entry(file_type)
+                .or_default()
+                .add(other_stats);
+        }
+        for (license, files) in &other.spdx {
+            *self.spdx.entry(license.clone()).or_default() += files;
+        }
+        self.spdx_missing += other.spdx_missing;
+        self.header_guards.pragma_once += other.header_guards.pragma_once;
+        self.header_guards.include_guard += other.header_guards.include_guard;
+        self.header_guards
+            .unguarded
+            .extend(other.header_guards.unguarded.iter().cloned());
+        self.cache_enabled |= other.cache_enabled;
+        self.cache_hits += other.cache_hits;
+        self.files_read += other.files_read;
+
+        // identical files copied between arches only show up once merged
+        self.hardlinks += other.hardlinks;
+        self.dedup_by_hash |= other.dedup_by_hash;
+        self.duplicate_files += other.duplicate_files;
+        self.duplicate_lines += other.duplicate_lines;
+        for (hash, lines) in &other.content_hashes {
+            self.record_content(*hash, *lines);
+        }
+        self.marker_files.extend(other.marker_files.iter().cloned());
     }
 
-    pub fn print(&self) {
-        println!("{:-<70}", "");
-        println!(
-            "{:^70}",
-            format!("Linux-{} Arch {}", self.version, self.arch.to_uppercase())
-        );
-        println!("{:-<70}", "");
-        println!(
-            "{: <30} {: <10} {: <10} {: <10} {: <10}",
-            "Language", "files", "blank", "comment", "code"
-        );
-        println!("{:-<70}", "");
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file_count.values().all(|stats| stats.files == 0)
+    }
 
-        let mut total_files = 0;
-        let mut total_blank = 0;
-        let mut total_comment = 0;
-        let mut total_code = 0;
+    fn total(&self) -> FileStat {
+        let mut total = FileStat::default();
+        for stats in self.file_count.values() {
+            total.add(stats);
+        }
+        total
+    }
 
+    // languages are ordered by code lines (descending), then by name
+    fn sorted_stats(&self) -> Vec<(&FileType, &FileStat)> {
         let mut sorted_stats: Vec<_> = self.file_count.iter().collect();
-        sorted_stats.sort_by(|a, b| b.1.code.cmp(&a.1.code));
-
-        for (file_type, stats) in sorted_stats {
-            let type_str = match file_type {
-                FileType::TypeC => "C",
-                FileType::TypeH => "C/C++ Header",
-                FileType::TypeRust => "Rust",
-                FileType::TypeAsm => "Assembly",
-                FileType::TypePython => "Python",
-                FileType::TypeM => "Makefile",
-                FileType::TypeK => "kconfig",
-                FileType::TypeOther => "Other",
-            };
-            println!(
-                "{: <30} {: <10} {: <10} {: <10} {: <10}",
-                type_str, stats.files, stats.blank, stats.comment, stats.code
-            );
+        sorted_stats
+            .sort_by_key(|(file_type, stats)| (Reverse(stats.code), self.language(**file_type)));
+        sorted_stats
+    }
 
-            total_files += stats.files;
-            total_blank += stats.blank;
-            total_comment += stats.comment;
-            total_code += stats.code;
+    // flat `<language>.<column>` numbers, used for baseline snapshots
+    pub fn metrics(&self) -> BTreeMap<String, u64> {
+        let mut metrics = BTreeMap::new();
+        let total = self.total();
+        let rows = self
+            .file_count
+            .iter()
+            .map(|(file_type, stats)| (self.language(*file_type), stats))
+            .chain(std::iter::once(("total", &total)));
+        for (name, stats) in rows {
+            metrics.insert(format!("{}.files", name), stats.files as u64);
+            metrics.insert(format!("{}.blank", name), stats.blank as u64);
+            metrics.insert(format!("{}.comment", name), stats.comment as u64);
+            metrics.insert(format!("{}.code", name), stats.code as u64);
         }
+        metrics
+    }
 
-        println!("{:-<70}", "");
-        println!(
-            "{: <30} {: <10} {: <10} {: <10} {: <10}",
-            "SUM:", total_files, total_blank, total_comment, total_code
-        );
-        println!("{:-<70}", "");
+    // the largest file and the TODO hotspots are shown as printed, not as counted
+    fn shown_path(&self, path: &Path) -> PathBuf {
+        self.relative_to
+            .as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .to_path_buf()
     }
-}
 
-impl From<(String, String, PathBuf)> for FileCounter {
-    fn from(value: (String, String, PathBuf)) -> Self {
-        FileCounter {
-            arch: value.0,
-            version: value.1,
-            dir_path: value.2,
-            file_count: HashMap::new(),
+    fn shown_stat(&self, mut stat: FileStat) -> FileStat {
+        stat.max_path = stat.max_path.map(|path| self.shown_path(&path));
+        stat
+    }
+
+    pub fn snapshot(&self) -> AnalysisResult {
+        let languages = self
+            .sorted_stats()
+            .into_iter()
+            .map(|(file_type, stat)| LanguageRow {
+                language: self.language(*file_type).to_string(),
+                stat: self.shown_stat(stat.clone()),
+            })
+            .collect();
+
+        AnalysisResult::Code(CodeSummary {
+            version: self.version.clone(),
+            arch: self.arch.clone(),
+            languages,
+            total: self.shown_stat(self.total()),
+            verbose: self.verbose,
+            cache_hits: self.cache_enabled.then_some(self.cache_hits),
+            hardlinks: self.hardlinks,
+            duplicates: self
+                .dedup_by_hash
+                .then_some((self.duplicate_lines, self.duplicate_files)),
+        })
+    }
+
+    pub fn print(&self, renderer: &dyn Renderer) -> io::Result<()> {
+        renderer.render(&self.snapshot(), &mut io::stdout())
+    }
+
+    // the common code and what each arch in `arch_counters` adds on top of it
+    pub fn whole_tree_report(&self, arch_counters: &[FileCounter]) -> WholeTreeReport {
+        let portions: Vec<(String, FileStat)> =
+            std::iter::once(("common".to_string(), self.total()))
+                .chain(
+                    arch_counters
+                        .iter()
+                        .map(|fc| (format!("+ {}", fc.arch), fc.total())),
+                )
+                .collect();
+        let mut total = FileStat::default();
+        for (_, stat) in &portions {
+            total.add(stat);
+        }
+        WholeTreeReport {
+            version: self.version.clone(),
+            portions,
+            total,
+        }
+    }
+
+    // the same rows as `print`, in the shape `count --format json` writes; `todos` lists that many
+    // files with the most markers
+    pub fn export(&self, todos: Option<usize>) -> CodeExport {
+        CodeExport {
+            arch: self.arch.clone(),
+            version: self.version.to_string(),
+            languages: self
+                .sorted_stats()
+                .into_iter()
+                .map(|(file_type, stats)| LanguageExport {
+                    language: self.language(*file_type).to_string(),
+                    stat: self.shown_stat(stats.clone()).export(),
+                })
+                .collect(),
+            total: self.shown_stat(self.total()).export(),
+            spdx: self.spdx.clone().into_iter().collect(),
+            spdx_missing: self.spdx_missing,
+            todo_files: todos.map(|top| {
+                self.marker_hotspots(top)
+                    .into_iter()
+                    .map(|(path, markers)| MarkerFile {
+                        path: self.shown_path(path),
+                        markers,
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    // the `top` files with the most markers, ties in path order
+    fn marker_hotspots(&self, top: usize) -> Vec<(&PathBuf, usize)> {
+        let mut marker_files: Vec<_> = self
+            .marker_files
+            .iter()
+            .map(|(path, markers)| (path, *markers))
+            .collect();
+        marker_files.sort_by_key(|(path, markers)| (Reverse(*markers), *path));
+        marker_files.truncate(top);
+        marker_files
+    }
+
+    // languages are ordered by name, a language missing on one side counts as zero
+    pub fn delta_report(&self, other: &FileCounter) -> LanguageDeltaReport {
+        let mut languages: Vec<&str> = self
+            .file_count
+            .keys()
+            .map(|file_type| self.language(*file_type))
+            .chain(
+                other
+                    .file_count
+                    .keys()
+                    .map(|file_type| other.language(*file_type)),
+            )
+            .collect();
+        languages.sort();
+        languages.dedup();
+
+        let empty = FileStat::default();
+        LanguageDeltaReport {
+            old_version: self.version.clone(),
+            new_version: other.version.clone(),
+            arch: self.arch.clone(),
+            languages: languages
+                .into_iter()
+                .map(|language| LanguageDeltaRow {
+                    language: language.to_string(),
+                    old: self.stats_of(language).unwrap_or(&empty).clone(),
+                    new: other.stats_of(language).unwrap_or(&empty).clone(),
+                })
+                .collect(),
+            old_total: self.total(),
+            new_total: other.total(),
+        }
+    }
+
+    // languages are ordered by marker count (descending), then by name, followed by
+    // the `top` files with the most markers
+    pub fn todo_report(&self, top: usize) -> TodoReport {
+        let mut sorted_stats: Vec<_> = self
+            .file_count
+            .iter()
+            .filter(|(_, stats)| stats.markers.total() > 0)
+            .collect();
+        sorted_stats.sort_by_key(|(file_type, stats)| {
+            (Reverse(stats.markers.total()), self.language(**file_type))
+        });
+
+        TodoReport {
+            languages: sorted_stats
+                .into_iter()
+                .map(|(file_type, stats)| MarkerRow {
+                    language: self.language(*file_type).to_string(),
+                    todo: stats.markers.todo,
+                    fixme: stats.markers.fixme,
+                    xxx: stats.markers.xxx,
+                })
+                .collect(),
+            files: self
+                .marker_hotspots(top)
+                .into_iter()
+                .map(|(path, markers)| MarkerFile {
+                    path: self.shown_path(path),
+                    markers,
+                })
+                .collect(),
+        }
+    }
+
+    // licenses are ordered by file count (descending), then by expression
+    pub fn spdx_report(&self) -> SpdxReport {
+        let mut licenses: Vec<(String, usize)> = self
+            .spdx
+            .iter()
+            .map(|(license, files)| (license.clone(), *files))
+            .collect();
+        licenses.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        SpdxReport {
+            licenses,
+            missing: self.spdx_missing,
+        }
+    }
+
+    // languages with the most files without code come first
+    pub fn file_health_report(&self) -> FileHealthReport {
+        let mut languages: Vec<LanguageRow> = self
+            .file_count
+            .iter()
+            .map(|(file_type, stat)| LanguageRow {
+                language: self.language(*file_type).to_string(),
+                stat: self.shown_stat(stat.clone()),
+            })
+            .collect();
+        languages.sort_by(|a, b| {
+            let without_code =
+                |row: &LanguageRow| row.stat.empty_files() + row.stat.comment_only_files();
+            without_code(b)
+                .cmp(&without_code(a))
+                .then_with(|| a.language.cmp(&b.language))
+        });
+        FileHealthReport {
+            languages,
+            total: self.total(),
+        }
+    }
+
+    pub fn header_guard_report(&self) -> HeaderGuardReport {
+        let guards = &self.header_guards;
+        let mut unguarded: Vec<PathBuf> = guards
+            .unguarded
+            .iter()
+            .map(|path| self.shown_path(path))
+            .collect();
+        unguarded.sort();
+        HeaderGuardReport {
+            pragma_once: guards.pragma_once,
+            include_guard: guards.include_guard,
+            unguarded,
         }
     }
 }
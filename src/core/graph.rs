@@ -0,0 +1,155 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    Depends,
+    Select,
+    // `default FOO` or `default y if FOO`, an implicit dependency on FOO
+    Default,
+}
+
+impl EdgeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EdgeKind::Depends => "depends on",
+            EdgeKind::Select => "selects",
+            EdgeKind::Default => "defaults from",
+        }
+    }
+
+    fn dot_style(&self) -> &'static str {
+        match self {
+            EdgeKind::Depends => "solid",
+            EdgeKind::Select => "bold",
+            EdgeKind::Default => "dotted, color=blue",
+        }
+    }
+}
+
+// edges point from a symbol to the symbols it references
+#[derive(Default)]
+pub struct DependencyGraph {
+    edges: BTreeMap<String, BTreeSet<(String, EdgeKind)>>,
+}
+
+impl DependencyGraph {
+    pub fn add_node(&mut self, symbol: &str) {
+        self.edges.entry(symbol.to_string()).or_default();
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, kind: EdgeKind) {
+        if from == to {
+            return;
+        }
+        self.edges
+            .entry(from.to_string())
+            .or_default()
+            .insert((to.to_string(), kind));
+    }
+
+    pub fn edges_from(&self, symbol: &str) -> impl Iterator<Item = &(String, EdgeKind)> {
+        self.edges.get(symbol).into_iter().flatten()
+    }
+
+    // every symbol that depends on, selects or defaults from `symbol`
+    pub fn reverse_deps(&self, symbol: &str) -> Vec<(&str, EdgeKind)> {
+        let mut reverse = Vec::new();
+        for (from, edges) in &self.edges {
+            for (to, kind) in edges {
+                if to == symbol {
+                    reverse.push((from.as_str(), *kind));
+                }
+            }
+        }
+        reverse
+    }
+
+    pub fn print_reverse_deps(&self, symbol: &str) {
+        let reverse = self.reverse_deps(symbol);
+        println!("{:-<90}", "");
+        println!("{:^90}", format!("Reverse Dependencies of {}", symbol));
+        println!("{:-<90}", "");
+        for (from, kind) in &reverse {
+            println!("{: <45} {}", from, kind.label());
+        }
+        println!("{:-<90}", "");
+        println!("{: <45} {} Components", "SUM:", reverse.len());
+        println!("{:-<90}", "");
+    }
+
+    // each cycle is listed once, starting from its smallest symbol
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = BTreeSet::new();
+        let mut done = BTreeSet::new();
+        for start in self.edges.keys() {
+            let mut path = Vec::new();
+            self.visit(start, &mut path, &mut done, &mut cycles);
+        }
+        cycles.into_iter().collect()
+    }
+
+    fn visit<'a>(
+        &'a self,
+        symbol: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+        cycles: &mut BTreeSet<Vec<String>>,
+    ) {
+        if let Some(index) = path.iter().position(|entry| *entry == symbol) {
+            let cycle = &path[index..];
+            let min = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap_or(0);
+            let rotated = cycle[min..]
+                .iter()
+                .chain(&cycle[..min])
+                .map(|entry| entry.to_string())
+                .collect();
+            cycles.insert(rotated);
+            return;
+        }
+        if done.contains(symbol) {
+            return;
+        }
+
+        path.push(symbol);
+        for (to, _) in self.edges_from(symbol) {
+            self.visit(to, path, done, cycles);
+        }
+        path.pop();
+        done.insert(symbol);
+    }
+
+    pub fn print_cycles(&self) {
+        let cycles = self.find_cycles();
+        println!("{:-<90}", "");
+        println!("{:^90}", "Dependency Cycles");
+        println!("{:-<90}", "");
+        for cycle in &cycles {
+            println!("{} -> {}", cycle.join(" -> "), cycle[0]);
+        }
+        println!("{:-<90}", "");
+        println!("{: <45} {} Cycles", "SUM:", cycles.len());
+        println!("{:-<90}", "");
+    }
+
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph \"{}\" {{", name);
+        for (from, edges) in &self.edges {
+            let _ = writeln!(dot, "    \"{}\";", from);
+            for (to, kind) in edges {
+                let _ = writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\" [style={}];",
+                    from,
+                    to,
+                    kind.dot_style()
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
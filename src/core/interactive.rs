@@ -0,0 +1,502 @@
+use crate::core::error::{Error, Result};
+use crate::core::kconfig_counter::KconfigCounter;
+use crate::core::report::ColorChoice;
+use log::warn;
+use regex::Regex;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Editor, Helper};
+use std::cmp::Reverse;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command as Process, Stdio};
+
+const PROMPT: &str = "Enter a component name or a command ('help' to list them, 'q' to quit)>> ";
+
+const HISTORY_FILE: &str = ".auto_script_history";
+
+// (command, description) pairs listed by `help`
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        "show NAME",
+        "show the details of the component NAME, a bare NAME does the same, wildcards list the matches",
+    ),
+    (
+        "list PATTERN",
+        "list the components matching PATTERN, `*` and `?` are wildcards",
+    ),
+    (
+        "deps NAME",
+        "list what NAME depends on, selects and defaults from",
+    ),
+    (
+        "rdeps NAME",
+        "list the components depending on, selecting or defaulting from NAME",
+    ),
+    (
+        "footprint NAME",
+        "sum up the type, dependencies, selects, guarded code and objects of NAME",
+    ),
+    (
+        "top N",
+        "list the N components with the most guarded code lines",
+    ),
+    ("grep REGEX", "search the guarded code snippets for REGEX"),
+    (
+        "save NAME FILE",
+        "write the details of NAME to a new FILE, `save!` overwrites it",
+    ),
+    ("export NAME FILE", "write the details of NAME to FILE, like `save!`"),
+    ("help, ?", "list the available commands"),
+    ("q, quit", "leave the interactive mode, so does Ctrl-D"),
+];
+
+enum Command<'a> {
+    Empty,
+    Help,
+    Quit,
+    Show(&'a str),
+    List(&'a str),
+    Deps(&'a str),
+    Rdeps(&'a str),
+    Footprint(&'a str),
+    Top(usize),
+    Grep(Regex),
+    // (name, path, whether an existing file is overwritten)
+    Save(&'a str, &'a str, bool),
+}
+
+impl<'a> Command<'a> {
+    // the error is shown to the user in place of the command's output
+    fn parse(input: &'a str) -> std::result::Result<Self, String> {
+        let input = input.trim();
+        let mut words = input.split_whitespace();
+        let Some(first) = words.next() else {
+            return Ok(Command::Empty);
+        };
+        let args: Vec<&str> = words.collect();
+        let command = first.to_ascii_lowercase();
+        let usage = |usage: &str| format!("usage: {}", usage);
+
+        match (command.as_str(), args.as_slice()) {
+            ("help" | "?", []) => Ok(Command::Help),
+            ("q" | "quit", []) => Ok(Command::Quit),
+            // a name with wildcards lists what it matches instead
+            ("show", [pattern]) if pattern.contains(['*', '?']) => Ok(Command::List(pattern)),
+            ("show", [name]) => Ok(Command::Show(name)),
+            ("show", _) => Err(usage("show NAME")),
+            ("list", [pattern]) => Ok(Command::List(pattern)),
+            ("list", _) => Err(usage("list PATTERN")),
+            ("deps", [name]) => Ok(Command::Deps(name)),
+            ("deps", _) => Err(usage("deps NAME")),
+            ("rdeps", [name]) => Ok(Command::Rdeps(name)),
+            ("rdeps", _) => Err(usage("rdeps NAME")),
+            ("footprint", [name]) => Ok(Command::Footprint(name)),
+            ("footprint", _) => Err(usage("footprint NAME")),
+            ("top", [n]) => n.parse().map(Command::Top).map_err(|_| usage("top N")),
+            ("top", _) => Err(usage("top N")),
+            // the pattern may contain spaces, it is the rest of the line
+            ("grep", [_, ..]) => {
+                let pattern = input[first.len()..].trim();
+                Regex::new(pattern)
+                    .map(Command::Grep)
+                    .map_err(|err| format!("invalid pattern: {}", err))
+            }
+            ("grep", _) => Err(usage("grep REGEX")),
+            ("save", [name, file]) => Ok(Command::Save(name, file, false)),
+            ("save!" | "export", [name, file]) => Ok(Command::Save(name, file, true)),
+            ("save" | "save!", _) => Err(usage("save[!] NAME FILE")),
+            ("export", _) => Err(usage("export NAME FILE")),
+            (_, []) if first.contains(['*', '?']) => Ok(Command::List(first)),
+            (_, []) => Ok(Command::Show(first)),
+            _ => Err(format!("unknown command '{}'", first)),
+        }
+    }
+}
+
+fn write_help(out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "Available commands:")?;
+    for (command, description) in COMMANDS {
+        writeln!(out, "  {: <18} {}", command, description)?;
+    }
+    Ok(())
+}
+
+// a few close names beat a bare "not found"
+fn not_found(kc: &KconfigCounter, name: &str) -> String {
+    let suggestions = kc.suggestions(name);
+    if suggestions.is_empty() {
+        format!(
+            "Component '{}' not found, type 'help' to list the available commands.",
+            name
+        )
+    } else {
+        format!(
+            "Component '{}' not found, did you mean {}?",
+            name,
+            suggestions.join(", ")
+        )
+    }
+}
+
+fn show(kc: &KconfigCounter, name: &str, out: &mut dyn Write, styled: bool) -> io::Result<()> {
+    if !kc.write_component(name, out, styled)? {
+        writeln!(out, "{}", not_found(kc, name))?;
+    }
+    Ok(())
+}
+
+// without wildcards PATTERN matches as a substring, like `--query` suggestions do
+fn list(kc: &KconfigCounter, pattern: &str, out: &mut dyn Write) -> io::Result<()> {
+    let names = if pattern.contains(['*', '?']) {
+        kc.expand_glob(pattern)
+    } else {
+        kc.find(pattern)
+    };
+    for name in &names {
+        writeln!(out, "{}", name)?;
+    }
+    writeln!(out, "{} components", names.len())
+}
+
+fn deps(kc: &KconfigCounter, name: &str, out: &mut dyn Write) -> io::Result<()> {
+    let Some((name, _)) = kc.lookup(name) else {
+        return writeln!(out, "{}", not_found(kc, name));
+    };
+    let graph = kc.dependency_graph();
+    let edges: Vec<_> = graph.edges_from(name).collect();
+    for (to, kind) in &edges {
+        writeln!(out, "{: <45} {}", to, kind.label())?;
+    }
+    writeln!(out, "{} references {} symbols", name, edges.len())
+}
+
+// also answers for symbols that are referenced but never defined
+fn rdeps(kc: &KconfigCounter, name: &str, out: &mut dyn Write) -> io::Result<()> {
+    let symbol = match kc.lookup(name) {
+        Some((symbol, _)) => symbol.to_string(),
+        None => {
+            let name = name.to_ascii_uppercase();
+            name.strip_prefix("CONFIG_").unwrap_or(&name).to_string()
+        }
+    };
+    let graph = kc.dependency_graph();
+    let reverse = graph.reverse_deps(&symbol);
+    for (from, kind) in &reverse {
+        writeln!(out, "{: <45} {}", from, kind.label())?;
+    }
+    writeln!(out, "{} components reference {}", reverse.len(), symbol)
+}
+
+fn footprint(kc: &KconfigCounter, name: &str, out: &mut dyn Write) -> io::Result<()> {
+    match kc.footprint(name) {
+        Some(footprint) => footprint.write(out),
+        None => writeln!(out, "{}", not_found(kc, name)),
+    }
+}
+
+fn top(kc: &KconfigCounter, n: usize, out: &mut dyn Write) -> io::Result<()> {
+    let mut components: Vec<(&str, usize)> = kc
+        .components()
+        .map(|(name, stat)| (name, stat.code_lines()))
+        .filter(|(_, lines)| *lines > 0)
+        .collect();
+    components.sort_by_key(|(name, lines)| (Reverse(*lines), *name));
+    for (name, lines) in components.into_iter().take(n) {
+        writeln!(out, "{: <45} {}", name, lines)?;
+    }
+    Ok(())
+}
+
+// one `NAME file: line` per matching snippet line, components in name order
+fn grep(kc: &KconfigCounter, pattern: &Regex, out: &mut dyn Write) -> io::Result<()> {
+    let mut components: Vec<_> = kc.components().collect();
+    components.sort_by_key(|(name, _)| *name);
+    let mut matches = 0;
+    for (name, stat) in components {
+        for snippet in stat.code_snippets() {
+            for line in snippet.code().lines().filter(|line| pattern.is_match(line)) {
+                writeln!(
+                    out,
+                    "{} {}: {}",
+                    name,
+                    kc.display_path(snippet.file()),
+                    line
+                )?;
+                matches += 1;
+            }
+        }
+    }
+    writeln!(out, "{} matching lines", matches)
+}
+
+// the same detail view `--query` prints, without colors
+fn save(
+    kc: &KconfigCounter,
+    name: &str,
+    path: &str,
+    overwrite: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let mut detail = Vec::new();
+    if !kc.write_component(name, &mut detail, false)? {
+        return writeln!(out, "{}", not_found(kc, name));
+    }
+    let snippets = kc.lookup(name).map_or(0, |(_, stat)| stat.snippet_count());
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!overwrite)
+        .open(path);
+    match file.and_then(|mut file| file.write_all(&detail)) {
+        Ok(()) => writeln!(
+            out,
+            "wrote {} bytes ({} snippets) to {}",
+            detail.len(),
+            snippets,
+            path
+        ),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            writeln!(out, "{} already exists, use `save!` to overwrite it", path)
+        }
+        Err(err) => writeln!(out, "failed to write {}: {}", path, err),
+    }
+}
+
+/// Runs one line of the prompt against `kc`, `Ok(false)` once the user quits.
+///
+/// A line that does not parse prints its usage and keeps the loop going:
+///
+/// ```
+/// use auto_script::core::interactive::execute;
+/// use auto_script::KconfigCounter;
+///
+/// let kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), "Kconfig".into());
+/// let mut out = Vec::new();
+/// assert!(execute(&kc, "top many", &mut out).unwrap());
+/// assert_eq!(String::from_utf8(out).unwrap(), "usage: top N\n");
+/// assert!(!execute(&kc, "q", &mut Vec::new()).unwrap());
+/// ```
+pub fn execute(kc: &KconfigCounter, line: &str, out: &mut dyn Write) -> io::Result<bool> {
+    execute_styled(kc, line, out, false)
+}
+
+// user-facing feedback goes to `out` next to the prompt, never through the logger
+fn execute_styled(
+    kc: &KconfigCounter,
+    line: &str,
+    out: &mut dyn Write,
+    styled: bool,
+) -> io::Result<bool> {
+    let command = match Command::parse(line) {
+        Ok(command) => command,
+        Err(message) => {
+            writeln!(out, "{}", message)?;
+            return Ok(true);
+        }
+    };
+    match command {
+        Command::Empty => {}
+        Command::Help => write_help(out)?,
+        Command::Quit => return Ok(false),
+        Command::Show(name) => show(kc, name, out, styled)?,
+        Command::List(pattern) => list(kc, pattern, out)?,
+        Command::Deps(name) => deps(kc, name, out)?,
+        Command::Rdeps(name) => rdeps(kc, name, out)?,
+        Command::Footprint(name) => footprint(kc, name, out)?,
+        Command::Top(n) => top(kc, n, out)?,
+        Command::Grep(pattern) => grep(kc, &pattern, out)?,
+        Command::Save(name, path, overwrite) => save(kc, name, path, overwrite, out)?,
+    }
+    Ok(true)
+}
+
+// `$PAGER`, otherwise `less -R` so the colored snippets survive
+fn pager() -> Vec<String> {
+    let pager = std::env::var("PAGER").unwrap_or_default();
+    let words: Vec<String> = pager.split_whitespace().map(String::from).collect();
+    if words.is_empty() {
+        vec!["less".to_string(), "-R".to_string()]
+    } else {
+        words
+    }
+}
+
+// replies taller than the terminal go through the pager, the rest is printed as is
+fn page(reply: &[u8], terminal: bool) -> io::Result<()> {
+    let height = match terminal_size::terminal_size() {
+        Some((_, terminal_size::Height(height))) if terminal => height as usize,
+        _ => return io::stdout().write_all(reply),
+    };
+    if reply.iter().filter(|&&byte| byte == b'\n').count() < height {
+        return io::stdout().write_all(reply);
+    }
+
+    let pager = pager();
+    let child = Process::new(&pager[0])
+        .args(&pager[1..])
+        .stdin(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            warn!("failed to start pager {:?}: {}", pager[0], err);
+            return io::stdout().write_all(reply);
+        }
+    };
+    // quitting the pager early closes its stdin, that is not an error
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(err) = stdin.write_all(reply) {
+            if err.kind() != io::ErrorKind::BrokenPipe {
+                return Err(err);
+            }
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Completes the word under the cursor to the component names of one counter.
+///
+/// A `CONFIG_` prefix is kept as typed and the case of the word is ignored:
+///
+/// ```
+/// use auto_script::core::interactive::ComponentCompleter;
+///
+/// let completer = ComponentCompleter::new(["SMP", "SMP_ON_UP", "MMU"]);
+/// assert_eq!(completer.candidates("config_sm", 9), (7, vec!["SMP", "SMP_ON_UP"]));
+/// assert_eq!(completer.candidates("MM", 2), (0, vec!["MMU"]));
+/// ```
+pub struct ComponentCompleter {
+    // sorted, candidates are listed in this order
+    names: Vec<String>,
+}
+
+impl ComponentCompleter {
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut names: Vec<String> = names.into_iter().map(Into::into).collect();
+        names.sort();
+        names.dedup();
+        ComponentCompleter { names }
+    }
+
+    pub fn for_counter(kc: &KconfigCounter) -> Self {
+        ComponentCompleter::new(kc.components().map(|(name, _)| name))
+    }
+
+    // (start of the replaced word, names it may become), the word ends at `pos`
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<&str>) {
+        let line = &line[..pos];
+        let mut start = line
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map(|(index, c)| index + c.len_utf8())
+            .unwrap_or(0);
+        let mut word = &line[start..];
+        if word
+            .get(..7)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("CONFIG_"))
+        {
+            start += 7;
+            word = &word[7..];
+        }
+
+        let names = self
+            .names
+            .iter()
+            .filter(|name| {
+                name.get(..word.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(word))
+            })
+            .map(String::as_str)
+            .collect();
+        (start, names)
+    }
+}
+
+impl Completer for ComponentCompleter {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let (start, names) = self.candidates(line, pos);
+        Ok((start, names.into_iter().map(String::from).collect()))
+    }
+}
+
+impl Hinter for ComponentCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for ComponentCompleter {}
+
+impl Validator for ComponentCompleter {}
+
+impl Helper for ComponentCompleter {}
+
+// `~/.auto_script_history`, none without a home directory
+pub fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+// reads commands until `q` or the end of input, Ctrl-C only drops the current line
+pub fn run(kc: &KconfigCounter, history: Option<PathBuf>, color: ColorChoice) -> Result<()> {
+    let terminal = io::stdout().is_terminal();
+    let styled = color.enabled(terminal);
+    // an ambiguous prefix lists every candidate instead of cycling through them
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::<ComponentCompleter, DefaultHistory>::with_config(config)?;
+    editor.set_helper(Some(ComponentCompleter::for_counter(kc)));
+    if let Some(path) = &history {
+        // the first session has no history yet
+        if path.exists() {
+            if let Err(err) = editor.load_history(path) {
+                warn!("failed to load history {:?}: {}", path, err);
+            }
+        }
+    }
+
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => {
+                println!();
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !line.trim().is_empty() {
+            editor.add_history_entry(line.trim())?;
+        }
+        let mut reply = Vec::new();
+        let keep_going = execute_styled(kc, &line, &mut reply, styled).map_err(Error::Terminal)?;
+        page(&reply, terminal).map_err(Error::Terminal)?;
+        if !keep_going {
+            break;
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(err) = editor.save_history(path) {
+            warn!("failed to save history {:?}: {}", path, err);
+        }
+    }
+    Ok(())
+}
@@ -1,123 +1,1339 @@
-use crate::core::utils::get_filed;
-use anyhow::Result;
-use log::{error, info, warn};
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, Write};
+use crate::core::dotconfig::{ConfigDiff, DotConfig};
+use crate::core::error::{Error, Result};
+use crate::core::graph::{DependencyGraph, EdgeKind};
+use crate::core::kconfig_expr::{Conditional, Expr, Tristate};
+use crate::core::maintainers::{Maintainers, Subsystem};
+use crate::core::progress::PhaseCounts;
+use crate::core::report::{
+    highlight_header, highlight_snippet, AnalysisResult, ComponentGroupRows, KconfigSummary,
+    Renderer,
+};
+use crate::core::source::{read_dir, FsTree, SourceTree};
+use crate::core::utils::{display_path, edit_distance, get_filed, glob_match, strip_bom};
+use crate::core::version::KernelVersion;
+use log::{debug, info, trace};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
-use std::{fs, io};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-#[derive(Debug)]
-enum KconfigComponentType {
+// "did you mean" candidates offered when a component lookup fails
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KconfigComponentType {
     Unknown,
     Bool,
-    Value,
+    Tristate,
+    Int,
+    Hex,
+    String,
+}
+
+impl KconfigComponentType {
+    pub(crate) const ALL: [KconfigComponentType; 6] = [
+        KconfigComponentType::Bool,
+        KconfigComponentType::Tristate,
+        KconfigComponentType::Int,
+        KconfigComponentType::Hex,
+        KconfigComponentType::String,
+        KconfigComponentType::Unknown,
+    ];
+
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "bool" | "def_bool" => Some(KconfigComponentType::Bool),
+            "tristate" | "def_tristate" => Some(KconfigComponentType::Tristate),
+            "int" => Some(KconfigComponentType::Int),
+            "hex" => Some(KconfigComponentType::Hex),
+            "string" => Some(KconfigComponentType::String),
+            _ => None,
+        }
+    }
 }
 
+impl fmt::Display for KconfigComponentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            KconfigComponentType::Unknown => "unknown",
+            KconfigComponentType::Bool => "boolean",
+            KconfigComponentType::Tristate => "tristate",
+            KconfigComponentType::Int => "integer",
+            KconfigComponentType::Hex => "hex",
+            KconfigComponentType::String => "string",
+        };
+        f.write_str(label)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct KconfigStat {
     default_value: Vec<String>,
     select: Vec<String>,
     depend: Vec<String>,
     value_type: KconfigComponentType,
     count: usize,
-    code_snippets: Vec<String>,
+    code_snippets: Vec<CodeSnippet>,
+    kconfig_file: PathBuf,
+    choice: Option<usize>,
+    makefile_lines: usize,
+    prompt: Option<String>,
+    // the `if` after the prompt only hides the question, the symbol keeps its value
+    visible_if: Option<String>,
+    // objects a Makefile adds under the symbol, e.g. `obj-$(CONFIG_FOO) += foo.o`
+    #[serde(default)]
+    objects: Vec<PathBuf>,
+    // owners from `MAINTAINERS`, only filled in by `assign_maintainers`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    maintainers: Vec<Subsystem>,
 }
 
-pub struct KconfigCounter {
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct KconfigChoice {
+    prompt: Option<String>,
+    default: Option<String>,
+    members: Vec<String>,
+}
+
+impl KconfigChoice {
+    // without an explicit `default` the first member is selected
+    pub fn default_member(&self) -> Option<&str> {
+        match &self.default {
+            Some(default) => default.split_whitespace().next(),
+            None => self.members.first().map(|member| member.as_str()),
+        }
+    }
+}
+
+impl KconfigStat {
+    pub fn value_type(&self) -> KconfigComponentType {
+        self.value_type
+    }
+
+    pub fn depends(&self) -> &[String] {
+        &self.depend
+    }
+
+    pub fn selects(&self) -> &[String] {
+        &self.select
+    }
+
+    pub fn defaults(&self) -> &[String] {
+        &self.default_value
+    }
+
+    // how many `config` entries define the symbol, more than one across arches or choices
+    pub fn definitions(&self) -> usize {
+        self.count
+    }
+
+    pub fn code_snippets(&self) -> &[CodeSnippet] {
+        &self.code_snippets
+    }
+
+    pub fn snippet_count(&self) -> usize {
+        self.code_snippets.len()
+    }
+
+    pub fn kconfig_file(&self) -> &Path {
+        &self.kconfig_file
+    }
+
+    pub fn makefile_lines(&self) -> usize {
+        self.makefile_lines
+    }
+
+    pub fn objects(&self) -> &[PathBuf] {
+        &self.objects
+    }
+
+    pub fn maintainers(&self) -> &[Subsystem] {
+        &self.maintainers
+    }
+
+    pub fn prompt(&self) -> Option<&str> {
+        self.prompt.as_deref()
+    }
+
+    pub fn visible_if(&self) -> Option<&str> {
+        self.visible_if.as_deref()
+    }
+
+    pub fn code_lines(&self) -> usize {
+        self.code_snippets.iter().map(|snippet| snippet.lines).sum()
+    }
+
+    // the attributes compared by `diff_components` and `trace`
+    pub fn attributes(&self) -> [(&'static str, String); 4] {
+        let mut select = self.select.clone();
+        select.sort();
+        [
+            ("type", self.value_type.to_string()),
+            ("depends", self.effective_depends()),
+            ("select", select.join(", ")),
+            ("default", self.default_value.join("; ")),
+        ]
+    }
+
+    // the kernel ANDs every `depends on` line of a symbol together
+    pub fn effective_depends(&self) -> String {
+        let mut terms: Vec<String> = Vec::new();
+        for depend in &self.depend {
+            let depend = depend.split_whitespace().collect::<Vec<_>>().join(" ");
+            if depend.is_empty() {
+                continue;
+            }
+            let term = if self.depend.len() > 1 && depend.contains("||") {
+                format!("({})", depend)
+            } else {
+                depend
+            };
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        terms.join(" && ")
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CodeSnippet {
+    // empty unless the counter stores snippets, see `KconfigCounterBuilder::store_snippets`
+    code: String,
+    lines: usize,
+    module: bool,
+    // the C source or header the block was found in
+    file: PathBuf,
+    // 1-based line of the opening `#if`, 0 in exports that predate it
+    #[serde(default)]
+    line: usize,
+}
+
+impl CodeSnippet {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    // guarded by `CONFIG_<NAME>_MODULE`, i.e. only built as a module
+    pub fn module(&self) -> bool {
+        self.module
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ComponentGroup {
+    Letter,
+    File,
+    // see `KconfigCounter::subsystem`
+    Subsystem,
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DiffFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+// the saved form of one arch, `--export-kconfig` writes a list of these
+#[derive(Serialize, Deserialize)]
+pub struct KconfigExport {
     arch: String,
     version: String,
+    components: BTreeMap<String, KconfigStat>,
+    choices: Vec<KconfigChoice>,
+    // `KconfigCounter::dir_components`, derived from the components and only written out
+    #[serde(default)]
+    directories: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+// the document `--export-kconfig` writes
+#[derive(Serialize, Deserialize)]
+pub struct KconfigExports {
+    pub arches: Vec<KconfigExport>,
+}
+
+#[derive(Serialize)]
+pub struct AttributeChange {
+    pub attribute: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize)]
+pub struct ChangedComponent {
+    pub name: String,
+    pub changes: Vec<AttributeChange>,
+}
+
+#[derive(Serialize)]
+pub struct ComponentDiff {
+    pub old: String,
+    pub new: String,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+    pub changed: Vec<ChangedComponent>,
+}
+
+#[derive(Serialize)]
+pub struct ArchValue {
+    pub arch: String,
+    pub value_type: String,
+    pub default: String,
+}
+
+#[derive(Serialize)]
+pub struct DivergentComponent {
+    pub name: String,
+    // the attributes that differ between arches, "type" and/or "default"
+    pub attributes: Vec<&'static str>,
+    pub values: Vec<ArchValue>,
+}
+
+#[derive(Serialize)]
+pub struct ArchComparison {
+    pub version: String,
+    pub arches: Vec<String>,
+    pub shared: Vec<String>,
+    // only filled with three or more arches
+    pub partial: Vec<String>,
+    pub exclusive: BTreeMap<String, Vec<String>>,
+    pub divergent: Vec<DivergentComponent>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlipChange {
+    Enabled,
+    Disabled,
+    Changed,
+}
+
+#[derive(Serialize)]
+pub struct DefconfigRow {
+    pub name: String,
+    pub a: String,
+    pub b: String,
+    pub change: FlipChange,
+    pub code_lines: usize,
+    pub makefile_lines: usize,
+}
+
+// a `.config` enabling several members of one choice, `validate_dotconfig_choices`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ChoiceViolation {
+    pub prompt: Option<String>,
+    pub enabled: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct DefconfigReport {
+    pub version: String,
+    pub arch: String,
+    pub a: String,
+    pub b: String,
+    pub rows: Vec<DefconfigRow>,
+    pub gained: usize,
+    pub lost: usize,
+    // differing symbols the arch Kconfig does not define
+    pub unparsed: usize,
+}
+
+#[derive(Serialize)]
+pub struct LineDelta {
+    pub name: String,
+    pub old: usize,
+    pub new: usize,
+    pub delta: i64,
+}
+
+#[derive(Serialize)]
+pub struct CodeDelta {
+    pub old: String,
+    pub new: String,
+    // symbols in both trees whose guarded lines changed, largest change first
+    pub changed: Vec<LineDelta>,
+    pub unchanged: usize,
+    pub only_old: Vec<LineDelta>,
+    pub only_new: Vec<LineDelta>,
+}
+
+#[derive(Serialize)]
+pub struct DirRow {
+    // relative to the kernel root, "." for the root itself
+    pub dir: String,
+    pub components: Vec<String>,
+}
+
+// directories by how many components condition their code, see `KconfigCounter::dir_report`
+#[derive(Serialize)]
+pub struct DirReport {
+    pub version: String,
+    pub arch: String,
+    pub dirs: Vec<DirRow>,
+}
+
+#[derive(Serialize)]
+pub struct SubsystemRow {
+    pub subsystem: String,
+    pub components: usize,
+    // every type in `KconfigComponentType::ALL` order, zeros included
+    pub types: Vec<(KconfigComponentType, usize)>,
+    pub code_lines: Option<usize>,
+}
+
+// see `KconfigCounter::subsystem_report`
+#[derive(Serialize)]
+pub struct SubsystemReport {
+    pub version: String,
+    pub arch: String,
+    pub subsystems: Vec<SubsystemRow>,
+}
+
+// components depending on symbols no Kconfig defines, see `KconfigCounter::dangling_report`
+pub struct DanglingReport {
+    pub label: String,
+    // (component, missing dependency)
+    pub dangling: Vec<(String, String)>,
+}
+
+pub struct SnippetRow {
+    pub component: String,
+    pub lines: usize,
+    // relative to the kernel root
+    pub file: PathBuf,
+}
+
+// the longest guarded blocks, see `KconfigCounter::snippet_report`
+pub struct SnippetReport {
+    pub label: String,
+    pub snippets: Vec<SnippetRow>,
+}
+
+pub enum TraceState {
+    Missing,
+    // the first tree defining the symbol, with every attribute
+    Present(Vec<(&'static str, String)>),
+    // empty when nothing changed since the previous tree having the symbol
+    Changed(Vec<AttributeChange>),
+}
+
+pub struct TraceStep {
+    pub version: KernelVersion,
+    pub state: TraceState,
+}
+
+// one symbol across kernel versions, see `KconfigCounter::trace`
+pub struct TraceReport {
+    pub symbol: String,
+    pub arch: String,
+    pub steps: Vec<TraceStep>,
+}
+
+// everything one symbol adds to the kernel, see `KconfigCounter::footprint`
+#[derive(Debug, Serialize)]
+pub struct Footprint {
+    pub name: String,
+    pub value_type: KconfigComponentType,
+    pub depends: String,
+    pub selects: Vec<String>,
+    pub code_lines: usize,
+    pub snippets: usize,
+    // C sources and headers holding the guarded code
+    pub files: usize,
+    pub objects: Vec<String>,
+}
+
+impl Footprint {
+    pub fn write(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        let or_none = |text: String| {
+            if text.is_empty() {
+                "<none>".to_string()
+            } else {
+                text
+            }
+        };
+        writeln!(out, "Footprint: {}", self.name)?;
+        writeln!(out, "  Value Type: {}", self.value_type)?;
+        writeln!(out, "  Depends on: {}", or_none(self.depends.clone()))?;
+        writeln!(out, "  Selects: {}", or_none(self.selects.join(", ")))?;
+        writeln!(
+            out,
+            "  Guarded code: {} lines in {} snippets, {} files",
+            self.code_lines, self.snippets, self.files
+        )?;
+        writeln!(out, "  Objects: {}", or_none(self.objects.join(", ")))
+    }
+}
+
+impl KconfigExport {
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+}
+
+pub struct KconfigCounter {
+    arch: String,
+    version: KernelVersion,
     kconfig_path: PathBuf,
+    kernel_root: Option<PathBuf>,
     check_all: bool,
+    sorted: bool,
+    source_include: Vec<String>,
+    group_by: Option<ComponentGroup>,
+    defconfig: Option<PathBuf>,
+    // Kconfig files and code directories left out, together with everything below them
+    exclude: Vec<PathBuf>,
+    store_snippets: bool,
+    // `$(NAME)` substitutions in `source` paths, e.g. SRCARCH
+    kconfig_env: HashMap<String, String>,
     component: HashMap<String, KconfigStat>,
-    code_dir: HashSet<PathBuf>,
+    choices: Vec<KconfigChoice>,
+    code_dir: BTreeSet<PathBuf>,
+    recurse_source: bool,
+    // `source` lines left alone without `recurse_source`, resolved below the kernel root
+    skipped_sources: Vec<PathBuf>,
     total_components: usize,
     total_code_lines: usize,
+    // non-blank Kconfig lines inside `help` blocks, and the other non-comment ones
+    help_lines: usize,
+    structural_lines: usize,
+    // conditions of the enclosing `if` blocks, sourced files inherit them
+    if_conditions: Vec<String>,
+    // per-file messages are only counted, see `set_summarize`
+    summarize: bool,
+    progress: PhaseCounts,
+    // printed paths are shown below the kernel root
+    relative_paths: bool,
+    tree: Rc<dyn SourceTree>,
+    // raised from outside, e.g. by Ctrl-C, to end `analyze_code` early
+    stop: Arc<AtomicBool>,
+}
+
+/// Collects the options of a [`KconfigCounter`], `arch`, `version` and `kconfig_path`
+/// are required.
+///
+/// ```
+/// use auto_script::KconfigCounter;
+///
+/// let kc = KconfigCounter::builder()
+///     .arch("riscv")
+///     .version("6.9.5")
+///     .kconfig_path("/opt/linux-6.9.5/arch/riscv/Kconfig")
+///     .kconfig_env("SRCARCH", "riscv")
+///     .store_snippets(false)
+///     .build()
+///     .unwrap();
+/// assert_eq!(kc.label(), "Linux-6.9.5 riscv");
+///
+/// assert!(KconfigCounter::builder().arch("riscv").build().is_err());
+/// ```
+pub struct KconfigCounterBuilder {
+    arch: Option<String>,
+    version: Option<KernelVersion>,
+    kconfig_path: Option<PathBuf>,
+    kernel_root: Option<PathBuf>,
+    check_all: bool,
+    sorted: bool,
+    summarize: bool,
+    source_include: Vec<String>,
+    group_by: Option<ComponentGroup>,
+    defconfig: Option<PathBuf>,
+    exclude: Vec<PathBuf>,
+    store_snippets: bool,
+    kconfig_env: HashMap<String, String>,
+    relative_paths: bool,
+    recurse_source: bool,
+    tree: Option<Rc<dyn SourceTree>>,
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl Default for KconfigCounterBuilder {
+    fn default() -> Self {
+        KconfigCounterBuilder {
+            arch: None,
+            version: None,
+            kconfig_path: None,
+            kernel_root: None,
+            check_all: false,
+            sorted: false,
+            summarize: false,
+            source_include: vec!["arch/".to_string()],
+            group_by: None,
+            defconfig: None,
+            exclude: Vec::new(),
+            store_snippets: true,
+            kconfig_env: HashMap::new(),
+            relative_paths: false,
+            recurse_source: true,
+            tree: None,
+            stop: None,
+        }
+    }
+}
+
+impl KconfigCounterBuilder {
+    pub fn arch(mut self, arch: impl Into<String>) -> Self {
+        self.arch = Some(arch.into());
+        self
+    }
+
+    pub fn version(mut self, version: impl Into<KernelVersion>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    pub fn kconfig_path(mut self, kconfig_path: impl Into<PathBuf>) -> Self {
+        self.kconfig_path = Some(kconfig_path.into());
+        self
+    }
+
+    // `source` paths are resolved below it, whatever the directory is called
+    pub fn kernel_root(mut self, kernel_root: impl Into<PathBuf>) -> Self {
+        self.kernel_root = Some(kernel_root.into());
+        self
+    }
+
+    // follow every `source`, not only the ones below `source_include`
+    pub fn check_all(mut self, check_all: bool) -> Self {
+        self.check_all = check_all;
+        self
+    }
+
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    // per-file messages are only counted
+    pub fn summarize(mut self, summarize: bool) -> Self {
+        self.summarize = summarize;
+        self
+    }
+
+    pub fn source_include(mut self, source_include: Vec<String>) -> Self {
+        self.source_include = source_include;
+        self
+    }
+
+    pub fn group_by(mut self, group_by: Option<ComponentGroup>) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn defconfig(mut self, defconfig: impl Into<PathBuf>) -> Self {
+        self.defconfig = Some(defconfig.into());
+        self
+    }
+
+    pub fn exclude(mut self, path: impl Into<PathBuf>) -> Self {
+        self.exclude.push(path.into());
+        self
+    }
+
+    // without the snippet text only the guarded line counts are kept
+    pub fn store_snippets(mut self, store_snippets: bool) -> Self {
+        self.store_snippets = store_snippets;
+        self
+    }
+
+    pub fn kconfig_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.kconfig_env.insert(name.into(), value.into());
+        self
+    }
+
+    // print paths below the kernel root instead of as they were read
+    pub fn relative_paths(mut self, relative_paths: bool) -> Self {
+        self.relative_paths = relative_paths;
+        self
+    }
+
+    // `false` parses the top Kconfig only, its `source` lines are just recorded
+    pub fn recurse_source(mut self, recurse_source: bool) -> Self {
+        self.recurse_source = recurse_source;
+        self
+    }
+
+    // Kconfig files and code are read from `tree` instead of the disk
+    pub fn source_tree(mut self, tree: Rc<dyn SourceTree>) -> Self {
+        self.tree = Some(tree);
+        self
+    }
+
+    // once `stop` is raised the code analysis reads no further file
+    pub fn stop_flag(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn build(self) -> Result<KconfigCounter> {
+        let arch = self.arch.ok_or(Error::MissingOption("arch"))?;
+        let version = self.version.ok_or(Error::MissingOption("version"))?;
+        let kconfig_path = self
+            .kconfig_path
+            .ok_or(Error::MissingOption("kconfig_path"))?;
+        if let Some(defconfig) = &self.defconfig {
+            fs::metadata(defconfig).map_err(Error::io(defconfig))?;
+        }
+
+        let mut kc = KconfigCounter::new(arch, version, kconfig_path);
+        kc.check_all = self.check_all;
+        kc.sorted = self.sorted;
+        kc.summarize = self.summarize;
+        kc.source_include = self.source_include;
+        kc.group_by = self.group_by;
+        kc.defconfig = self.defconfig;
+        kc.exclude = self.exclude;
+        kc.store_snippets = self.store_snippets;
+        kc.kconfig_env = self.kconfig_env;
+        kc.relative_paths = self.relative_paths;
+        kc.recurse_source = self.recurse_source;
+        kc.kernel_root = self.kernel_root;
+        if let Some(tree) = self.tree {
+            kc.tree = tree;
+        }
+        if let Some(stop) = self.stop {
+            kc.stop = stop;
+        }
+        Ok(kc)
+    }
 }
 
 impl KconfigCounter {
-    pub fn new(arch: String, version: String, kconfig_path: PathBuf) -> Self {
+    pub fn new(arch: String, version: KernelVersion, kconfig_path: PathBuf) -> Self {
         KconfigCounter {
             arch,
             version,
             kconfig_path,
+            kernel_root: None,
             check_all: false,
+            sorted: false,
+            source_include: vec!["arch/".to_string()],
+            group_by: None,
+            defconfig: None,
+            exclude: Vec::new(),
+            store_snippets: true,
+            kconfig_env: HashMap::new(),
             component: HashMap::new(),
-            code_dir: HashSet::new(),
+            choices: Vec::new(),
+            code_dir: BTreeSet::new(),
+            recurse_source: true,
+            skipped_sources: Vec::new(),
             total_components: 0,
             total_code_lines: 0,
+            help_lines: 0,
+            structural_lines: 0,
+            if_conditions: Vec::new(),
+            summarize: false,
+            progress: PhaseCounts::default(),
+            relative_paths: false,
+            tree: Rc::new(FsTree),
+            stop: Arc::default(),
+        }
+    }
+
+    pub fn builder() -> KconfigCounterBuilder {
+        KconfigCounterBuilder::default()
+    }
+
+    // the defconfig given to the builder, compared against another one by `defconfig_report`
+    pub fn defconfig(&self) -> Option<&Path> {
+        self.defconfig.as_deref()
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|excluded| path.starts_with(excluded))
+    }
+
+    // unknown variables are left in place, the missing file is reported as usual
+    fn expand_env(&self, source_path: &str) -> String {
+        let mut expanded = source_path.to_string();
+        for (name, value) in &self.kconfig_env {
+            expanded = expanded.replace(&format!("$({})", name), value);
+        }
+        expanded
+    }
+
+    /// Every parsed component with its statistics, in no particular order.
+    ///
+    /// ```
+    /// use auto_script::KconfigCounter;
+    /// use std::fs;
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let kconfig = dir.path().join("Kconfig");
+    /// fs::write(&kconfig, "config SMP\n\tbool \"smp\"\n\tdepends on MMU\n").unwrap();
+    ///
+    /// let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), kconfig);
+    /// kc.parse_kconfig().unwrap();
+    /// let (name, stat) = kc.components().next().unwrap();
+    /// assert_eq!(name, "SMP");
+    /// assert_eq!(stat.depends(), ["MMU"]);
+    /// ```
+    pub fn components(&self) -> impl Iterator<Item = (&str, &KconfigStat)> {
+        self.component
+            .iter()
+            .map(|(name, stat)| (name.as_str(), stat))
+    }
+
+    // directories whose code `analyze_code` walks, one per followed `source`
+    pub fn code_dirs(&self) -> impl Iterator<Item = &Path> {
+        self.code_dir.iter().map(PathBuf::as_path)
+    }
+
+    pub fn skipped_sources(&self) -> &[PathBuf] {
+        &self.skipped_sources
+    }
+
+    pub fn progress(&self) -> PhaseCounts {
+        PhaseCounts {
+            components: self.component.len(),
+            ..self.progress
+        }
+    }
+
+    // `source` paths are relative to the kernel root, e.g. "arch/riscv/kvm/Kconfig"
+    fn follow_source(&self, source_path: &str) -> bool {
+        self.check_all
+            || self
+                .source_include
+                .iter()
+                .any(|prefix| source_path.starts_with(prefix.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.component.is_empty()
+    }
+
+    pub fn total_code_lines(&self) -> usize {
+        self.total_code_lines
+    }
+
+    pub fn metrics(&self) -> BTreeMap<String, u64> {
+        let mut metrics = BTreeMap::new();
+        metrics.insert("components".to_string(), self.component.len() as u64);
+        metrics.insert("choices".to_string(), self.choices.len() as u64);
+        metrics.insert("code_lines".to_string(), self.total_code_lines as u64);
+        metrics.insert("help_lines".to_string(), self.help_lines as u64);
+        metrics.insert("structural_lines".to_string(), self.structural_lines as u64);
+        for (value_type, count) in self.type_histogram() {
+            metrics.insert(format!("type.{}", value_type), count as u64);
+        }
+        metrics
+    }
+
+    pub fn export(&self) -> KconfigExport {
+        KconfigExport {
+            arch: self.arch.clone(),
+            version: self.version.to_string(),
+            components: self
+                .component
+                .iter()
+                .map(|(name, stat)| (name.clone(), stat.clone()))
+                .collect(),
+            choices: self.choices.clone(),
+            directories: self
+                .dir_components()
+                .into_iter()
+                .map(|(dir, names)| (dir, names.into_iter().map(String::from).collect()))
+                .collect(),
+        }
+    }
+
+    pub fn from_export(export: KconfigExport) -> Self {
+        let mut kc = KconfigCounter::new(export.arch, export.version.into(), PathBuf::new());
+        kc.total_components = export.components.len();
+        kc.total_code_lines = export
+            .components
+            .values()
+            .flat_map(|stat| &stat.code_snippets)
+            .map(|snippet| snippet.lines)
+            .sum();
+        kc.component = export.components.into_iter().collect();
+        kc.choices = export.choices;
+        kc
+    }
+
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    pub fn code_delta(&self, other: &KconfigCounter) -> CodeDelta {
+        let line_delta = |name: &String, old: usize, new: usize| LineDelta {
+            name: name.clone(),
+            old,
+            new,
+            delta: new as i64 - old as i64,
+        };
+        let mut delta = CodeDelta {
+            old: self.label(),
+            new: other.label(),
+            changed: Vec::new(),
+            unchanged: 0,
+            only_old: Vec::new(),
+            only_new: Vec::new(),
+        };
+        for (name, stat) in &self.component {
+            match other.component.get(name) {
+                Some(other_stat) if stat.code_lines() == other_stat.code_lines() => {
+                    delta.unchanged += 1
+                }
+                Some(other_stat) => {
+                    delta
+                        .changed
+                        .push(line_delta(name, stat.code_lines(), other_stat.code_lines()))
+                }
+                None => delta.only_old.push(line_delta(name, stat.code_lines(), 0)),
+            }
+        }
+        for (name, stat) in &other.component {
+            if !self.component.contains_key(name) {
+                delta.only_new.push(line_delta(name, 0, stat.code_lines()));
+            }
+        }
+        for deltas in [&mut delta.changed, &mut delta.only_old, &mut delta.only_new] {
+            deltas.sort_by(|a, b| {
+                b.delta
+                    .abs()
+                    .cmp(&a.delta.abs())
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+        delta
+    }
+
+    // (symbol, missing dependency) pairs sorted by symbol, a symbol defined in a Kconfig
+    // that was not followed (see `--full`) is reported as missing too
+    pub fn find_dangling_depends(&self) -> Vec<(String, String)> {
+        let mut dangling = BTreeSet::new();
+        for (name, stat) in &self.component {
+            // a condition that fails to parse, e.g. one calling `$(...)`, names no symbol
+            let depends = stat
+                .depend
+                .iter()
+                .filter_map(|depend| Expr::parse(depend).ok());
+            for depend in depends {
+                for symbol in depend.symbols() {
+                    if !self.component.contains_key(symbol) {
+                        dangling.insert((name.clone(), symbol.to_string()));
+                    }
+                }
+            }
+        }
+        dangling.into_iter().collect()
+    }
+
+    // symbols with more than one unconditional `default`, or one `if` guard given twice;
+    // only the first of them can ever apply
+    pub fn find_default_conflicts(&self) -> Vec<String> {
+        let mut conflicts: Vec<String> = self
+            .component
+            .iter()
+            .filter(|(_, stat)| {
+                let mut guards = HashSet::new();
+                // a default that fails to parse, e.g. one calling `$(...)`, is left out
+                !stat
+                    .default_value
+                    .iter()
+                    .filter_map(|default| Conditional::parse(default).ok())
+                    .all(|default| guards.insert(default.condition))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        conflicts.sort();
+        conflicts
+    }
+
+    pub fn dangling_report(&self) -> DanglingReport {
+        DanglingReport {
+            label: self.label(),
+            dangling: self.find_dangling_depends(),
         }
     }
 
-    pub fn set_check_all(&mut self) {
-        self.check_all = true;
+    // the `n` longest guarded blocks, ties are ordered by component name
+    pub fn top_snippets(&self, n: usize) -> Vec<(&str, &CodeSnippet)> {
+        let mut snippets: Vec<(&str, &CodeSnippet)> = self
+            .component
+            .iter()
+            .flat_map(|(name, stat)| {
+                stat.code_snippets
+                    .iter()
+                    .map(move |snippet| (name.as_str(), snippet))
+            })
+            .collect();
+        snippets.sort_by(|(a_name, a), (b_name, b)| {
+            b.lines
+                .cmp(&a.lines)
+                .then(a_name.cmp(b_name))
+                .then(a.file.cmp(&b.file))
+        });
+        snippets.truncate(n);
+        snippets
+    }
+
+    pub fn snippet_report(&self, n: usize) -> SnippetReport {
+        let kernel_root = self.kernel_root();
+        SnippetReport {
+            label: self.label(),
+            snippets: self
+                .top_snippets(n)
+                .into_iter()
+                .map(|(name, snippet)| SnippetRow {
+                    component: name.to_string(),
+                    lines: snippet.lines,
+                    file: snippet
+                        .file
+                        .strip_prefix(&kernel_root)
+                        .unwrap_or(&snippet.file)
+                        .to_path_buf(),
+                })
+                .collect(),
+        }
+    }
+
+    // the directories below the kernel root each component guards code or builds objects in
+    pub fn component_dirs(&self) -> BTreeMap<&str, BTreeSet<PathBuf>> {
+        let root = self.kernel_root();
+        let mut dirs = BTreeMap::new();
+        for (name, stat) in &self.component {
+            let files = stat
+                .code_snippets
+                .iter()
+                .map(|snippet| &snippet.file)
+                .chain(&stat.objects);
+            let touched: BTreeSet<PathBuf> = files
+                .filter_map(|file| file.parent())
+                .map(|dir| dir.strip_prefix(&root).unwrap_or(dir).to_path_buf())
+                .collect();
+            if !touched.is_empty() {
+                dirs.insert(name.as_str(), touched);
+            }
+        }
+        dirs
+    }
+
+    // `component_dirs` the other way around
+    pub fn dir_components(&self) -> BTreeMap<PathBuf, BTreeSet<&str>> {
+        let mut components: BTreeMap<PathBuf, BTreeSet<&str>> = BTreeMap::new();
+        for (name, dirs) in self.component_dirs() {
+            for dir in dirs {
+                components.entry(dir).or_default().insert(name);
+            }
+        }
+        components
+    }
+
+    // the most configurable directories first, ties in path order
+    pub fn dir_report(&self) -> DirReport {
+        let mut dirs: Vec<DirRow> = self
+            .dir_components()
+            .into_iter()
+            .map(|(dir, names)| DirRow {
+                dir: match dir.as_os_str().is_empty() {
+                    true => ".".to_string(),
+                    false => dir.to_string_lossy().into_owned(),
+                },
+                components: names.into_iter().map(String::from).collect(),
+            })
+            .collect();
+        dirs.sort_by_key(|row| Reverse(row.components.len()));
+        DirReport {
+            version: self.version.to_string(),
+            arch: self.arch.clone(),
+            dirs,
+        }
+    }
+
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for (name, stat) in &self.component {
+            graph.add_node(name);
+            // expressions that fail to parse, e.g. ones calling `$(...)`, add no edge
+            for depend in stat
+                .depend
+                .iter()
+                .filter_map(|depend| Expr::parse(depend).ok())
+            {
+                for symbol in depend.symbols() {
+                    graph.add_edge(name, symbol, EdgeKind::Depends);
+                }
+            }
+            // `select FOO if BAR` only selects FOO
+            for select in stat
+                .select
+                .iter()
+                .filter_map(|select| Conditional::parse(select).ok())
+            {
+                for symbol in select.value.symbols() {
+                    graph.add_edge(name, symbol, EdgeKind::Select);
+                }
+            }
+            for default in stat
+                .default_value
+                .iter()
+                .filter_map(|default| Conditional::parse(default).ok())
+            {
+                for symbol in default.symbols() {
+                    graph.add_edge(name, symbol, EdgeKind::Default);
+                }
+            }
+        }
+        graph
+    }
+
+    pub fn label(&self) -> String {
+        format!("Linux-{} {}", self.version, self.arch)
+    }
+
+    pub fn diff_components(&self, other: &KconfigCounter) -> ComponentDiff {
+        let mut removed: Vec<String> = self
+            .component
+            .keys()
+            .filter(|name| !other.component.contains_key(*name))
+            .cloned()
+            .collect();
+        let mut added: Vec<String> = other
+            .component
+            .keys()
+            .filter(|name| !self.component.contains_key(*name))
+            .cloned()
+            .collect();
+        removed.sort();
+        added.sort();
+
+        let mut changed = Vec::new();
+        let mut shared: Vec<&String> = self
+            .component
+            .keys()
+            .filter(|name| other.component.contains_key(*name))
+            .collect();
+        shared.sort();
+        for name in shared {
+            let changes = attribute_changes(&self.component[name], &other.component[name]);
+            if !changes.is_empty() {
+                changed.push(ChangedComponent {
+                    name: name.clone(),
+                    changes,
+                });
+            }
+        }
+
+        ComponentDiff {
+            old: self.label(),
+            new: other.label(),
+            removed,
+            added,
+            changed,
+        }
+    }
+
+    // without a root from the builder, the `linux-<version>` directory above the Kconfig
+    fn kernel_root(&self) -> PathBuf {
+        if let Some(kernel_root) = &self.kernel_root {
+            return kernel_root.clone();
+        }
+        let mut kernel_path = self.kconfig_path.clone();
+        // `linux-6.10.0-rc3` as configured or `linux-6.10-rc3` as unpacked from kernel.org
+        let dir_names = [
+            format!("linux-{}", self.version.plain()),
+            self.version.tarball_dir(),
+        ];
+
+        while let Some(parent) = kernel_path.parent() {
+            if dir_names.iter().any(|name| parent.ends_with(name)) {
+                kernel_path = parent.to_path_buf();
+                break;
+            }
+            kernel_path = parent.to_path_buf();
+        }
+        kernel_path
+    }
+
+    pub(crate) fn display_path(&self, path: &Path) -> String {
+        let root = self.relative_paths.then(|| self.kernel_root());
+        display_path(path, root.as_deref()).to_string()
     }
 
     pub fn parse_kconfig(&mut self) -> Result<()> {
-        self.parse_kconfig_path(&self.kconfig_path.clone())
+        if !self.tree.exists(&self.kconfig_path) {
+            return Err(Error::ArchNotFound {
+                arch: self.arch.clone(),
+                path: self.kconfig_path.clone(),
+            });
+        }
+        self.parse_kconfig_path(&self.kconfig_path.clone())?;
+        info!(arch = self.arch.as_str(); "{}", self.progress());
+        Ok(())
+    }
+
+    pub fn parse_kconfig_path(&mut self, kconfig_path: &Path) -> Result<()> {
+        let tree = self.tree.clone();
+        let reader = tree.read(kconfig_path).map_err(Error::io(kconfig_path))?;
+        self.parse_kconfig_reader(reader, kconfig_path)
     }
 
-    pub fn parse_kconfig_path(&mut self, kconfig_path: &PathBuf) -> Result<()> {
-        let file = File::open(kconfig_path)?;
-        let reader = io::BufReader::new(file);
+    /// Parses Kconfig text read from `reader`, `kconfig_path` is recorded as the file defining
+    /// its components and names it in errors. `source` lines are still resolved in the
+    /// counter's source tree, the disk unless the builder was given another one.
+    ///
+    /// ```
+    /// use auto_script::{KconfigComponentType, KconfigCounter};
+    /// use std::path::PathBuf;
+    ///
+    /// let kconfig = PathBuf::from("arch/riscv/Kconfig");
+    /// let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), kconfig.clone());
+    /// let text = "config NR_CPUS\n\tint \"cpus\"\n\tdefault \"8\"\n";
+    /// kc.parse_kconfig_reader(text.as_bytes(), &kconfig).unwrap();
+    /// let (_, nr_cpus) = kc.components().next().unwrap();
+    /// assert_eq!(nr_cpus.value_type(), KconfigComponentType::Int);
+    /// ```
+    pub fn parse_kconfig_reader(
+        &mut self,
+        reader: impl BufRead,
+        kconfig_path: &Path,
+    ) -> Result<()> {
+        self.progress.kconfig_files += 1;
 
+        // an unbalanced `endif` must not close the blocks of the sourcing file
+        let if_depth = self.if_conditions.len();
         let mut component_name = String::new();
-        let mut update = false;
+        let mut current_choice: Option<usize> = None;
+        // indentation of the `help` keyword and of the first help text line
+        let mut help: Option<(usize, Option<usize>)> = None;
 
-        for line in reader.lines() {
-            let line = line?;
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::io(kconfig_path))?;
+            let line = if index == 0 { strip_bom(&line) } else { &line };
             let trim_line = line.trim();
+
+            // help text ends at the first line indented less than its first line
+            if let Some((keyword_indent, text_indent)) = &mut help {
+                if trim_line.is_empty() {
+                    continue;
+                }
+                let indent = indentation(line);
+                let text_indent = *text_indent.get_or_insert(indent);
+                if indent > *keyword_indent && indent >= text_indent {
+                    self.help_lines += 1;
+                    continue;
+                }
+                help = None;
+            }
+
             if trim_line.starts_with('#') {
                 continue;
             }
+            if !trim_line.is_empty() {
+                self.structural_lines += 1;
+            }
 
             if trim_line.starts_with("source") {
-                let mut kernel_path = self.kconfig_path.clone();
-                let kernel_version = format!("linux-{}", self.version);
+                let kernel_path = self.kernel_root();
+                let source_path = get_filed(trim_line, "source");
+                let source_path = self.expand_env(source_path.trim_matches('"'));
+                let source_kconfig = kernel_path.join(&source_path);
 
-                while let Some(parent) = kernel_path.parent() {
-                    if parent.ends_with(&kernel_version) {
-                        kernel_path = parent.to_path_buf();
-                        break;
+                if self.is_excluded(&source_kconfig) {
+                    if !self.summarize {
+                        info!(arch = self.arch.as_str(); "skip excluded Kconfig -> {:?}", source_kconfig);
                     }
-                    kernel_path = parent.to_path_buf();
-                }
-                let source_path = get_filed(trim_line, "source");
-                let source_path = source_path.trim_matches('"');
-                let mut kconfig_path = kernel_path;
-                kconfig_path.push(source_path);
-                kconfig_path.canonicalize().unwrap();
-
-                if self.check_all || kconfig_path.to_str().unwrap_or("").contains("/arch/") {
-                    warn!("fetch a new Kconfig -> {:?}", kconfig_path);
-                    info!(
-                        "entering the Kconfig of corresponding architecture -> {}",
-                        self.arch
-                    );
-                    self.code_dir
-                        .insert(kconfig_path.clone().parent().unwrap().to_path_buf());
-                    self.parse_kconfig_path(&kconfig_path);
-                } else if self.check_all {
-                    warn!("fetch a new Kconfig -> {:?}", kconfig_path);
-                    self.code_dir
-                        .insert(kconfig_path.clone().parent().unwrap().to_path_buf());
-                    self.parse_kconfig_path(&kconfig_path);
+                } else if !self.recurse_source {
+                    self.skipped_sources.push(source_kconfig);
+                } else if self.follow_source(&source_path) {
+                    if !self.tree.exists(&source_kconfig) {
+                        return Err(Error::KconfigSourceMissing {
+                            referenced_from: kconfig_path.to_path_buf(),
+                            path: source_kconfig,
+                        });
+                    }
+                    if !self.summarize {
+                        info!(arch = self.arch.as_str(); "fetch a new Kconfig -> {:?}", source_kconfig);
+                        debug!(
+                            "entering the Kconfig of corresponding architecture -> {}",
+                            self.arch
+                        );
+                    }
+                    if let Some(parent) = source_kconfig.parent() {
+                        self.code_dir.insert(parent.to_path_buf());
+                    }
+                    self.parse_kconfig_path(&source_kconfig)?;
                 }
             }
 
             if trim_line.is_empty() {
-                update = true;
                 continue;
             }
 
-            if update && trim_line.starts_with("config") {
-                // println!("{}", trim_line);
-                component_name = get_filed(trim_line, "config");
-                update = false;
+            let keyword = trim_line.split_whitespace().next().unwrap_or("");
+            if keyword == "help" || keyword == "---help---" {
+                help = Some((indentation(line), None));
+                continue;
+            }
+            if keyword == "if" {
+                self.if_conditions.push(get_filed(trim_line, "if"));
+                component_name.clear();
+                continue;
+            }
+            if keyword == "endif" {
+                if self.if_conditions.len() > if_depth {
+                    self.if_conditions.pop();
+                }
+                component_name.clear();
+                continue;
+            }
+            if keyword == "choice" {
+                current_choice = Some(self.choices.len());
+                self.choices.push(KconfigChoice::default());
+                component_name.clear();
+                continue;
+            }
+            if keyword == "endchoice" {
+                current_choice = None;
+                component_name.clear();
+                continue;
+            }
+            // choice header lines come before the first member
+            if let (Some(index), true) = (current_choice, component_name.is_empty()) {
+                let choice = &mut self.choices[index];
+                match keyword {
+                    "prompt" => {
+                        let prompt = get_filed(trim_line, "prompt");
+                        choice.prompt = Some(prompt.trim_matches('"').to_string());
+                    }
+                    "default" => choice.default = Some(get_filed(trim_line, "default")),
+                    _ => {}
+                }
             }
 
             if trim_line.starts_with("config ") {
                 component_name = get_filed(trim_line, "config");
-                info!("fetch the component name -> {}", component_name);
+                if !self.summarize {
+                    debug!(arch = self.arch.as_str(); "fetch the component name -> {}", component_name);
+                }
 
                 let entry = self
                     .component
@@ -128,17 +1344,35 @@ impl KconfigCounter {
                             default_value: Vec::new(),
                             select: Vec::new(),
                             depend: Vec::new(),
-                            value_type: KconfigComponentType::Value,
+                            value_type: KconfigComponentType::Unknown,
                             count: 0,
                             code_snippets: Vec::new(),
+                            kconfig_file: kconfig_path.to_path_buf(),
+                            choice: None,
+                            makefile_lines: 0,
+                            prompt: None,
+                            visible_if: None,
+                            objects: Vec::new(),
+                            maintainers: Vec::new(),
                         }
                     });
 
                 entry.count += 1;
+                // `if` blocks add their condition to every enclosed symbol
+                for condition in &self.if_conditions {
+                    if !entry.depend.contains(condition) {
+                        entry.depend.push(condition.clone());
+                    }
+                }
+                if let Some(index) = current_choice {
+                    entry.choice = Some(index);
+                    self.choices[index].members.push(component_name.clone());
+                }
             }
 
             if trim_line.starts_with("depends on") {
-                info!(
+                trace!(
+                    arch = self.arch.as_str();
                     "fetch the component {} depend on -> {}",
                     component_name,
                     get_filed(trim_line, "depends on")
@@ -148,9 +1382,23 @@ impl KconfigCounter {
                 }
             }
 
-            if trim_line.starts_with("bool") {
+            if let Some(value_type) = KconfigComponentType::from_keyword(keyword) {
                 if let Some(stat) = self.component.get_mut(&component_name) {
-                    stat.value_type = KconfigComponentType::Bool;
+                    stat.value_type = value_type;
+                }
+            }
+
+            // `bool "Foo" if X` and `prompt "Foo" if X`, `def_bool y if X` is a default
+            if keyword == "prompt"
+                || (KconfigComponentType::from_keyword(keyword).is_some()
+                    && !keyword.starts_with("def_"))
+            {
+                if let Some(stat) = self.component.get_mut(&component_name) {
+                    let (prompt, condition) = split_prompt(&get_filed(trim_line, keyword));
+                    if prompt.is_some() {
+                        stat.prompt = prompt;
+                        stat.visible_if = condition;
+                    }
                 }
             }
 
@@ -160,16 +1408,16 @@ impl KconfigCounter {
                 }
             }
 
-            if trim_line.starts_with("def_bool") {
+            if keyword == "def_bool" || keyword == "def_tristate" {
                 if let Some(stat) = self.component.get_mut(&component_name) {
                     stat.default_value.clear();
-                    stat.default_value.push(get_filed(trim_line, "def_bool"));
-                    stat.value_type = KconfigComponentType::Bool;
+                    stat.default_value.push(get_filed(trim_line, keyword));
                 }
             }
 
             if trim_line.starts_with("select") {
-                info!(
+                trace!(
+                    arch = self.arch.as_str();
                     "fetch the component {} select -> {}",
                     component_name,
                     get_filed(trim_line, "select")
@@ -181,77 +1429,181 @@ impl KconfigCounter {
             }
         }
 
+        self.if_conditions.truncate(if_depth);
         Ok(())
     }
 
-    pub fn analyze_code(&mut self) {
-        info!("code path directory to retrieve: {:#?}", self.code_dir);
-        for path in &self.code_dir.clone() {
-            self.analyze_code_path(path).unwrap()
+    pub fn analyze_code(&mut self) -> Result<()> {
+        let dirs: Vec<PathBuf> = self.code_dir.iter().cloned().collect();
+        self.analyze_code_dirs(&dirs)
+    }
+
+    pub fn analyze_code_dirs(&mut self, dirs: &[PathBuf]) -> Result<()> {
+        info!(arch = self.arch.as_str(); "code path directory to retrieve: {:#?}", dirs);
+        // a directory nested in another one is already walked through its parent
+        for path in dirs {
+            if dirs.iter().any(|dir| dir != path && path.starts_with(dir)) {
+                continue;
+            }
+            self.analyze_code_path(path)?;
         }
+        info!(arch = self.arch.as_str(); "{}", self.progress());
+        Ok(())
     }
 
-    pub fn analyze_code_path(&mut self, code_dir: &PathBuf) -> Result<()> {
-        for entry in fs::read_dir(code_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
+    pub fn analyze_code_path(&mut self, code_dir: &Path) -> Result<()> {
+        let tree = self.tree.clone();
+        for entry in read_dir(tree.as_ref(), code_dir, self.sorted).map_err(Error::io(code_dir))? {
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let path = entry.path;
+            if self.is_excluded(&path) {
+                self.progress.skipped += 1;
+            } else if entry.is_dir {
                 self.analyze_code_path(&path)?;
             } else {
                 match path.extension().and_then(|s| s.to_str()) {
                     Some("c") | Some("h") => self.parse_code(&path)?,
-                    _ => {}
+                    None if path.ends_with("Makefile") || path.ends_with("Kbuild") => {
+                        self.parse_makefile(&path)?
+                    }
+                    _ => self.progress.skipped += 1,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // `obj-$(CONFIG_FOO) += foo.o` and `ifdef CONFIG_FOO` lines count towards FOO
+    pub fn parse_makefile(&mut self, file_path: &Path) -> Result<()> {
+        if !self.summarize {
+            debug!(arch = self.arch.as_str(); "start to parse -> {:?}", file_path);
+        }
+        let tree = self.tree.clone();
+        let reader = tree.read(file_path).map_err(Error::io(file_path))?;
+        self.parse_makefile_reader(reader, file_path)
+    }
+
+    // objects are recorded relative to the directory of `file_path`
+    pub fn parse_makefile_reader(&mut self, reader: impl BufRead, file_path: &Path) -> Result<()> {
+        self.progress.code_files += 1;
+        let dir = file_path.parent().unwrap_or(Path::new(""));
+
+        // the symbols of an assignment continued with `\` on the next line
+        let mut continued: BTreeSet<String> = BTreeSet::new();
+        for line in reader.lines() {
+            let line = line.map_err(Error::io(file_path))?;
+            if line.trim_start().starts_with('#') {
+                continued.clear();
+                continue;
+            }
+            let symbols = config_symbols(&line);
+            for symbol in &symbols {
+                if let Some(stat) = self.component.get_mut(symbol) {
+                    stat.makefile_lines += 1;
+                }
+            }
+
+            let (owners, objects) = match line.split_once("+=").or_else(|| line.split_once(":=")) {
+                _ if !continued.is_empty() => (std::mem::take(&mut continued), line.as_str()),
+                Some((target, objects)) => (config_symbols(target), objects),
+                None => continue,
+            };
+            for object in objects
+                .split_whitespace()
+                .filter(|word| word.ends_with(".o"))
+            {
+                for owner in &owners {
+                    if let Some(stat) = self.component.get_mut(owner) {
+                        let object = dir.join(object);
+                        if !stat.objects.contains(&object) {
+                            stat.objects.push(object);
+                        }
+                    }
                 }
             }
+            if line.trim_end().ends_with('\\') {
+                continued = owners;
+            }
         }
         Ok(())
     }
 
     pub fn parse_code(&mut self, file_path: &PathBuf) -> Result<()> {
-        info!("start to parse -> {:?}", file_path);
-        let file = File::open(file_path)?;
-        let reader = io::BufReader::new(file);
-        let mut in_config_block = false;
-        let mut component_name = String::new();
+        if !self.summarize {
+            debug!(arch = self.arch.as_str(); "start to parse -> {:?}", file_path);
+        }
+        let tree = self.tree.clone();
+        let reader = tree.read(file_path).map_err(Error::io(file_path))?;
+        self.parse_code_reader(reader, file_path)
+    }
+
+    // `file_path` is recorded on every snippet and names the file in errors
+    pub fn parse_code_reader(&mut self, reader: impl BufRead, file_path: &Path) -> Result<()> {
+        self.progress.code_files += 1;
+        let mut config_block: Option<(usize, String, bool)> = None;
         let mut snippet = String::new();
         let mut snippet_line_count = 0;
+        let mut snippet_start = 0;
         let mut ifdef_stack = Vec::new();
 
-        for line in reader.lines() {
-            let line = line?;
-            if line.contains("#ifdef CONFIG_") {
-                component_name = get_filed(line.trim(), "#ifdef CONFIG_");
-                info!("find config -> {}", component_name);
-                if self.component.contains_key(&component_name) {
-                    // info!("can entry?");
-                    in_config_block = true;
-                    snippet.push_str(&line);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::io(file_path))?;
+            let line = if index == 0 { strip_bom(&line) } else { &line };
+            let trim_line = line.trim();
+            if trim_line.starts_with("#if") {
+                let guard = self.config_guard(trim_line);
+                if let Some((component_name, module)) = &guard {
+                    trace!(arch = self.arch.as_str(); "find config -> {} (module: {})", component_name, module);
+                }
+                match (&config_block, guard) {
+                    (None, Some((component_name, module))) => {
+                        config_block = Some((ifdef_stack.len(), component_name, module));
+                        snippet_start = index + 1;
+                        ifdef_stack.push(true);
+                    }
+                    (_, guard) => ifdef_stack.push(guard.is_some()),
+                }
+                if config_block.is_some() {
+                    snippet.push_str(line);
                     snippet.push('\n');
                     snippet_line_count += 1;
                 }
-                ifdef_stack.push(component_name.clone());
-            } else if line.contains("#endif") {
-                if !ifdef_stack.is_empty() {
-                    let last_component = ifdef_stack.pop().unwrap();
-                    if ifdef_stack.is_empty() {
-                        in_config_block = false;
-                        if let Some(stat) = self.component.get_mut(&last_component) {
-                            stat.code_snippets.push(snippet.clone());
+            } else if trim_line.starts_with("#endif") {
+                if ifdef_stack.pop().is_none() {
+                    continue;
+                }
+                match &config_block {
+                    Some((depth, component_name, module)) if *depth == ifdef_stack.len() => {
+                        if let Some(stat) = self.component.get_mut(component_name) {
+                            stat.code_snippets.push(CodeSnippet {
+                                code: if self.store_snippets {
+                                    snippet.clone()
+                                } else {
+                                    String::new()
+                                },
+                                lines: snippet_line_count,
+                                module: *module,
+                                file: file_path.to_path_buf(),
+                                line: snippet_start,
+                            });
                         }
-                        // info!("fetch the snippet code: \n{}", snippet);
                         self.total_code_lines += snippet_line_count;
 
+                        config_block = None;
                         snippet.clear();
                         snippet_line_count = 0;
-                    } else {
-                        snippet.push_str(&line);
+                    }
+                    Some(_) => {
+                        snippet.push_str(line);
                         snippet.push('\n');
                         snippet_line_count += 1;
                     }
+                    None => {}
                 }
-            } else if in_config_block {
-                // info!("get the line -> {}", line);
-                snippet.push_str(&line);
+            } else if config_block.is_some() {
+                snippet.push_str(line);
                 snippet.push('\n');
                 snippet_line_count += 1;
             }
@@ -260,55 +1612,655 @@ impl KconfigCounter {
         Ok(())
     }
 
-    pub fn print(&self) {
-        println!("{:-<90}", "");
-        println!(
-            "{:^90}",
-            format!("Linux-{} Arch {}", self.version, self.arch.to_uppercase())
-        );
-        println!("{:-<90}", "");
-        println!("{:^45} {:^45}", "Component", "Component");
-        println!("{:-<90}", "");
-        let mut iter = self.component.keys();
-        while let Some(name1) = iter.next() {
-            let unwrap = String::new();
-            let name2 = iter.next().unwrap_or(&unwrap);
-            println!("{:^45} | {:^45}", name1, name2);
-        }
-        println!("{:-<90}", "");
-        println!("{:^45} {:>20} Components", "SUM:", self.component.len());
-        println!("{:-<90}", "");
-        println!(
-            "{:^45} {:>20} Total Code Lines",
-            "SUM:", self.total_code_lines
-        );
-        println!("{:-<90}", "");
-
-        let mut input = String::new();
-        loop {
-            print!("Enter a component name to view its details (or 'q' to quit)>> ");
-            io::stdout().flush().unwrap();
-            input.clear();
-            io::stdin().read_line(&mut input).unwrap();
-            let input = input.trim();
-
-            if input.eq_ignore_ascii_case("q") {
-                break;
+    // map the first `CONFIG_` symbol of an `#ifdef`/`#if` directive to a parsed component,
+    // `CONFIG_FOO_MODULE` belongs to the tristate `FOO` and marks module-only code;
+    // negated guards like `#if !defined(CONFIG_FOO)` hold the code without `FOO`, as `#ifndef` does
+    fn config_guard(&self, directive: &str) -> Option<(String, bool)> {
+        if directive.starts_with("#ifndef") {
+            return None;
+        }
+        let start = directive.find("CONFIG_")?;
+        if negated(&directive[..start]) {
+            return None;
+        }
+        let start = start + "CONFIG_".len();
+        let symbol: String = directive[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+
+        if self.component.contains_key(&symbol) {
+            return Some((symbol, false));
+        }
+        match symbol.strip_suffix("_MODULE") {
+            Some(base) if self.component.contains_key(base) => Some((base.to_string(), true)),
+            _ => None,
+        }
+    }
+
+    fn group_key(&self, name: &str, group_by: ComponentGroup) -> String {
+        match group_by {
+            ComponentGroup::Letter => name
+                .chars()
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string())
+                .unwrap_or_default(),
+            ComponentGroup::File => {
+                let kconfig_file = &self.component[name].kconfig_file;
+                kconfig_file
+                    .strip_prefix(self.kernel_root())
+                    .unwrap_or(kconfig_file)
+                    .display()
+                    .to_string()
             }
+            ComponentGroup::Subsystem => self.subsystem(&self.component[name]),
+        }
+    }
+
+    // the top level directory of the Kconfig defining `stat`, e.g. "fs" or "arch";
+    // `drivers/` is split one level deeper into "drivers/net", "drivers/gpu", ...
+    fn subsystem(&self, stat: &KconfigStat) -> String {
+        let root = self.kernel_root();
+        let kconfig_file = stat
+            .kconfig_file
+            .strip_prefix(&root)
+            .unwrap_or(&stat.kconfig_file);
+        let dirs: Vec<String> = kconfig_file
+            .parent()
+            .into_iter()
+            .flat_map(|dir| dir.components())
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        match dirs.as_slice() {
+            [] => ".".to_string(),
+            [drivers, second, ..] if drivers == "drivers" => format!("drivers/{}", second),
+            [top, ..] => top.clone(),
+        }
+    }
 
-            if let Some(stat) = self.component.get(input) {
-                println!("Component: {}", input);
-                println!("  Value Type: {:?}", stat.value_type);
-                println!("  Depends on: {:#?}", stat.depend);
-                println!("  Default value: {:#?}", stat.default_value);
-                println!("  Select: {:#?}", stat.select);
-                println!("  Code Snippets: ");
-                for code_snippet in &stat.code_snippets {
-                    println!("{}", code_snippet);
+    // components, types and guarded lines per `subsystem`, largest subsystem first;
+    // the line totals are left out unless code was analyzed
+    pub fn subsystem_report(&self) -> SubsystemReport {
+        let mut rows: BTreeMap<String, (usize, HashMap<KconfigComponentType, usize>, usize)> =
+            BTreeMap::new();
+        for stat in self.component.values() {
+            let row = rows.entry(self.subsystem(stat)).or_default();
+            row.0 += 1;
+            *row.1.entry(stat.value_type).or_default() += 1;
+            row.2 += stat.code_lines();
+        }
+        let with_code = self.progress.code_files > 0;
+        let mut subsystems: Vec<SubsystemRow> = rows
+            .into_iter()
+            .map(
+                |(subsystem, (components, types, code_lines))| SubsystemRow {
+                    subsystem,
+                    components,
+                    types: KconfigComponentType::ALL
+                        .into_iter()
+                        .map(|value_type| {
+                            (value_type, types.get(&value_type).copied().unwrap_or(0))
+                        })
+                        .collect(),
+                    code_lines: with_code.then_some(code_lines),
+                },
+            )
+            .collect();
+        subsystems.sort_by_key(|row| Reverse(row.components));
+        SubsystemReport {
+            version: self.version.to_string(),
+            arch: self.arch.clone(),
+            subsystems,
+        }
+    }
+
+    pub fn type_histogram(&self) -> HashMap<KconfigComponentType, usize> {
+        let mut histogram = HashMap::new();
+        for stat in self.component.values() {
+            *histogram.entry(stat.value_type).or_default() += 1;
+        }
+        histogram
+    }
+
+    pub fn snapshot(&self) -> AnalysisResult {
+        // components are ordered by name, groups by their header
+        let mut names: Vec<String> = self.component.keys().cloned().collect();
+        names.sort();
+        let groups = match self.group_by {
+            None => vec![ComponentGroupRows {
+                header: None,
+                names,
+            }],
+            Some(group_by) => {
+                let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                for name in names {
+                    groups
+                        .entry(self.group_key(&name, group_by))
+                        .or_default()
+                        .push(name);
                 }
+                groups
+                    .into_iter()
+                    .map(|(header, names)| ComponentGroupRows {
+                        header: Some(header),
+                        names,
+                    })
+                    .collect()
+            }
+        };
+
+        let histogram = self.type_histogram();
+        AnalysisResult::Kconfig(KconfigSummary {
+            version: self.version.clone(),
+            arch: self.arch.clone(),
+            groups,
+            components: self.component.len(),
+            choices: self.choices.len(),
+            code_lines: self.total_code_lines,
+            help_lines: self.help_lines,
+            structural_lines: self.structural_lines,
+            types: KconfigComponentType::ALL
+                .into_iter()
+                .map(|value_type| (value_type, histogram.get(&value_type).copied().unwrap_or(0)))
+                .collect(),
+        })
+    }
+
+    pub fn compare_arches(counters: &[KconfigCounter]) -> ArchComparison {
+        let mut owners: BTreeMap<&String, Vec<&KconfigCounter>> = BTreeMap::new();
+        for kc in counters {
+            for name in kc.component.keys() {
+                owners.entry(name).or_default().push(kc);
+            }
+        }
+
+        let mut comparison = ArchComparison {
+            version: counters
+                .first()
+                .map(|kc| kc.version.to_string())
+                .unwrap_or_default(),
+            arches: counters.iter().map(|kc| kc.arch.clone()).collect(),
+            shared: Vec::new(),
+            partial: Vec::new(),
+            exclusive: counters
+                .iter()
+                .map(|kc| (kc.arch.clone(), Vec::new()))
+                .collect(),
+            divergent: Vec::new(),
+        };
+        for (name, owners) in &owners {
+            match owners.len() {
+                1 => comparison
+                    .exclusive
+                    .entry(owners[0].arch.clone())
+                    .or_default()
+                    .push((*name).clone()),
+                len if len == counters.len() => comparison.shared.push((*name).clone()),
+                _ => comparison.partial.push((*name).clone()),
+            }
+        }
+
+        // a shared symbol with a different type or default usually hides real divergence
+        for name in &comparison.shared {
+            let stats: Vec<(&str, &KconfigStat)> = owners[name]
+                .iter()
+                .map(|kc| (kc.arch.as_str(), &kc.component[name]))
+                .collect();
+            let (_, first) = stats[0];
+            let mut attributes = Vec::new();
+            if stats
+                .iter()
+                .any(|(_, stat)| stat.value_type != first.value_type)
+            {
+                attributes.push("type");
+            }
+            if stats
+                .iter()
+                .any(|(_, stat)| stat.default_value != first.default_value)
+            {
+                attributes.push("default");
+            }
+            if attributes.is_empty() {
+                continue;
+            }
+            comparison.divergent.push(DivergentComponent {
+                name: name.clone(),
+                attributes,
+                values: stats
+                    .into_iter()
+                    .map(|(arch, stat)| ArchValue {
+                        arch: arch.to_string(),
+                        value_type: stat.value_type.to_string(),
+                        default: stat.default_value.join("; "),
+                    })
+                    .collect(),
+            });
+        }
+        comparison
+    }
+
+    // `symbol` in every counter, oldest kernel first; a gap in the history compares
+    // against the last tree that had the symbol
+    pub fn trace(symbol: &str, counters: &mut [KconfigCounter]) -> TraceReport {
+        counters.sort_by(|a, b| a.version.cmp(&b.version));
+
+        let mut steps = Vec::new();
+        let mut previous: Option<&KconfigStat> = None;
+        for kc in counters.iter() {
+            let stat = kc.component.get(symbol);
+            let state = match (previous, stat) {
+                (_, None) => TraceState::Missing,
+                (None, Some(stat)) => TraceState::Present(stat.attributes().to_vec()),
+                (Some(old), Some(new)) => TraceState::Changed(attribute_changes(old, new)),
+            };
+            steps.push(TraceStep {
+                version: kc.version.clone(),
+                state,
+            });
+            previous = stat.or(previous);
+        }
+        TraceReport {
+            symbol: symbol.to_string(),
+            arch: counters
+                .first()
+                .map(|kc| kc.arch.clone())
+                .unwrap_or_default(),
+            steps,
+        }
+    }
+
+    // only parsed components are reported, the code lines come from `analyze_code`
+    pub fn defconfig_report(&self, diff: &ConfigDiff, a: &str, b: &str) -> DefconfigReport {
+        let mut report = DefconfigReport {
+            version: self.version.to_string(),
+            arch: self.arch.clone(),
+            a: a.to_string(),
+            b: b.to_string(),
+            rows: Vec::new(),
+            gained: 0,
+            lost: 0,
+            unparsed: 0,
+        };
+
+        let flipped = diff
+            .only_in_a
+            .iter()
+            .map(|name| (name, "enabled", "-", FlipChange::Disabled))
+            .chain(
+                diff.only_in_b
+                    .iter()
+                    .map(|name| (name, "-", "enabled", FlipChange::Enabled)),
+            );
+        let changed = diff
+            .changed
+            .iter()
+            .map(|(name, a, b)| (name, a.as_str(), b.as_str(), FlipChange::Changed));
+        for (name, a_value, b_value, change) in flipped.chain(changed) {
+            let Some(stat) = self.component.get(name) else {
+                report.unparsed += 1;
+                continue;
+            };
+            let code_lines = stat.code_lines();
+            match change {
+                FlipChange::Enabled => report.gained += code_lines,
+                FlipChange::Disabled => report.lost += code_lines,
+                FlipChange::Changed => {}
+            }
+            report.rows.push(DefconfigRow {
+                name: name.clone(),
+                a: a_value.to_string(),
+                b: b_value.to_string(),
+                change,
+                code_lines,
+                makefile_lines: stat.makefile_lines,
+            });
+        }
+        report
+    }
+
+    // a choice selects exactly one member, a tristate choice may still build several as `m`;
+    // more than one at `y` comes from a hand-edited or badly merged `.config`
+    pub fn validate_dotconfig_choices(&self, dotconfig: &DotConfig) -> Vec<ChoiceViolation> {
+        self.choices
+            .iter()
+            .filter_map(|choice| {
+                let enabled: Vec<String> = choice
+                    .members
+                    .iter()
+                    .filter(|member| dotconfig.value(member) == Some("y"))
+                    .cloned()
+                    .collect();
+                (enabled.len() > 1).then(|| ChoiceViolation {
+                    prompt: choice.prompt.clone(),
+                    enabled,
+                })
+            })
+            .collect()
+    }
+
+    // the parsed symbols whose `depends on` the enabled symbols of `dotconfig` satisfy,
+    // i.e. the ones that could be switched on without touching anything else; a symbol
+    // missing from the `.config` is `n` and a condition that fails to parse is not met
+    pub fn satisfiable_symbols(&self, dotconfig: &DotConfig) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .component
+            .iter()
+            .filter(|(_, stat)| {
+                let depends = stat.effective_depends();
+                depends.is_empty()
+                    || Expr::parse(&depends).is_ok_and(|expr| expr.eval(dotconfig) != Tristate::N)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // one symbol per line for grep/awk, never enters the interactive loop
+    pub fn list_components(&self, with_type: bool) {
+        let mut names: Vec<&String> = self.component.keys().collect();
+        names.sort();
+        for name in names {
+            if with_type {
+                println!("{}\t{}", name, self.component[name].value_type);
+            } else {
+                println!("{}", name);
+            }
+        }
+    }
+
+    // the summary tables only, see `interactive::run` for the component prompt
+    pub fn print(&self, renderer: &dyn Renderer) -> io::Result<()> {
+        renderer.render(&self.snapshot(), &mut io::stdout())
+    }
+
+    // `CONFIG_SMP`, `smp` and `SMP` all name the same symbol
+    pub fn lookup(&self, name: &str) -> Option<(&str, &KconfigStat)> {
+        let name = name.trim();
+        let name = match name.get(..7) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("CONFIG_") => &name[7..],
+            _ => name,
+        };
+        if let Some((key, stat)) = self.component.get_key_value(name) {
+            return Some((key.as_str(), stat));
+        }
+        self.component
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .min_by_key(|(key, _)| key.as_str())
+            .map(|(key, stat)| (key.as_str(), stat))
+    }
+
+    /// Looks a component up by name, ignoring case and an optional `CONFIG_` prefix.
+    ///
+    /// ```
+    /// use auto_script::KconfigCounter;
+    /// use std::path::PathBuf;
+    ///
+    /// let kconfig = PathBuf::from("arch/riscv/Kconfig");
+    /// let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), kconfig.clone());
+    /// kc.parse_kconfig_reader("config RISCV_ISA_C\n\tbool\n".as_bytes(), &kconfig)
+    ///     .unwrap();
+    /// assert!(kc.component("CONFIG_RISCV_ISA_C").is_some());
+    /// assert!(kc.component("riscv_isa_c").is_some());
+    /// assert_eq!(kc.suggestions("RISCV_ISA_D"), ["RISCV_ISA_C"]);
+    /// ```
+    pub fn component(&self, name: &str) -> Option<&KconfigStat> {
+        self.lookup(name).map(|(_, stat)| stat)
+    }
+
+    // names containing `pattern`, normalized like `component`, sorted
+    pub fn find(&self, pattern: &str) -> Vec<&str> {
+        let pattern = pattern.trim().to_ascii_uppercase();
+        let pattern = pattern.strip_prefix("CONFIG_").unwrap_or(&pattern);
+        let mut names: Vec<&str> = self
+            .component
+            .keys()
+            .filter(|name| name.to_ascii_uppercase().contains(pattern))
+            .map(|name| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // components matching a `*`/`?` pattern in name order, a `CONFIG_` prefix is dropped like `lookup` does
+    pub fn expand_glob(&self, pattern: &str) -> Vec<&str> {
+        let pattern = pattern.trim();
+        let pattern = match pattern.get(..7) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("CONFIG_") => &pattern[7..],
+            _ => pattern,
+        };
+        let mut names: Vec<&str> = self
+            .component
+            .keys()
+            .filter(|name| glob_match(pattern, name))
+            .map(|name| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // matches where each symbol is defined, guards code and builds objects against
+    // the `F:` patterns, the objects stand for the C sources they are built from
+    pub fn assign_maintainers(&mut self, maintainers: &Maintainers) {
+        let root = self.kernel_root();
+        for stat in self.component.values_mut() {
+            let objects = stat.objects.iter().map(|object| object.with_extension("c"));
+            let locations: Vec<String> = std::iter::once(stat.kconfig_file.clone())
+                .chain(
+                    stat.code_snippets
+                        .iter()
+                        .map(|snippet| snippet.file.clone()),
+                )
+                .chain(objects)
+                .map(|path| {
+                    let path = path.strip_prefix(&root).unwrap_or(&path);
+                    path.to_string_lossy().replace('\\', "/")
+                })
+                .collect();
+            stat.maintainers = maintainers
+                .owners(locations.iter().map(String::as_str))
+                .into_iter()
+                .cloned()
+                .collect();
+        }
+    }
+
+    // type, dependencies, selects, guarded code and built objects of one symbol,
+    // `None` if it is not defined
+    pub fn footprint(&self, symbol: &str) -> Option<Footprint> {
+        let (name, stat) = self.lookup(symbol)?;
+        let mut selects = stat.select.clone();
+        selects.sort();
+        selects.dedup();
+        let files: BTreeSet<&Path> = stat
+            .code_snippets
+            .iter()
+            .map(|snippet| snippet.file.as_path())
+            .collect();
+        Some(Footprint {
+            name: name.to_string(),
+            value_type: stat.value_type,
+            depends: stat.effective_depends(),
+            selects,
+            code_lines: stat.code_lines(),
+            snippets: stat.code_snippets.len(),
+            files: files.len(),
+            objects: stat
+                .objects
+                .iter()
+                .map(|object| self.display_path(object))
+                .collect(),
+        })
+    }
+
+    // the closest few names for a failed lookup, nearest first
+    pub fn suggestions(&self, name: &str) -> Vec<&str> {
+        let name = name.trim().to_ascii_uppercase();
+        let name = name.strip_prefix("CONFIG_").unwrap_or(&name);
+        let limit = (name.len() / 3).max(1);
+        let mut candidates: Vec<(usize, &str)> = self
+            .component
+            .keys()
+            .map(|key| (edit_distance(name, key), key.as_str()))
+            .filter(|(distance, _)| *distance <= limit)
+            .collect();
+        candidates.sort();
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    // details of one component, false if there is no such component
+    pub fn print_component(&self, name: &str) -> bool {
+        // stdout going away is not worth reporting
+        self.write_component(name, &mut io::stdout(), false)
+            .unwrap_or(true)
+    }
+
+    // the detail view of `print_component`, `Ok(false)` when there is no such component;
+    // `styled` colors the snippets and makes their headers stand out in a pager
+    pub fn write_component(
+        &self,
+        name: &str,
+        out: &mut dyn io::Write,
+        styled: bool,
+    ) -> io::Result<bool> {
+        let Some((name, stat)) = self.lookup(name) else {
+            return Ok(false);
+        };
+        writeln!(out, "Component: {}", name)?;
+        writeln!(
+            out,
+            "  Defined in: {}",
+            self.display_path(&stat.kconfig_file)
+        )?;
+        writeln!(out, "  Value Type: {}", stat.value_type)?;
+        if let Some(prompt) = &stat.prompt {
+            writeln!(out, "  Prompt: {:?}", prompt)?;
+        }
+        if let Some(condition) = &stat.visible_if {
+            writeln!(out, "  Visible if: {}", condition)?;
+        }
+        writeln!(out, "  Depends on: {:#?}", stat.depend)?;
+        writeln!(out, "  Effective depends: {}", stat.effective_depends())?;
+        writeln!(out, "  Default value: {:#?}", stat.default_value)?;
+        writeln!(out, "  Select: {:#?}", stat.select)?;
+        if let Some(choice) = stat.choice.map(|index| &self.choices[index]) {
+            writeln!(
+                out,
+                "  Choice: {} (default: {}, members: {})",
+                choice.prompt.as_deref().unwrap_or("<no prompt>"),
+                choice.default_member().unwrap_or("<none>"),
+                choice.members.join(", ")
+            )?;
+        }
+        if !stat.maintainers.is_empty() {
+            writeln!(out, "  Maintainers:")?;
+            for subsystem in &stat.maintainers {
+                writeln!(
+                    out,
+                    "    {}: {}",
+                    subsystem.name,
+                    subsystem.maintainers.join(", ")
+                )?;
+            }
+        }
+        writeln!(out, "  Code Snippets: ")?;
+        for code_snippet in &stat.code_snippets {
+            let header = format!(
+                "  {}:{}:",
+                self.display_path(&code_snippet.file),
+                code_snippet.line
+            );
+            if styled {
+                writeln!(out, "{}", highlight_header(&header))?;
+            } else {
+                writeln!(out, "{}", header)?;
+            }
+            if code_snippet.module {
+                writeln!(out, "  [module]")?;
+            }
+            if styled {
+                writeln!(out, "{}", highlight_snippet(&code_snippet.code, name))?;
             } else {
-                error!("Component '{}' not found.", input);
+                writeln!(out, "{}", code_snippet.code)?;
             }
         }
+        Ok(true)
+    }
+}
+
+// the names following each `CONFIG_` on a Makefile line
+fn config_symbols(line: &str) -> BTreeSet<String> {
+    line.match_indices("CONFIG_")
+        .map(|(start, _)| {
+            line[start + "CONFIG_".len()..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect()
+        })
+        .filter(|symbol: &String| !symbol.is_empty())
+        .collect()
+}
+
+// splits `"Foo" if X` into the prompt text and its visibility condition
+fn split_prompt(text: &str) -> (Option<String>, Option<String>) {
+    let Some(quoted) = text.strip_prefix('"') else {
+        return (None, None);
+    };
+    let mut escaped = false;
+    let Some(end) = quoted.find(|c: char| {
+        let closing = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        closing
+    }) else {
+        return (Some(quoted.to_string()), None);
+    };
+    let condition = quoted[end + 1..]
+        .trim()
+        .strip_prefix("if")
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .map(|rest| rest.trim().to_string());
+    (Some(quoted[..end].to_string()), condition)
+}
+
+fn attribute_changes(old: &KconfigStat, new: &KconfigStat) -> Vec<AttributeChange> {
+    old.attributes()
+        .into_iter()
+        .zip(new.attributes())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((attribute, old), (_, new))| AttributeChange {
+            attribute,
+            old,
+            new,
+        })
+        .collect()
+}
+
+// tabs advance to the next multiple of 8, like in the kernel's Kconfig lexer
+fn indentation(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width = (width / 8 + 1) * 8,
+            _ => break,
+        }
     }
+    width
+}
+
+// whether the text in front of a guard's `CONFIG_` ends in a `!`, as in
+// `#if !CONFIG_FOO`, `#if !defined(CONFIG_FOO)` or `#if !IS_ENABLED(CONFIG_FOO)`
+fn negated(prefix: &str) -> bool {
+    let prefix = prefix.trim_end().trim_end_matches('(').trim_end();
+    let prefix = prefix.trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    prefix
+        .trim_end()
+        .trim_end_matches('(')
+        .trim_end()
+        .ends_with('!')
 }
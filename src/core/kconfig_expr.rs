@@ -0,0 +1,432 @@
+//! Kconfig expressions, the conditions after `depends on`, `if`, `visible if` and `default ... if`.
+//!
+//! Evaluation follows the tristate logic of the kernel's kconfig: `&&` takes the
+//! smaller value, `||` the larger one and `!` mirrors `m` onto itself.
+//!
+//! ```
+//! use auto_script::core::kconfig_expr::{Expr, Tristate};
+//! use std::collections::HashMap;
+//!
+//! let values: HashMap<String, String> = [("SMP", "y"), ("NR_CPUS", "8")]
+//!     .map(|(name, value)| (name.to_string(), value.to_string()))
+//!     .into();
+//! let expr = Expr::parse("SMP && NR_CPUS >= 4 || !MMU").unwrap();
+//! assert_eq!(expr.eval(&values), Tristate::Y);
+//! ```
+
+use crate::core::dotconfig::DotConfig;
+use crate::core::error::{Error, Result};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tristate {
+    N,
+    M,
+    Y,
+}
+
+impl Tristate {
+    // `y` and `m` as written in a `.config`, anything else is `n`
+    pub fn from_value(value: &str) -> Self {
+        match value {
+            "y" => Tristate::Y,
+            "m" => Tristate::M,
+            _ => Tristate::N,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering.is_eq(),
+            CompareOp::Ne => ordering.is_ne(),
+            CompareOp::Lt => ordering.is_lt(),
+            CompareOp::Le => ordering.is_le(),
+            CompareOp::Gt => ordering.is_gt(),
+            CompareOp::Ge => ordering.is_ge(),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+}
+
+// one side of a comparison or a bare operand
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Symbol(String),
+    // `y`, `m`, `n`, numbers and quoted strings
+    Const(String),
+}
+
+impl Operand {
+    fn word(word: &str) -> Self {
+        let constant = matches!(word, "y" | "m" | "n") || number(word).is_some();
+        match constant {
+            true => Operand::Const(word.to_string()),
+            false => Operand::Symbol(word.to_string()),
+        }
+    }
+
+    // a symbol nobody set is `n`, like an unset symbol in a `.config`
+    fn value<'a>(&'a self, values: &'a impl SymbolValues) -> &'a str {
+        match self {
+            Operand::Symbol(name) => values.value(name).unwrap_or("n"),
+            Operand::Const(value) => value,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Operand(Operand),
+    Compare(CompareOp, Operand, Operand),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+// where an expression looks up the value of a symbol, e.g. a `.config`
+pub trait SymbolValues {
+    fn value(&self, name: &str) -> Option<&str>;
+}
+
+impl SymbolValues for DotConfig {
+    fn value(&self, name: &str) -> Option<&str> {
+        DotConfig::value(self, name)
+    }
+}
+
+impl SymbolValues for HashMap<String, String> {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.get(name).map(String::as_str)
+    }
+}
+
+impl SymbolValues for BTreeMap<String, String> {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.get(name).map(String::as_str)
+    }
+}
+
+// the value of a `default`, `select` or `imply` line and the `if` guard after it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conditional {
+    pub value: Expr,
+    pub condition: Option<Expr>,
+}
+
+impl Conditional {
+    /// Splits `<value> if <condition>` at the `if` outside of strings and parentheses.
+    ///
+    /// ```
+    /// use auto_script::core::kconfig_expr::Conditional;
+    ///
+    /// let default = Conditional::parse("\"boot if ready\" if 64BIT").unwrap();
+    /// assert_eq!(default.value.to_string(), "\"boot if ready\"");
+    /// assert_eq!(default.condition.unwrap().symbols(), ["64BIT"]);
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = |reason| Error::InvalidExpr {
+            expr: text.to_string(),
+            reason,
+        };
+        let mut tokens = tokenize(text).map_err(invalid)?;
+        let mut depth = 0usize;
+        let split = tokens.iter().position(|token| {
+            match token {
+                Token::Open => depth += 1,
+                Token::Close => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            depth == 0 && *token == Token::Word("if".to_string())
+        });
+        let condition = match split {
+            Some(index) => {
+                let condition = tokens.split_off(index + 1);
+                tokens.pop();
+                Some(parse_tokens(condition).map_err(invalid)?)
+            }
+            None => None,
+        };
+        Ok(Conditional {
+            value: parse_tokens(tokens).map_err(invalid)?,
+            condition,
+        })
+    }
+
+    // the symbols of the value, then the ones only the condition reads
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut symbols = self.value.symbols();
+        for symbol in self.condition.iter().flat_map(Expr::symbols) {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        symbols
+    }
+}
+
+fn parse_tokens(tokens: Vec<Token>) -> std::result::Result<Expr, &'static str> {
+    let mut parser = Parser { tokens, next: 0 };
+    let parsed = parser.or()?;
+    match parser.peek() {
+        None => Ok(parsed),
+        Some(_) => Err("unexpected tokens after the expression"),
+    }
+}
+
+impl Expr {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let invalid = |reason| Error::InvalidExpr {
+            expr: expr.to_string(),
+            reason,
+        };
+        tokenize(expr).and_then(parse_tokens).map_err(invalid)
+    }
+
+    pub fn eval(&self, values: &impl SymbolValues) -> Tristate {
+        match self {
+            Expr::Operand(operand) => Tristate::from_value(operand.value(values)),
+            Expr::Compare(op, left, right) => {
+                let ordering = compare(left.value(values), right.value(values));
+                match op.holds(ordering) {
+                    true => Tristate::Y,
+                    false => Tristate::N,
+                }
+            }
+            Expr::Not(expr) => match expr.eval(values) {
+                Tristate::N => Tristate::Y,
+                Tristate::M => Tristate::M,
+                Tristate::Y => Tristate::N,
+            },
+            Expr::And(left, right) => left.eval(values).min(right.eval(values)),
+            Expr::Or(left, right) => left.eval(values).max(right.eval(values)),
+        }
+    }
+
+    // every symbol the expression reads, in order of appearance
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut symbols = Vec::new();
+        self.collect_symbols(&mut symbols);
+        symbols
+    }
+
+    fn collect_symbols<'a>(&'a self, symbols: &mut Vec<&'a str>) {
+        let mut push = |operand: &'a Operand| {
+            if let Operand::Symbol(name) = operand {
+                if !symbols.contains(&name.as_str()) {
+                    symbols.push(name);
+                }
+            }
+        };
+        match self {
+            Expr::Operand(operand) => push(operand),
+            Expr::Compare(_, left, right) => {
+                push(left);
+                push(right);
+            }
+            Expr::Not(expr) => expr.collect_symbols(symbols),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.collect_symbols(symbols);
+                right.collect_symbols(symbols);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Const(value) if value.is_empty() || value.contains(char::is_whitespace) => {
+                write!(f, "\"{}\"", value)
+            }
+            Operand::Symbol(value) | Operand::Const(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// fully parenthesized below the top level, the grouping the parser chose stays visible
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nested = |expr: &Expr| match expr {
+            Expr::And(..) | Expr::Or(..) => format!("({})", expr),
+            _ => expr.to_string(),
+        };
+        match self {
+            Expr::Operand(operand) => write!(f, "{}", operand),
+            Expr::Compare(op, left, right) => write!(f, "{}{}{}", left, op.as_str(), right),
+            Expr::Not(expr) => write!(f, "!{}", nested(expr)),
+            Expr::And(left, right) => write!(f, "{} && {}", nested(left), nested(right)),
+            Expr::Or(left, right) => write!(f, "{} || {}", nested(left), nested(right)),
+        }
+    }
+}
+
+// decimal and `0x` hex numbers compare by value, anything else as strings
+fn compare(left: &str, right: &str) -> Ordering {
+    match (number(left), number(right)) {
+        (Some(left), Some(right)) => left.cmp(&right),
+        _ => left.cmp(right),
+    }
+}
+
+fn number(word: &str) -> Option<i64> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+    Compare(CompareOp),
+}
+
+fn tokenize(expr: &str) -> std::result::Result<Vec<Token>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|(_, c)| *c == expected).is_some();
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '!' if next_is('=') => Token::Compare(CompareOp::Ne),
+            '!' => Token::Not,
+            '=' => Token::Compare(CompareOp::Eq),
+            '<' if next_is('=') => Token::Compare(CompareOp::Le),
+            '<' => Token::Compare(CompareOp::Lt),
+            '>' if next_is('=') => Token::Compare(CompareOp::Ge),
+            '>' => Token::Compare(CompareOp::Gt),
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => value.extend(chars.next().map(|(_, c)| c)),
+                        Some((_, end)) if end == c => break,
+                        Some((_, c)) => value.push(c),
+                        None => return Err("unterminated string"),
+                    }
+                }
+                Token::Quoted(value)
+            }
+            _ if c.is_ascii_alphanumeric() || c == '_' || c == '-' => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    end = index + c.len_utf8();
+                }
+                Token::Word(expr[start..end].to_string())
+            }
+            _ => return Err("unsupported character, macros like `$(...)` are not expanded"),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// `||` binds loosest, then `&&`, then `!`; comparisons only join two operands
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn take(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn or(&mut self) -> std::result::Result<Expr, &'static str> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> std::result::Result<Expr, &'static str> {
+        let mut expr = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> std::result::Result<Expr, &'static str> {
+        match self.take() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.not()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.take() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("missing `)`"),
+                }
+            }
+            Some(Token::Word(word)) => self.comparison(Operand::word(&word)),
+            Some(Token::Quoted(value)) => self.comparison(Operand::Const(value)),
+            Some(_) => Err("expected a symbol, `!` or `(`"),
+            None => Err("expression ends early"),
+        }
+    }
+
+    fn comparison(&mut self, left: Operand) -> std::result::Result<Expr, &'static str> {
+        let Some(Token::Compare(op)) = self.peek().cloned() else {
+            return Ok(Expr::Operand(left));
+        };
+        self.next += 1;
+        let right = match self.take() {
+            Some(Token::Word(word)) => Operand::word(&word),
+            Some(Token::Quoted(value)) => Operand::Const(value),
+            _ => return Err("a comparison needs a symbol or constant on both sides"),
+        };
+        Ok(Expr::Compare(op, left, right))
+    }
+}
@@ -1,13 +1,168 @@
-use anyhow::Result;
-use flexi_logger::{FileSpec, Logger, WriteMode};
+use crate::core::error::{Error, Result};
+use chrono::{SecondsFormat, Utc};
+#[cfg(feature = "file-logging")]
+use flexi_logger::{
+    Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, Logger, LoggerHandle, Naming, WriteMode,
+};
+use log::kv::{Key, Value, VisitSource};
+use log::Record;
+use serde_json::{Map, Value as JsonValue};
+use std::io::Write;
+use std::path::Path;
 
-pub fn set_logger() -> Result<()> {
-    let file = FileSpec::try_from("./log")?;
-    Logger::try_with_str("info")
-        .unwrap()
+// rotate at 10 MiB and keep the last few files next to the current one
+#[cfg(feature = "file-logging")]
+const LOG_ROTATE_SIZE: u64 = 10 * 1024 * 1024;
+#[cfg(feature = "file-logging")]
+const LOG_KEEP_FILES: usize = 3;
+
+// `-v` raises the default level step by step, `RUST_LOG` always wins
+fn default_level(verbosity: u8) -> &'static str {
+    match verbosity {
+        0 => "error",
+        1 => "warn",
+        2 => "info",
+        3 => "debug",
+        _ => "trace",
+    }
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum LogFormat {
+    // the backend's own format, key-values such as `arch=riscv` follow the message
+    Human,
+    // one JSON object per line
+    Json,
+}
+
+struct JsonFields<'a>(&'a mut Map<String, JsonValue>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(
+        &mut self,
+        key: Key<'kvs>,
+        value: Value<'kvs>,
+    ) -> std::result::Result<(), log::kv::Error> {
+        self.0.insert(
+            key.as_str().to_string(),
+            JsonValue::String(value.to_string()),
+        );
+        Ok(())
+    }
+}
+
+// `{"ts":..,"level":..,"target":..,"msg":..}` followed by the record's key-values
+fn json_record(record: &Record) -> String {
+    let mut fields = Map::new();
+    fields.insert(
+        "ts".to_string(),
+        Utc::now()
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+            .into(),
+    );
+    fields.insert("level".to_string(), record.level().as_str().into());
+    fields.insert("target".to_string(), record.target().into());
+    fields.insert("msg".to_string(), record.args().to_string().into());
+    let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+    JsonValue::Object(fields).to_string()
+}
+
+// flexi_logger ends every record with a newline itself
+#[cfg(feature = "file-logging")]
+fn flexi_json_format(
+    w: &mut dyn Write,
+    _now: &mut DeferredNow,
+    record: &Record,
+) -> std::io::Result<()> {
+    write!(w, "{}", json_record(record))
+}
+
+// `--log-filter` directives override the `-v` level for the modules they name
+fn log_spec(verbosity: u8, filter: Option<&str>) -> String {
+    let level = default_level(verbosity);
+    match filter {
+        Some(filter) if !filter.trim().is_empty() => format!("{},{}", level, filter.trim()),
+        _ => level.to_string(),
+    }
+}
+
+// flushes the log file on drop, keep it alive until exit
+pub struct LogHandle {
+    #[cfg(feature = "file-logging")]
+    _file: Option<LoggerHandle>,
+}
+
+// keeps what was being set up in the message, the logger's own error as the source
+fn logging<E>(message: String) -> impl FnOnce(E) -> Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    move |source| Error::Logging {
+        message,
+        source: Some(Box::new(source)),
+    }
+}
+
+pub fn set_logger(
+    log_file: Option<&Path>,
+    verbosity: u8,
+    filter: Option<&str>,
+    format: LogFormat,
+) -> Result<LogHandle> {
+    let spec = log_spec(verbosity, filter);
+    let Some(log_file) = log_file else {
+        let mut builder = env_logger::Builder::from_env(
+            env_logger::Env::default().default_filter_or(spec.as_str()),
+        );
+        if let LogFormat::Json = format {
+            builder.format(|buf, record| writeln!(buf, "{}", json_record(record)));
+        }
+        builder
+            .try_init()
+            .map_err(logging("failed to initialize env_logger".to_string()))?;
+        return Ok(LogHandle {
+            #[cfg(feature = "file-logging")]
+            _file: None,
+        });
+    };
+    start_file_logger(log_file, &spec, format)
+}
+
+#[cfg(not(feature = "file-logging"))]
+fn start_file_logger(log_file: &Path, _spec: &str, _format: LogFormat) -> Result<LogHandle> {
+    Err(Error::Logging {
+        message: format!(
+            "cannot log to {:?}, built without the `file-logging` feature",
+            log_file
+        ),
+        source: None,
+    })
+}
+
+#[cfg(feature = "file-logging")]
+fn start_file_logger(log_file: &Path, spec: &str, format: LogFormat) -> Result<LogHandle> {
+    let file = FileSpec::try_from(log_file)
+        .map_err(logging(format!("invalid log file path {:?}", log_file)))?;
+    let mut logger = Logger::try_with_env_or_str(spec)
+        .map_err(logging("invalid log specification".to_string()))?;
+    if let LogFormat::Json = format {
+        logger = logger.format(flexi_json_format);
+    }
+    let handle = logger
         .log_to_file(file)
+        .duplicate_to_stderr(Duplicate::Warn)
+        .rotate(
+            Criterion::Size(LOG_ROTATE_SIZE),
+            Naming::Numbers,
+            Cleanup::KeepLogFiles(LOG_KEEP_FILES),
+        )
         .write_mode(WriteMode::BufferAndFlush)
         .start()
-        .unwrap();
-    Ok(())
+        .map_err(logging(format!(
+            "failed to start logging to {:?}",
+            log_file
+        )))?;
+    Ok(LogHandle {
+        _file: Some(handle),
+    })
 }
@@ -0,0 +1,143 @@
+//! Owners of kernel files, read from the `MAINTAINERS` file at the kernel root.
+//!
+//! Every section is a paragraph starting with the subsystem name, followed by tagged
+//! lines such as `M:\tName <mail>`, `F:\tdrivers/net/` or `X:\tdrivers/net/wireless/`.
+
+use crate::core::error::{Error, Result};
+use crate::core::source::SourceTree;
+use crate::core::utils::glob_match_case;
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Subsystem {
+    pub name: String,
+    // the `M:` lines, e.g. "Palmer Dabbelt <palmer@dabbelt.com>"
+    pub maintainers: Vec<String>,
+}
+
+struct Section {
+    subsystem: Subsystem,
+    files: Vec<String>,
+    excluded: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Maintainers {
+    sections: Vec<Section>,
+}
+
+impl Maintainers {
+    pub fn load(tree: &dyn SourceTree, path: &Path) -> Result<Self> {
+        let reader = tree.read(path).map_err(Error::io(path))?;
+        Maintainers::parse(reader, path)
+    }
+
+    // the introduction before the first section has no `F:` lines and is skipped with
+    // every other paragraph that owns no files
+    pub fn parse(reader: impl BufRead, path: &Path) -> Result<Self> {
+        let mut sections = Vec::new();
+        let mut current: Option<Section> = None;
+        for line in reader.lines() {
+            let line = line.map_err(Error::io(path))?;
+            let tagged = match line.as_bytes() {
+                [tag, b':', ..] if tag.is_ascii_uppercase() => Some((*tag, line[2..].trim())),
+                _ => None,
+            };
+            match (tagged, current.as_mut()) {
+                (Some((b'M', maintainer)), Some(section)) => {
+                    section.subsystem.maintainers.push(maintainer.to_string())
+                }
+                (Some((b'F', pattern)), Some(section)) => section.files.push(pattern.to_string()),
+                (Some((b'X', pattern)), Some(section)) => {
+                    section.excluded.push(pattern.to_string())
+                }
+                (Some(_), _) => {}
+                (None, _) if line.trim().is_empty() => sections.extend(current.take()),
+                (None, Some(_)) => {}
+                (None, None) => {
+                    current = Some(Section {
+                        subsystem: Subsystem {
+                            name: line.trim().to_string(),
+                            maintainers: Vec::new(),
+                        },
+                        files: Vec::new(),
+                        excluded: Vec::new(),
+                    })
+                }
+            }
+        }
+        sections.extend(current);
+        sections.retain(|section| !section.files.is_empty());
+        Ok(Maintainers { sections })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// The subsystems owning any of `paths`, relative to the kernel root, the one with the
+    /// most specific `F:` pattern first.
+    ///
+    /// ```
+    /// use auto_script::core::maintainers::Maintainers;
+    /// use std::path::Path;
+    ///
+    /// let excerpt = "RISC-V ARCHITECTURE\nM:\tPalmer Dabbelt <palmer@dabbelt.com>\n\
+    ///                F:\tarch/riscv/\n\nTHE REST\nF:\t*\nF:\t*/\n";
+    /// let maintainers = Maintainers::parse(excerpt.as_bytes(), Path::new("MAINTAINERS")).unwrap();
+    /// let owners = maintainers.owners(["arch/riscv/kernel/smp.c"]);
+    /// assert_eq!(owners[0].name, "RISC-V ARCHITECTURE");
+    /// assert_eq!(owners[1].name, "THE REST");
+    /// ```
+    pub fn owners<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<&Subsystem> {
+        let paths: Vec<&str> = paths.into_iter().collect();
+        let mut owners: Vec<(usize, &Subsystem)> = self
+            .sections
+            .iter()
+            .filter_map(|section| {
+                paths
+                    .iter()
+                    .filter(|path| {
+                        !section
+                            .excluded
+                            .iter()
+                            .any(|pattern| pattern_depth(pattern, path).is_some())
+                    })
+                    .filter_map(|path| {
+                        section
+                            .files
+                            .iter()
+                            .filter_map(|pattern| pattern_depth(pattern, path))
+                            .max()
+                    })
+                    .max()
+                    .map(|depth| (depth, &section.subsystem))
+            })
+            .collect();
+        owners.sort_by(|(a_depth, a), (b_depth, b)| b_depth.cmp(a_depth).then(a.name.cmp(&b.name)));
+        owners.into_iter().map(|(_, subsystem)| subsystem).collect()
+    }
+}
+
+// how many directory levels an `F:`/`X:` pattern spells out if it matches `path`:
+// a trailing `/` takes the whole subtree, `*` and `?` stay within one level and a
+// plain directory name without the `/` is a subtree as well
+pub fn pattern_depth(pattern: &str, path: &str) -> Option<usize> {
+    let subtree = pattern.ends_with('/');
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    let plain = !pattern.iter().any(|segment| segment.contains(['*', '?']));
+    let matches = match path.len().cmp(&pattern.len()) {
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => !subtree,
+        std::cmp::Ordering::Greater => subtree || plain,
+    };
+    let matches = matches
+        && pattern
+            .iter()
+            .zip(&path)
+            .all(|(pattern, segment)| glob_match_case(pattern, segment));
+    matches.then_some(pattern.len())
+}
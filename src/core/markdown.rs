@@ -0,0 +1,291 @@
+use crate::core::kconfig_counter::{
+    ArchComparison, CodeDelta, ComponentDiff, DefconfigReport, DirReport, FlipChange, LineDelta,
+    SubsystemReport,
+};
+use std::fmt::Write;
+
+// `||` in a depends expression would otherwise split the table cell
+fn cell(value: &str) -> String {
+    if value.is_empty() {
+        "_none_".to_string()
+    } else {
+        format!("`{}`", value.replace('|', "\\|"))
+    }
+}
+
+fn summary_table(out: &mut String, rows: &[(String, String)]) {
+    out.push_str("| | Count |\n|---|---:|\n");
+    for (label, count) in rows {
+        let _ = writeln!(out, "| {} | {} |", label, count);
+    }
+    out.push('\n');
+}
+
+// a collapsed section, `body` is rendered as-is inside it
+fn details(out: &mut String, summary: &str, body: &str) {
+    let _ = writeln!(out, "<details>\n<summary>{}</summary>\n", summary);
+    out.push_str(body);
+    out.push_str("\n</details>\n\n");
+}
+
+fn name_list(names: &[String]) -> String {
+    if names.is_empty() {
+        return "_none_\n".to_string();
+    }
+    names.iter().map(|name| format!("- `{}`\n", name)).collect()
+}
+
+pub fn component_diff(diff: &ComponentDiff) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "## Kconfig diff: {} → {}\n", diff.old, diff.new);
+    summary_table(
+        &mut out,
+        &[
+            ("Removed".to_string(), diff.removed.len().to_string()),
+            ("Added".to_string(), diff.added.len().to_string()),
+            ("Changed".to_string(), diff.changed.len().to_string()),
+        ],
+    );
+
+    details(
+        &mut out,
+        &format!("Removed ({})", diff.removed.len()),
+        &name_list(&diff.removed),
+    );
+    details(
+        &mut out,
+        &format!("Added ({})", diff.added.len()),
+        &name_list(&diff.added),
+    );
+
+    let mut changed = String::from("| Component | Attribute | Before → After |\n|---|---|---|\n");
+    for component in &diff.changed {
+        for change in &component.changes {
+            let _ = writeln!(
+                changed,
+                "| `{}` | {} | {} → {} |",
+                component.name,
+                change.attribute,
+                cell(&change.old),
+                cell(&change.new)
+            );
+        }
+    }
+    details(
+        &mut out,
+        &format!("Changed ({})", diff.changed.len()),
+        &changed,
+    );
+    out
+}
+
+pub fn arch_comparison(comparison: &ArchComparison) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "## Linux-{} arch comparison: {}\n",
+        comparison.version,
+        comparison.arches.join(", ")
+    );
+
+    let mut rows = vec![(
+        "Shared by all".to_string(),
+        comparison.shared.len().to_string(),
+    )];
+    if comparison.arches.len() > 2 {
+        rows.push((
+            "Shared by some".to_string(),
+            comparison.partial.len().to_string(),
+        ));
+    }
+    for (arch, names) in &comparison.exclusive {
+        rows.push((format!("Only {}", arch), names.len().to_string()));
+    }
+    rows.push((
+        "Divergent".to_string(),
+        comparison.divergent.len().to_string(),
+    ));
+    summary_table(&mut out, &rows);
+
+    for (arch, names) in &comparison.exclusive {
+        details(
+            &mut out,
+            &format!("Only {} ({})", arch, names.len()),
+            &name_list(names),
+        );
+    }
+
+    let mut divergent = String::from("| Component | Arch | Type | Default |\n|---|---|---|---|\n");
+    for component in &comparison.divergent {
+        for value in &component.values {
+            let _ = writeln!(
+                divergent,
+                "| `{}` | {} | {} | {} |",
+                component.name,
+                value.arch,
+                value.value_type,
+                cell(&value.default)
+            );
+        }
+    }
+    details(
+        &mut out,
+        &format!("Divergent ({})", comparison.divergent.len()),
+        &divergent,
+    );
+    out
+}
+
+pub fn defconfig_report(report: &DefconfigReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "## Linux-{} {} defconfig diff: `{}` → `{}`\n",
+        report.version, report.arch, report.a, report.b
+    );
+
+    let sections = [
+        ("Enabled", FlipChange::Enabled),
+        ("Disabled", FlipChange::Disabled),
+        ("Changed", FlipChange::Changed),
+    ];
+    let mut rows: Vec<(String, String)> = sections
+        .iter()
+        .map(|(label, change)| {
+            let count = report
+                .rows
+                .iter()
+                .filter(|row| row.change == *change)
+                .count();
+            (label.to_string(), count.to_string())
+        })
+        .collect();
+    rows.push((
+        "Guarded code lines".to_string(),
+        format!("+{} / -{}", report.gained, report.lost),
+    ));
+    rows.push(("Not parsed".to_string(), report.unparsed.to_string()));
+    summary_table(&mut out, &rows);
+
+    for (label, change) in sections {
+        let mut body = String::from(
+            "| Component | Before → After | Code lines | Makefile lines |\n|---|---|---:|---:|\n",
+        );
+        let mut count = 0;
+        for row in report.rows.iter().filter(|row| row.change == change) {
+            count += 1;
+            let _ = writeln!(
+                body,
+                "| `{}` | {} → {} | {} | {} |",
+                row.name,
+                cell(&row.a),
+                cell(&row.b),
+                row.code_lines,
+                row.makefile_lines
+            );
+        }
+        details(&mut out, &format!("{} ({})", label, count), &body);
+    }
+    out
+}
+
+fn delta_table(deltas: &[LineDelta]) -> String {
+    let mut table = String::from("| Component | Old | New | Delta |\n|---|---:|---:|---:|\n");
+    for delta in deltas {
+        let _ = writeln!(
+            table,
+            "| `{}` | {} | {} | {:+} |",
+            delta.name, delta.old, delta.new, delta.delta
+        );
+    }
+    table
+}
+
+pub fn code_delta(delta: &CodeDelta) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "## Guarded code lines: {} → {}\n",
+        delta.old, delta.new
+    );
+    summary_table(
+        &mut out,
+        &[
+            ("Changed".to_string(), delta.changed.len().to_string()),
+            ("Unchanged".to_string(), delta.unchanged.to_string()),
+            ("Only old".to_string(), delta.only_old.len().to_string()),
+            ("Only new".to_string(), delta.only_new.len().to_string()),
+        ],
+    );
+    for (label, deltas) in [
+        ("Changed", &delta.changed),
+        ("Only old", &delta.only_old),
+        ("Only new", &delta.only_new),
+    ] {
+        details(
+            &mut out,
+            &format!("{} ({})", label, deltas.len()),
+            &delta_table(deltas),
+        );
+    }
+    out
+}
+
+pub fn dir_report(report: &DirReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "## Directories: Linux-{} {}\n",
+        report.version, report.arch
+    );
+    out.push_str("| Directory | Components | Names |\n|---|---:|---|\n");
+    for row in &report.dirs {
+        let names: Vec<String> = row.components.iter().map(|name| cell(name)).collect();
+        let _ = writeln!(
+            out,
+            "| `{}` | {} | {} |",
+            row.dir,
+            row.components.len(),
+            names.join(", ")
+        );
+    }
+    out
+}
+
+pub fn subsystem_report(report: &SubsystemReport) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "## Subsystems: Linux-{} {}\n",
+        report.version, report.arch
+    );
+    let with_code = report.subsystems.iter().any(|row| row.code_lines.is_some());
+    out.push_str("| Subsystem | Components | Types |");
+    out.push_str(if with_code {
+        " Code |\n|---|---:|---|---:|\n"
+    } else {
+        "\n|---|---:|---|\n"
+    });
+    for row in &report.subsystems {
+        let types: Vec<String> = row
+            .types
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(value_type, count)| format!("{} {}", count, value_type))
+            .collect();
+        let _ = write!(
+            out,
+            "| `{}` | {} | {} |",
+            row.subsystem,
+            row.components,
+            types.join(", ")
+        );
+        match row.code_lines {
+            Some(code_lines) => {
+                let _ = writeln!(out, " {} |", code_lines);
+            }
+            None => out.push('\n'),
+        }
+    }
+    out
+}
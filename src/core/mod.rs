@@ -1,4 +1,29 @@
+pub mod arch;
+pub mod baseline;
+pub mod cache;
+pub mod dotconfig;
+pub mod error;
+#[cfg(feature = "fetch")]
+pub mod fetch;
 pub mod file_counter;
+pub mod graph;
+#[cfg(feature = "cli")]
+pub mod interactive;
 pub mod kconfig_counter;
+pub mod kconfig_expr;
+#[cfg(feature = "cli")]
 pub mod log;
+pub mod maintainers;
+pub mod markdown;
+pub mod output;
+pub mod pipeline;
+pub mod progress;
+pub mod report;
+pub mod schema;
+pub mod source;
+pub mod trend;
+#[cfg(feature = "cli")]
+pub mod tui;
 pub mod utils;
+pub mod version;
+pub mod workspace;
@@ -0,0 +1,90 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+}
+
+impl Compression {
+    // `report.json.gz` and `report.json.xz` are compressed, anything else is written as-is
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("xz") => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+}
+
+// where machine readable reports go, `finish` must be called to flush the compressed trailer
+pub enum Output {
+    Stdout(io::Stdout),
+    File(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Xz(XzEncoder<BufWriter<File>>),
+}
+
+impl Output {
+    pub fn stdout() -> Self {
+        Output::Stdout(io::stdout())
+    }
+
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match Compression::from_path(path) {
+            Compression::None => Output::File(file),
+            Compression::Gzip => Output::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            Compression::Xz => Output::Xz(XzEncoder::new(file, 6)),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Stdout(mut stdout) => stdout.flush(),
+            Output::File(mut file) => file.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+            Output::Xz(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(stdout) => stdout.write(buf),
+            Output::File(file) => file.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+            Output::Xz(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(stdout) => stdout.flush(),
+            Output::File(file) => file.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+            Output::Xz(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// reads a file written through `Output`, decompressing by extension
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let file = BufReader::new(File::open(path)?);
+    let mut reader: Box<dyn Read> = match Compression::from_path(path) {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(GzDecoder::new(file)),
+        Compression::Xz => Box::new(XzDecoder::new(file)),
+    };
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    Ok(content)
+}
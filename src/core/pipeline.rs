@@ -0,0 +1,501 @@
+use crate::core::arch::{srcarch, UM_HOST_ARCH};
+use crate::core::cache::ScanCache;
+use crate::core::error::{Error, Result};
+use crate::core::file_counter::{FileCounter, ScanReport};
+use crate::core::kconfig_counter::{ComponentGroup, KconfigCounter, KconfigCounterBuilder};
+use crate::core::maintainers::Maintainers;
+use crate::core::source::{FsTree, SourceTree};
+use crate::core::utils::display_path;
+use crate::core::version::{fetch_kernel_release, KernelVersion};
+use log::info;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// What to analyze and how, everything about presenting the results stays with the caller.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub kernel_path: PathBuf,
+    pub arches: Vec<PathBuf>,
+    // relative to the kernel root, `{arch}` is replaced by the arch name
+    pub arch_template: String,
+    pub verbose: bool,
+    pub sorted: bool,
+    // one log line per phase instead of one per file
+    pub summarize: bool,
+    pub count_duplicates: bool,
+    pub dedup_by_hash: bool,
+    pub lang_map: Vec<(String, String)>,
+    // also count everything outside the arch directories
+    pub whole_tree: bool,
+    // also count `Documentation/` on its own
+    pub include_docs: bool,
+    // `None` neither loads nor saves a scan cache
+    pub cache: Option<PathBuf>,
+    pub strict: bool,
+    pub fail_on_empty: bool,
+    pub full: bool,
+    // parse the arch Kconfig alone, without following its `source` lines
+    pub no_recurse_source: bool,
+    pub source_include: Vec<String>,
+    pub group_by: Option<ComponentGroup>,
+    // also supplies `CONFIG_LOCALVERSION` to the reported version
+    pub defconfig: Option<PathBuf>,
+    // relative to the kernel root, replaces the directories derived from Kconfig
+    pub code_dir: Vec<PathBuf>,
+    // print paths below the kernel root, the counters keep them absolute
+    pub relative: bool,
+    // what `kernel_path` is read from, e.g. a git revision instead of the disk
+    pub source_tree: Rc<dyn SourceTree>,
+    // look the owners of every component up in `MAINTAINERS` at the kernel root
+    pub maintainers: bool,
+    // raised e.g. by Ctrl-C, the analyses then stop and keep what they counted so far
+    pub stop: Arc<AtomicBool>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            kernel_path: PathBuf::from("/opt/linux-6.9.5"),
+            arches: vec![PathBuf::from("riscv")],
+            arch_template: "arch/{arch}".to_string(),
+            verbose: false,
+            sorted: false,
+            summarize: false,
+            count_duplicates: false,
+            dedup_by_hash: false,
+            lang_map: Vec::new(),
+            whole_tree: false,
+            include_docs: false,
+            cache: None,
+            strict: false,
+            fail_on_empty: false,
+            full: false,
+            no_recurse_source: false,
+            source_include: vec!["arch/".to_string()],
+            group_by: None,
+            defconfig: None,
+            code_dir: Vec::new(),
+            relative: false,
+            source_tree: Rc::new(FsTree),
+            maintainers: false,
+            stop: Arc::default(),
+        }
+    }
+}
+
+impl Options {
+    // `<kernel>/arch/<arch>` unless the arch template says otherwise
+    // `x86_64` lives in `arch/x86`, see `srcarch`
+    pub fn arch_subdir(&self, kernel_path: &Path, arch: &Path) -> PathBuf {
+        let arch = arch.to_string_lossy();
+        kernel_path.join(self.arch_template.replace("{arch}", srcarch(&arch)))
+    }
+
+    // the directory holding every arch, only known when `{arch}` is the last component
+    pub fn arch_root(&self, kernel_path: &Path) -> Option<PathBuf> {
+        let prefix = self.arch_template.strip_suffix("{arch}")?;
+        if prefix.contains("{arch}") || !(prefix.is_empty() || prefix.ends_with('/')) {
+            return None;
+        }
+        Some(kernel_path.join(prefix))
+    }
+
+    // the same options for another kernel tree, which is read from the disk
+    pub fn on_disk(&self) -> Options {
+        Options {
+            source_tree: Rc::new(FsTree),
+            ..self.clone()
+        }
+    }
+
+    pub fn file_counter(
+        &self,
+        arch: &str,
+        version: &KernelVersion,
+        dir_path: PathBuf,
+    ) -> FileCounter {
+        let mut fc = FileCounter::new(arch.to_string(), version.clone(), dir_path);
+        if self.verbose {
+            fc.set_verbose();
+        }
+        if self.sorted {
+            fc.set_sorted();
+        }
+        if self.summarize {
+            fc.set_summarize();
+        }
+        if self.count_duplicates {
+            fc.set_count_duplicates();
+        }
+        if self.dedup_by_hash {
+            fc.set_dedup_by_hash();
+        }
+        if self.relative {
+            fc.set_relative_to(self.kernel_path.clone());
+        }
+        fc.set_lang_map(&self.lang_map);
+        fc.set_source_tree(self.source_tree.clone());
+        fc.set_stop_flag(self.stop.clone());
+        fc
+    }
+
+    pub fn kconfig_counter(
+        &self,
+        arch: &Path,
+        version: &KernelVersion,
+        kernel_path: &Path,
+    ) -> Result<KconfigCounter> {
+        let arch_path = self.arch_subdir(kernel_path, arch).join("Kconfig");
+        info!("fetch {:?} arch Kconfig path -> {:?}", arch, arch_path);
+        self.kconfig_builder(arch, version, kernel_path)
+            .kconfig_path(arch_path)
+            .build()
+    }
+
+    // a Kconfig piped in instead of the arch's, its `source` lines are only recorded
+    pub fn kconfig_counter_from_reader(
+        &self,
+        arch: &Path,
+        version: &KernelVersion,
+        reader: impl BufRead,
+    ) -> Result<KconfigCounter> {
+        let stdin = Path::new("<stdin>");
+        let mut kc = self
+            .kconfig_builder(arch, version, &self.kernel_path)
+            .kconfig_path(stdin)
+            .recurse_source(false)
+            .build()?;
+        kc.parse_kconfig_reader(reader, stdin)?;
+        Ok(kc)
+    }
+
+    fn kconfig_builder(
+        &self,
+        arch: &Path,
+        version: &KernelVersion,
+        kernel_path: &Path,
+    ) -> KconfigCounterBuilder {
+        let arch = arch.to_string_lossy();
+        let mut builder = KconfigCounter::builder()
+            .arch(arch.as_ref())
+            .version(version.clone())
+            .kernel_root(kernel_path)
+            .check_all(self.full)
+            .recurse_source(!self.no_recurse_source)
+            .sorted(self.sorted)
+            .summarize(self.summarize)
+            .source_include(self.source_include.clone())
+            .group_by(self.group_by)
+            .relative_paths(self.relative)
+            .source_tree(self.source_tree.clone())
+            .stop_flag(self.stop.clone())
+            .kconfig_env("SRCARCH", srcarch(&arch));
+        if srcarch(&arch) == "um" {
+            builder = builder
+                .kconfig_env("HEADER_ARCH", UM_HOST_ARCH)
+                .kconfig_env("SUBARCH", UM_HOST_ARCH);
+        }
+        if let Some(defconfig) = &self.defconfig {
+            builder = builder.defconfig(defconfig);
+        }
+        builder
+    }
+
+    pub fn analyze_kconfig_code(&self, kc: &mut KconfigCounter, kernel_path: &Path) -> Result<()> {
+        if self.code_dir.is_empty() {
+            kc.analyze_code()?;
+        } else {
+            let code_dirs: Vec<PathBuf> = self
+                .code_dir
+                .iter()
+                .map(|dir| kernel_path.join(dir))
+                .collect();
+            kc.analyze_code_dirs(&code_dirs)?;
+        }
+        Ok(())
+    }
+}
+
+// one line per unreadable path under a headline, `None` when everything was read;
+// paths are shown below `root` when one is given
+pub fn scan_report_summary(
+    portion: &str,
+    report: &ScanReport,
+    root: Option<&Path>,
+) -> Option<String> {
+    if report.is_empty() {
+        return None;
+    }
+
+    let mut summary = format!(
+        "{}: {} unreadable directories, {} unreadable files",
+        portion,
+        report.unreadable_dirs.len(),
+        report.unreadable_files.len()
+    );
+    for failure in report
+        .unreadable_dirs
+        .iter()
+        .chain(&report.unreadable_files)
+    {
+        summary.push_str(&format!(
+            "\n  {}: {}",
+            display_path(&failure.path, root),
+            failure.reason
+        ));
+    }
+    Some(summary)
+}
+
+/// The counters retained by a [`Pipeline`], in `arches` order.
+pub struct Results<'a> {
+    pub version: &'a KernelVersion,
+    pub code: &'a [FileCounter],
+    // every arch merged, only when more than one arch was counted
+    pub all_arches: Option<&'a FileCounter>,
+    // the tree outside the arch directories, only with `whole_tree`
+    pub common: Option<&'a FileCounter>,
+    // `Documentation/`, only with `include_docs`
+    pub docs: Option<&'a FileCounter>,
+    pub kconfig: &'a [KconfigCounter],
+}
+
+/// Runs the code and Kconfig analyses of one kernel tree.
+///
+/// ```no_run
+/// use auto_script::core::pipeline::{Options, Pipeline};
+///
+/// let mut pipeline = Pipeline::new(Options::default()).unwrap();
+/// pipeline.run_code_analysis().unwrap();
+/// pipeline.run_kconfig_analysis(false).unwrap();
+/// assert_eq!(pipeline.results().code.len(), 1);
+/// ```
+pub struct Pipeline {
+    options: Options,
+    version: KernelVersion,
+    warnings: Vec<String>,
+    code: Vec<FileCounter>,
+    all_arches: Option<FileCounter>,
+    common: Option<FileCounter>,
+    docs: Option<FileCounter>,
+    kconfig: Vec<KconfigCounter>,
+}
+
+impl Pipeline {
+    pub fn new(options: Options) -> Result<Self> {
+        info!("fetch linux kernel directory: {:?}", options.kernel_path);
+        let version = fetch_kernel_release(
+            options.source_tree.as_ref(),
+            &options.kernel_path,
+            options.defconfig.as_deref(),
+        )?;
+        info!("fetch linux kernel version: {}", version);
+
+        Ok(Pipeline {
+            options,
+            version,
+            warnings: Vec::new(),
+            code: Vec::new(),
+            all_arches: None,
+            common: None,
+            docs: None,
+            kconfig: Vec::new(),
+        })
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn version(&self) -> &KernelVersion {
+        &self.version
+    }
+
+    // whether `Options::stop` was raised, the results are partial then
+    pub fn interrupted(&self) -> bool {
+        self.options.stop.load(Ordering::Relaxed)
+    }
+
+    // unreadable paths met without `strict`, one summary per scanned portion
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn results(&self) -> Results<'_> {
+        Results {
+            version: &self.version,
+            code: &self.code,
+            all_arches: self.all_arches.as_ref(),
+            common: self.common.as_ref(),
+            docs: self.docs.as_ref(),
+            kconfig: &self.kconfig,
+        }
+    }
+
+    fn check_scan_report(&mut self, portion: &str, report: &ScanReport) -> Result<()> {
+        let root = self
+            .options
+            .relative
+            .then_some(self.options.kernel_path.as_path());
+        let Some(summary) = scan_report_summary(portion, report, root) else {
+            return Ok(());
+        };
+        if self.options.strict {
+            return Err(Error::UnreadablePaths(summary));
+        }
+        self.warnings.push(summary);
+        Ok(())
+    }
+
+    pub fn run_code_analysis(&mut self) -> Result<()> {
+        let options = self.options.clone();
+        let kernel_path = &options.kernel_path;
+        let mut cache = options.cache.as_deref().map(ScanCache::load);
+
+        for arch in &options.arches {
+            if self.interrupted() {
+                break;
+            }
+            info!("fetch arch: {:?}", arch);
+            let arch_dir = options.arch_subdir(kernel_path, arch);
+            info!("fetch {:?} arch directory path -> {:?}", arch, arch_dir);
+
+            let mut fc = options.file_counter(&arch.to_string_lossy(), &self.version, arch_dir);
+            if let Some(cache) = cache.take() {
+                fc.set_cache(cache);
+            }
+            let report = fc.search()?;
+            cache = fc.take_cache();
+            self.check_scan_report(&arch.to_string_lossy(), &report)?;
+            if options.fail_on_empty && !report.interrupted && fc.is_empty() {
+                return Err(Error::EmptyAnalysis(format!(
+                    "code analysis of arch {:?} counted no files",
+                    arch
+                )));
+            }
+            self.code.push(fc);
+        }
+
+        if self.code.len() > 1 {
+            let mut all_arches = FileCounter::new(
+                "all arches".to_string(),
+                self.version.clone(),
+                options
+                    .arch_root(kernel_path)
+                    .unwrap_or_else(|| kernel_path.clone()),
+            );
+            if options.verbose {
+                all_arches.set_verbose();
+            }
+            if options.relative {
+                all_arches.set_relative_to(kernel_path.clone());
+            }
+            for fc in &self.code {
+                all_arches.merge(fc);
+            }
+            self.all_arches = Some(all_arches);
+        }
+
+        if options.whole_tree && !self.interrupted() {
+            info!("fetch whole tree: {:?}", kernel_path);
+            let mut fc = options.file_counter("common", &self.version, kernel_path.clone());
+            // every arch directory is excluded, the selected ones are counted above
+            match options.arch_root(kernel_path) {
+                Some(arch_root) => {
+                    let entries = options
+                        .source_tree
+                        .read_dir(&arch_root)
+                        .map_err(Error::io(&arch_root))?;
+                    for entry in entries.into_iter().filter(|entry| entry.is_dir) {
+                        fc.exclude(entry.path);
+                    }
+                }
+                None => {
+                    for arch in &options.arches {
+                        fc.exclude(options.arch_subdir(kernel_path, arch));
+                    }
+                }
+            }
+            if let Some(cache) = cache.take() {
+                fc.set_cache(cache);
+            }
+            let report = fc.search()?;
+            cache = fc.take_cache();
+            self.check_scan_report("whole tree", &report)?;
+            if options.fail_on_empty && !report.interrupted && fc.is_empty() {
+                return Err(Error::EmptyAnalysis(
+                    "whole tree code analysis counted no files".to_string(),
+                ));
+            }
+            self.common = Some(fc);
+        }
+
+        if options.include_docs && !self.interrupted() {
+            let docs_dir = kernel_path.join("Documentation");
+            info!("fetch documentation: {:?}", docs_dir);
+            if !options.source_tree.is_dir(&docs_dir) {
+                return Err(Error::Io {
+                    path: docs_dir,
+                    source: io::ErrorKind::NotFound.into(),
+                });
+            }
+            let mut fc = options.file_counter("docs", &self.version, docs_dir);
+            if let Some(cache) = cache.take() {
+                fc.set_cache(cache);
+            }
+            let report = fc.search()?;
+            cache = fc.take_cache();
+            self.check_scan_report("documentation", &report)?;
+            self.docs = Some(fc);
+        }
+
+        if let (Some(cache), Some(path)) = (&cache, &options.cache) {
+            cache.save(path)?;
+        }
+        Ok(())
+    }
+
+    // `with_code` also counts the code lines guarded by each component
+    pub fn run_kconfig_analysis(&mut self, with_code: bool) -> Result<()> {
+        let options = &self.options;
+        let maintainers = match options.maintainers {
+            true => Maintainers::load(
+                options.source_tree.as_ref(),
+                &options.kernel_path.join("MAINTAINERS"),
+            )?,
+            false => Maintainers::default(),
+        };
+        for arch in &options.arches {
+            if options.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            info!("fetch arch: {:?}", arch);
+            let mut kc = options.kconfig_counter(arch, &self.version, &options.kernel_path)?;
+            kc.parse_kconfig()?;
+            if options.fail_on_empty && kc.is_empty() {
+                return Err(Error::EmptyAnalysis(format!(
+                    "kconfig analysis of arch {:?} found no components",
+                    arch
+                )));
+            }
+            if with_code {
+                options.analyze_kconfig_code(&mut kc, &options.kernel_path)?;
+                let stopped = options.stop.load(Ordering::Relaxed);
+                if options.fail_on_empty && !stopped && kc.total_code_lines() == 0 {
+                    return Err(Error::EmptyAnalysis(format!(
+                        "kconfig code analysis of arch {:?} found no guarded code",
+                        arch
+                    )));
+                }
+            }
+            if !maintainers.is_empty() {
+                kc.assign_maintainers(&maintainers);
+            }
+            self.kconfig.push(kc);
+        }
+        Ok(())
+    }
+}
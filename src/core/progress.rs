@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// what a counter has gone through so far, logged once per phase instead of once per file
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PhaseCounts {
+    pub kconfig_files: usize,
+    pub components: usize,
+    pub code_files: usize,
+    pub skipped: usize,
+}
+
+// 5214 -> "5,214"
+fn thousands(value: usize) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// e.g. "parsed 68 Kconfig files, 812 components; analyzed 5,214 code files (3 skipped)"
+impl fmt::Display for PhaseCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut phases = Vec::new();
+        if self.kconfig_files > 0 {
+            phases.push(format!(
+                "parsed {} Kconfig files, {} components",
+                thousands(self.kconfig_files),
+                thousands(self.components)
+            ));
+        }
+        if self.code_files > 0 || self.skipped > 0 {
+            phases.push(format!(
+                "analyzed {} code files ({} skipped)",
+                thousands(self.code_files),
+                thousands(self.skipped)
+            ));
+        }
+        if phases.is_empty() {
+            return write!(f, "nothing processed");
+        }
+        write!(f, "{}", phases.join("; "))
+    }
+}
@@ -0,0 +1,1211 @@
+use crate::core::baseline::BaselineReport;
+use crate::core::file_counter::{FileStat, MarkerFile};
+use crate::core::kconfig_counter::{
+    ArchComparison, CodeDelta, ComponentDiff, DanglingReport, DefconfigReport, DirReport,
+    FlipChange, KconfigComponentType, SnippetReport, SubsystemReport, TraceReport, TraceState,
+};
+use crate::core::version::KernelVersion;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub struct LanguageRow {
+    pub language: String,
+    pub stat: FileStat,
+}
+
+// everything `FileCounter::print` shows, languages already ordered for display
+pub struct CodeSummary {
+    pub version: KernelVersion,
+    pub arch: String,
+    pub languages: Vec<LanguageRow>,
+    pub total: FileStat,
+    // bytes column and the largest file of every language
+    pub verbose: bool,
+    pub cache_hits: Option<usize>,
+    pub hardlinks: usize,
+    // (lines, files) of content-identical files, only when deduplicating by hash
+    pub duplicates: Option<(usize, usize)>,
+}
+
+// a header of `None` lists the components without grouping
+pub struct ComponentGroupRows {
+    pub header: Option<String>,
+    pub names: Vec<String>,
+}
+
+// everything `KconfigCounter::print` shows before entering the interactive loop
+pub struct KconfigSummary {
+    pub version: KernelVersion,
+    pub arch: String,
+    pub groups: Vec<ComponentGroupRows>,
+    pub components: usize,
+    pub choices: usize,
+    pub code_lines: usize,
+    pub help_lines: usize,
+    pub structural_lines: usize,
+    pub types: Vec<(KconfigComponentType, usize)>,
+}
+
+// the common code and what each arch adds on top, see `FileCounter::whole_tree_report`
+pub struct WholeTreeReport {
+    pub version: KernelVersion,
+    // `common` first, then `+ <arch>` for every arch
+    pub portions: Vec<(String, FileStat)>,
+    pub total: FileStat,
+}
+
+pub struct LanguageDeltaRow {
+    pub language: String,
+    pub old: FileStat,
+    pub new: FileStat,
+}
+
+// one arch in two kernel versions, `count --compare`
+pub struct LanguageDeltaReport {
+    pub old_version: KernelVersion,
+    pub new_version: KernelVersion,
+    pub arch: String,
+    pub languages: Vec<LanguageDeltaRow>,
+    pub old_total: FileStat,
+    pub new_total: FileStat,
+}
+
+pub struct MarkerRow {
+    pub language: String,
+    pub todo: usize,
+    pub fixme: usize,
+    pub xxx: usize,
+}
+
+// `--todos`, the languages with markers and the files holding the most of them
+pub struct TodoReport {
+    pub languages: Vec<MarkerRow>,
+    pub files: Vec<MarkerFile>,
+}
+
+// `--spdx`, (license expression, files) in display order
+pub struct SpdxReport {
+    pub licenses: Vec<(String, usize)>,
+    // files without an SPDX tag
+    pub missing: usize,
+}
+
+// `--file-health`, languages by their files without code
+pub struct FileHealthReport {
+    pub languages: Vec<LanguageRow>,
+    pub total: FileStat,
+}
+
+// `--header-guards`
+pub struct HeaderGuardReport {
+    pub pragma_once: usize,
+    pub include_guard: usize,
+    pub unguarded: Vec<PathBuf>,
+}
+
+/// A snapshot of one counter, taken once the analysis is done.
+pub enum AnalysisResult {
+    Code(CodeSummary),
+    Kconfig(KconfigSummary),
+    // `KconfigCounter::dir_report`, `--by-dir`
+    Dirs(DirReport),
+    // `KconfigCounter::subsystem_report`, `--by-subsystem`
+    Subsystems(SubsystemReport),
+    // `FileCounter::whole_tree_report`, below the common code table
+    WholeTree(WholeTreeReport),
+    // `FileCounter::delta_report`, `count --compare`
+    LanguageDelta(LanguageDeltaReport),
+    Todos(TodoReport),
+    Spdx(SpdxReport),
+    FileHealth(FileHealthReport),
+    HeaderGuards(HeaderGuardReport),
+    // `KconfigCounter::compare_arches`, more than one arch; `show_exclusive` lists
+    // the components only one arch has
+    Comparison {
+        comparison: ArchComparison,
+        show_exclusive: bool,
+    },
+    // `diff --compare-defconfig`
+    Defconfig(DefconfigReport),
+    // `diff --code-delta`
+    CodeDelta(CodeDelta),
+    // `diff <other>`
+    ComponentDiff(ComponentDiff),
+    // `--dangling-depends`
+    Dangling(DanglingReport),
+    // `analyze --top-snippets`
+    Snippets(SnippetReport),
+    // `trace`
+    Trace(TraceReport),
+    // `validate --check-baseline`
+    Baseline(BaselineReport),
+}
+
+pub trait Renderer {
+    fn render(&self, result: &AnalysisResult, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// The fixed-width tables printed by the command line tool.
+///
+/// ```
+/// use auto_script::core::report::{Renderer, TextRenderer};
+/// use auto_script::FileCounter;
+/// use std::fs;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// fs::write(dir.path().join("smp.c"), "int smp;\n").unwrap();
+/// let mut fc = FileCounter::new("riscv".into(), "6.9.5".into(), dir.path().into());
+/// fc.search().unwrap();
+///
+/// let mut out = Vec::new();
+/// TextRenderer::default().render(&fc.snapshot(), &mut out).unwrap();
+/// assert!(String::from_utf8(out).unwrap().contains("Linux-6.9.5 Arch RISCV"));
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct TextRenderer {
+    // total table width, each table has its own default
+    width: Option<usize>,
+    // bold table titles
+    color: bool,
+}
+
+/// When the tables and the interactive snippets are colored, `--color`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorChoice {
+    // only when the output is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIRECTIVE: &str = "\x1b[35m";
+const SYMBOL: &str = "\x1b[1;33m";
+const KEYWORD: &str = "\x1b[2m";
+const COMMENT: &str = "\x1b[32m";
+const HEADER: &str = "\x1b[1;36m";
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register", "return",
+    "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned",
+    "void", "volatile", "while",
+];
+
+pub const CODE_WIDTH: usize = 70;
+pub const KCONFIG_WIDTH: usize = 90;
+// narrower tables would break the fixed numeric columns
+pub const MIN_WIDTH: usize = 70;
+
+impl TextRenderer {
+    pub fn with_width(width: usize) -> Self {
+        TextRenderer {
+            width: Some(width.max(MIN_WIDTH)),
+            color: false,
+        }
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    // the width of the terminal stdout is attached to, if any
+    pub fn for_terminal() -> Self {
+        match terminal_size::terminal_size() {
+            Some((terminal_size::Width(width), _)) => TextRenderer::with_width(width as usize),
+            None => TextRenderer::default(),
+        }
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render(&self, result: &AnalysisResult, out: &mut dyn Write) -> io::Result<()> {
+        match result {
+            AnalysisResult::Code(summary) => {
+                render_code(summary, self.width.unwrap_or(CODE_WIDTH), self.color, out)
+            }
+            AnalysisResult::Kconfig(summary) => render_kconfig(
+                summary,
+                self.width.unwrap_or(KCONFIG_WIDTH),
+                self.color,
+                out,
+            ),
+            AnalysisResult::Dirs(report) => {
+                render_dirs(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::Subsystems(report) => {
+                render_subsystems(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::WholeTree(report) => {
+                render_whole_tree(report, self.width.unwrap_or(CODE_WIDTH), self.color, out)
+            }
+            AnalysisResult::LanguageDelta(report) => {
+                render_language_delta(report, self.width.unwrap_or(CODE_WIDTH), self.color, out)
+            }
+            AnalysisResult::Todos(report) => {
+                render_todos(report, self.width.unwrap_or(CODE_WIDTH), out)
+            }
+            AnalysisResult::Spdx(report) => {
+                render_spdx(report, self.width.unwrap_or(CODE_WIDTH), out)
+            }
+            AnalysisResult::FileHealth(report) => {
+                render_file_health(report, self.width.unwrap_or(CODE_WIDTH), out)
+            }
+            AnalysisResult::HeaderGuards(report) => {
+                render_header_guards(report, self.width.unwrap_or(CODE_WIDTH), out)
+            }
+            AnalysisResult::Comparison {
+                comparison,
+                show_exclusive,
+            } => render_comparison(
+                comparison,
+                *show_exclusive,
+                self.width.unwrap_or(KCONFIG_WIDTH),
+                self.color,
+                out,
+            ),
+            AnalysisResult::Defconfig(report) => {
+                render_defconfig(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::CodeDelta(delta) => {
+                render_code_delta(delta, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::ComponentDiff(diff) => {
+                render_component_diff(diff, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::Dangling(report) => {
+                render_dangling(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::Snippets(report) => {
+                render_snippets(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::Trace(report) => {
+                render_trace(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+            AnalysisResult::Baseline(report) => {
+                render_baseline(report, self.width.unwrap_or(KCONFIG_WIDTH), self.color, out)
+            }
+        }
+    }
+}
+
+// names wider than the column keep their start and end a `~`
+fn elide(name: &str, width: usize) -> String {
+    if name.chars().count() <= width {
+        return name.to_string();
+    }
+    let kept: String = name.chars().take(width.saturating_sub(1)).collect();
+    format!("{}~", kept)
+}
+
+// the centered `Linux-<version> Arch <ARCH>` line heading both tables,
+// followed by the release name when the Makefile has one
+fn title(
+    out: &mut dyn Write,
+    version: &KernelVersion,
+    arch: &str,
+    width: usize,
+    color: bool,
+) -> io::Result<()> {
+    let mut title = format!("Linux-{} Arch {}", version, arch.to_uppercase());
+    if let Some(name) = &version.name {
+        title.push_str(&format!(" ({})", name));
+    }
+    heading(out, &title, width, color)
+}
+
+fn heading(out: &mut dyn Write, text: &str, width: usize, color: bool) -> io::Result<()> {
+    let text = format!("{:^width$}", text);
+    if color {
+        writeln!(out, "{}{}{}", BOLD, text, RESET)
+    } else {
+        writeln!(out, "{}", text)
+    }
+}
+
+fn render_code(
+    summary: &CodeSummary,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    title(out, &summary.version, &summary.arch, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    write!(
+        out,
+        "{: <16} {: <8} {: <8} {: <8} {: <8} {: <8} {: <8}",
+        "Language", "files", "blank", "comment", "code", "avg", "max"
+    )?;
+    if summary.verbose {
+        write!(out, " {: <12}", "bytes")?;
+    }
+    writeln!(out)?;
+    writeln!(out, "{:-<width$}", "")?;
+
+    for row in &summary.languages {
+        code_row(out, &elide(&row.language, 16), &row.stat, summary.verbose)?;
+    }
+
+    writeln!(out, "{:-<width$}", "")?;
+    code_row(out, "SUM:", &summary.total, summary.verbose)?;
+    writeln!(out, "{:-<width$}", "")?;
+    if let Some(cache_hits) = summary.cache_hits {
+        writeln!(
+            out,
+            "{: <16} {}/{} files",
+            "Cache hits:",
+            cache_hits,
+            summary.total.files()
+        )?;
+        writeln!(out, "{:-<width$}", "")?;
+    }
+    if summary.hardlinks > 0 {
+        writeln!(
+            out,
+            "{: <16} {} files counted once",
+            "Hardlinks:", summary.hardlinks
+        )?;
+        writeln!(out, "{:-<width$}", "")?;
+    }
+    if let Some((lines, files)) = summary.duplicates {
+        writeln!(
+            out,
+            "{: <16} {} lines in {} content-identical files",
+            "Duplicates:", lines, files
+        )?;
+        writeln!(out, "{:-<width$}", "")?;
+    }
+
+    if summary.verbose {
+        writeln!(out, "{: <16} {: <8} path", "Largest file", "code")?;
+        writeln!(out, "{:-<width$}", "")?;
+        for row in &summary.languages {
+            if let Some(path) = row.stat.max_path() {
+                writeln!(
+                    out,
+                    "{: <16} {: <8} {}",
+                    elide(&row.language, 16),
+                    row.stat.max_code(),
+                    path.display()
+                )?;
+            }
+        }
+        writeln!(out, "{:-<width$}", "")?;
+    }
+    Ok(())
+}
+
+fn code_row(out: &mut dyn Write, name: &str, stat: &FileStat, verbose: bool) -> io::Result<()> {
+    write!(
+        out,
+        "{: <16} {: <8} {: <8} {: <8} {: <8} {: <8.1} {: <8}",
+        name,
+        stat.files(),
+        stat.blank(),
+        stat.comment(),
+        stat.code(),
+        stat.average(),
+        stat.max_code()
+    )?;
+    if verbose {
+        write!(out, " {: <12}", stat.bytes())?;
+    }
+    writeln!(out)
+}
+
+fn render_kconfig(
+    summary: &KconfigSummary,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    // two name columns around a ` | ` separator
+    let half = (width - 3) / 2;
+    // the counts leave room for their longest label, " Structural Lines"
+    let count = width.saturating_sub(half + 18).min(20);
+    writeln!(out, "{:-<width$}", "")?;
+    title(out, &summary.version, &summary.arch, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{:^half$} {:^half$}", "Component", "Component")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for group in &summary.groups {
+        if let Some(header) = &group.header {
+            writeln!(out, "[{}]", header)?;
+        }
+        for pair in group.names.chunks(2) {
+            let name2 = pair.get(1).map(|name| name.as_str()).unwrap_or("");
+            writeln!(
+                out,
+                "{:^half$} | {:^half$}",
+                elide(&pair[0], half),
+                elide(name2, half)
+            )?;
+        }
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{:^half$} {:>count$} Components",
+        "SUM:", summary.components
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{:^half$} {:>count$} Choices", "SUM:", summary.choices)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{:^half$} {:>count$} Total Code Lines",
+        "SUM:", summary.code_lines
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{:^half$} {:>count$} Help Lines",
+        "SUM:", summary.help_lines
+    )?;
+    writeln!(
+        out,
+        "{:^half$} {:>count$} Structural Lines",
+        "SUM:", summary.structural_lines
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+
+    writeln!(out, "{:^half$} {:>count$}", "Type", "Components")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for (value_type, components) in &summary.types {
+        writeln!(
+            out,
+            "{:^half$} {:>count$}",
+            value_type.to_string(),
+            components
+        )?;
+    }
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_dirs(
+    report: &DirReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    // the directory and example columns share what the count leaves over
+    let dir = (width - 14) / 2;
+    let examples = width - dir - 14;
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!("Linux-{} {} Directories", report.version, report.arch);
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{: <dir$} {: <12} examples", "Directory", "components")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for row in &report.dirs {
+        let mut names = row.components[..row.components.len().min(3)].join(", ");
+        if row.components.len() > 3 {
+            names.push_str(&format!(" +{} more", row.components.len() - 3));
+        }
+        writeln!(
+            out,
+            "{: <dir$} {: <12} {}",
+            elide(&row.dir, dir),
+            row.components.len(),
+            elide(&names, examples)
+        )?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{: <dir$} {}", "SUM:", report.dirs.len())?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_subsystems(
+    report: &SubsystemReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let with_code = report.subsystems.iter().any(|row| row.code_lines.is_some());
+    // the components, one column per type and the optional code column are fixed
+    let fixed = 12 + 9 * KconfigComponentType::ALL.len() + if with_code { 9 } else { 0 };
+    let name = width.saturating_sub(fixed).max(12);
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!("Linux-{} {} Subsystems", report.version, report.arch);
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    let mut header = format!("{: <name$} {: <11}", "Subsystem", "components");
+    for value_type in KconfigComponentType::ALL {
+        header.push_str(&format!(" {: <8}", value_type.to_string()));
+    }
+    if with_code {
+        header.push_str(" code");
+    }
+    writeln!(out, "{}", header.trim_end())?;
+    writeln!(out, "{:-<width$}", "")?;
+    for row in &report.subsystems {
+        let mut line = format!(
+            "{: <name$} {: <11}",
+            elide(&row.subsystem, name),
+            row.components
+        );
+        for (_, count) in &row.types {
+            line.push_str(&format!(" {: <8}", count));
+        }
+        if let Some(code_lines) = row.code_lines {
+            line.push_str(&format!(" {}", code_lines));
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    let components: usize = report.subsystems.iter().map(|row| row.components).sum();
+    writeln!(
+        out,
+        "{: <name$} {} components in {} subsystems",
+        "SUM:",
+        components,
+        report.subsystems.len()
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_whole_tree(
+    report: &WholeTreeReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!("Linux-{} Whole Tree", report.version);
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <30} {: <10} {: <10} {: <10} {: <10}",
+        "Portion", "files", "blank", "comment", "code"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    let row = |out: &mut dyn Write, portion: &str, stat: &FileStat| {
+        writeln!(
+            out,
+            "{: <30} {: <10} {: <10} {: <10} {: <10}",
+            portion,
+            stat.files(),
+            stat.blank(),
+            stat.comment(),
+            stat.code()
+        )
+    };
+    for (portion, stat) in &report.portions {
+        row(out, portion, stat)?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    row(out, "SUM:", &report.total)?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_language_delta(
+    report: &LanguageDeltaReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!(
+        "Linux-{} -> Linux-{} Arch {}",
+        report.old_version,
+        report.new_version,
+        report.arch.to_uppercase()
+    );
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <16} {: <12} {: <12} {: <12} {: <12}",
+        "Language", "files", "blank", "comment", "code"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    let delta = |a: usize, b: usize| b as i64 - a as i64;
+    let row = |out: &mut dyn Write, name: &str, a: &FileStat, b: &FileStat| {
+        writeln!(
+            out,
+            "{: <16} {: <+12} {: <+12} {: <+12} {: <+12}",
+            name,
+            delta(a.files(), b.files()),
+            delta(a.blank(), b.blank()),
+            delta(a.comment(), b.comment()),
+            delta(a.code(), b.code())
+        )
+    };
+    for language in &report.languages {
+        row(out, &language.language, &language.old, &language.new)?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    row(out, "SUM:", &report.old_total, &report.new_total)?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_todos(report: &TodoReport, width: usize, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "{: <16} {: <8} {: <8} {: <8} {: <8}",
+        "Language", "TODO", "FIXME", "XXX", "total"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    let row = |out: &mut dyn Write, name: &str, todo: usize, fixme: usize, xxx: usize| {
+        writeln!(
+            out,
+            "{: <16} {: <8} {: <8} {: <8} {: <8}",
+            name,
+            todo,
+            fixme,
+            xxx,
+            todo + fixme + xxx
+        )
+    };
+    let (mut todo, mut fixme, mut xxx) = (0, 0, 0);
+    for language in &report.languages {
+        row(
+            out,
+            &language.language,
+            language.todo,
+            language.fixme,
+            language.xxx,
+        )?;
+        todo += language.todo;
+        fixme += language.fixme;
+        xxx += language.xxx;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    row(out, "SUM:", todo, fixme, xxx)?;
+    writeln!(out, "{:-<width$}", "")?;
+
+    writeln!(out, "{: <16} path", "markers")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for file in &report.files {
+        writeln!(out, "{: <16} {}", file.markers, file.path.display())?;
+    }
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_spdx(report: &SpdxReport, width: usize, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "{: <50} {: <10}", "SPDX License", "files")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for (license, files) in &report.licenses {
+        writeln!(out, "{: <50} {: <10}", license, files)?;
+    }
+    writeln!(out, "{: <50} {: <10}", "(none)", report.missing)?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_file_health(
+    report: &FileHealthReport,
+    width: usize,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{: <16} {: <12} {: <12} {: <12}",
+        "Language", "files", "empty", "comment-only"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    let row = |out: &mut dyn Write, name: &str, stat: &FileStat| {
+        writeln!(
+            out,
+            "{: <16} {: <12} {: <12} {: <12}",
+            name,
+            stat.files(),
+            stat.empty_files(),
+            stat.comment_only_files()
+        )
+    };
+    for language in &report.languages {
+        row(out, &language.language, &language.stat)?;
+    }
+    row(out, "SUM:", &report.total)?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_header_guards(
+    report: &HeaderGuardReport,
+    width: usize,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{: <50} {: <10}", "C Header Guard", "files")?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{: <50} {: <10}", "#pragma once", report.pragma_once)?;
+    writeln!(
+        out,
+        "{: <50} {: <10}",
+        "#ifndef guard", report.include_guard
+    )?;
+    writeln!(out, "{: <50} {: <10}", "(none)", report.unguarded.len())?;
+    writeln!(out, "{:-<width$}", "")?;
+    for path in &report.unguarded {
+        writeln!(out, "{}", path.display())?;
+    }
+    if !report.unguarded.is_empty() {
+        writeln!(out, "{:-<width$}", "")?;
+    }
+    Ok(())
+}
+
+fn render_comparison(
+    comparison: &ArchComparison,
+    show_exclusive: bool,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!(
+        "Linux-{} Arch {} Comparison",
+        comparison.version,
+        comparison.arches.join(", ").to_uppercase()
+    );
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{:^45} {:>20} Components",
+        "Shared by all",
+        comparison.shared.len()
+    )?;
+    if comparison.arches.len() > 2 {
+        writeln!(
+            out,
+            "{:^45} {:>20} Components",
+            "Shared by some",
+            comparison.partial.len()
+        )?;
+    }
+    for (arch, names) in &comparison.exclusive {
+        writeln!(
+            out,
+            "{:^45} {:>20} Components",
+            format!("Only {}", arch),
+            names.len()
+        )?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+
+    if show_exclusive {
+        for (arch, names) in &comparison.exclusive {
+            writeln!(out, "[only {}]", arch)?;
+            for pair in names.chunks(2) {
+                let name2 = pair.get(1).map(|name| name.as_str()).unwrap_or("");
+                writeln!(out, "{:^45} | {:^45}", pair[0], name2)?;
+            }
+        }
+        writeln!(out, "{:-<width$}", "")?;
+    }
+
+    for component in &comparison.divergent {
+        writeln!(out, "{}", component.name)?;
+        for value in &component.values {
+            let mut details = Vec::new();
+            if component.attributes.contains(&"type") {
+                details.push(format!("type {}", value.value_type));
+            }
+            if component.attributes.contains(&"default") {
+                details.push(format!("default {:?}", value.default));
+            }
+            writeln!(out, "  {: <16} {}", value.arch, details.join(", "))?;
+        }
+    }
+    writeln!(
+        out,
+        "{:^45} {:>20} Divergent Components",
+        "SUM:",
+        comparison.divergent.len()
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_defconfig(
+    report: &DefconfigReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!(
+        "Linux-{} Arch {}: {} -> {}",
+        report.version,
+        report.arch.to_uppercase(),
+        report.a,
+        report.b
+    );
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <32} {: <10} {: <10} {: <12} {: <12}",
+        "Component", "a", "b", "code lines", "make lines"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    for row in &report.rows {
+        let code_lines = match row.change {
+            FlipChange::Enabled => format!("+{}", row.code_lines),
+            FlipChange::Disabled => format!("-{}", row.code_lines),
+            FlipChange::Changed => row.code_lines.to_string(),
+        };
+        writeln!(
+            out,
+            "{: <32} {: <10} {: <10} {: <12} {: <12}",
+            row.name, row.a, row.b, code_lines, row.makefile_lines
+        )?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <32} +{} / -{} guarded code lines, {} differing symbols not parsed",
+        "SUM:", report.gained, report.lost, report.unparsed
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_code_delta(
+    delta: &CodeDelta,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!("{} -> {} Code Lines", delta.old, delta.new);
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <45} {: <12} {: <12} {: <12}",
+        "Component", "old", "new", "delta"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    for (header, rows) in [
+        ("Changed", &delta.changed),
+        ("Only old", &delta.only_old),
+        ("Only new", &delta.only_new),
+    ] {
+        writeln!(out, "[{}]", header)?;
+        for row in rows {
+            writeln!(
+                out,
+                "{: <45} {: <12} {: <12} {:+}",
+                row.name, row.old, row.new, row.delta
+            )?;
+        }
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    let total: i64 = delta
+        .changed
+        .iter()
+        .chain(&delta.only_old)
+        .chain(&delta.only_new)
+        .map(|row| row.delta)
+        .sum();
+    writeln!(
+        out,
+        "{: <45} {:+} lines, {} changed, {} unchanged",
+        "SUM:",
+        total,
+        delta.changed.len(),
+        delta.unchanged
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_component_diff(
+    diff: &ComponentDiff,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    heading(out, &format!("{} -> {}", diff.old, diff.new), width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+
+    writeln!(
+        out,
+        "Removed, only in {} ({}):",
+        diff.old,
+        diff.removed.len()
+    )?;
+    for name in &diff.removed {
+        writeln!(out, "  - {}", name)?;
+    }
+    writeln!(out, "Added, only in {} ({}):", diff.new, diff.added.len())?;
+    for name in &diff.added {
+        writeln!(out, "  + {}", name)?;
+    }
+    writeln!(out, "Changed ({}):", diff.changed.len())?;
+    for component in &diff.changed {
+        writeln!(out, "  ~ {}", component.name)?;
+        for change in &component.changes {
+            writeln!(
+                out,
+                "      {: <10} {:?} -> {:?}",
+                change.attribute, change.old, change.new
+            )?;
+        }
+    }
+
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <30} {} removed, {} added, {} changed",
+        "SUM:",
+        diff.removed.len(),
+        diff.added.len(),
+        diff.changed.len()
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_dangling(
+    report: &DanglingReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!("{} Dangling Dependencies", report.label);
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{: <45} missing dependency", "Component")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for (name, missing) in &report.dangling {
+        writeln!(out, "{: <45} {}", name, missing)?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <45} {} Dependencies",
+        "SUM:",
+        report.dangling.len()
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_snippets(
+    report: &SnippetReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!("{} Largest Guarded Blocks", report.label);
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(out, "{: <35} {: <8} file", "Component", "lines")?;
+    writeln!(out, "{:-<width$}", "")?;
+    for snippet in &report.snippets {
+        writeln!(
+            out,
+            "{: <35} {: <8} {}",
+            snippet.component,
+            snippet.lines,
+            snippet.file.display()
+        )?;
+    }
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_trace(
+    report: &TraceReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!(
+        "Trace {} Arch {}",
+        report.symbol,
+        report.arch.to_uppercase()
+    );
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    for step in &report.steps {
+        let label = format!("Linux-{}", step.version);
+        match &step.state {
+            TraceState::Missing => writeln!(out, "{: <16} missing", label)?,
+            TraceState::Present(attributes) => {
+                writeln!(out, "{: <16} present", label)?;
+                for (attribute, value) in attributes {
+                    writeln!(out, "    {: <10} {:?}", attribute, value)?;
+                }
+            }
+            TraceState::Changed(changes) => {
+                let state = if changes.is_empty() {
+                    "unchanged"
+                } else {
+                    "changed"
+                };
+                writeln!(out, "{: <16} {}", label, state)?;
+                for change in changes {
+                    writeln!(
+                        out,
+                        "    {: <10} {:?} -> {:?}",
+                        change.attribute, change.old, change.new
+                    )?;
+                }
+            }
+        }
+    }
+    writeln!(out, "{:-<width$}", "")
+}
+
+fn render_baseline(
+    report: &BaselineReport,
+    width: usize,
+    color: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(out, "{:-<width$}", "")?;
+    let text = format!(
+        "Baseline Linux-{} -> Linux-{}",
+        report.old_version, report.new_version
+    );
+    heading(out, &text, width, color)?;
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <50} {: <12} {: <12} {: <12}",
+        "Metric", "baseline", "current", "drift"
+    )?;
+    writeln!(out, "{:-<width$}", "")?;
+    let or_dash = |value: Option<u64>| value.map_or("-".to_string(), |value| value.to_string());
+    for metric in &report.metrics {
+        let drift = match (metric.drift, metric.baseline) {
+            (Some(drift), _) => format!("{:+.2}%", drift),
+            (None, None) => "new".to_string(),
+            (None, Some(_)) => "not measured".to_string(),
+        };
+        writeln!(
+            out,
+            "{: <50} {: <12} {: <12} {: <12}{}",
+            metric.name,
+            or_dash(metric.baseline),
+            or_dash(metric.current),
+            drift,
+            if metric.over { " !" } else { "" }
+        )?;
+    }
+    writeln!(out, "{:-<width$}", "")?;
+    writeln!(
+        out,
+        "{: <50} {} metrics exceed the {}% tolerance",
+        "SUM:",
+        report.exceeded(),
+        report.tolerance
+    )?;
+    writeln!(out, "{:-<width$}", "")
+}
+
+/// Colors a guarded code snippet for the terminal.
+///
+/// Preprocessor lines are magenta, comments green, C keywords dimmed and the
+/// `CONFIG_` symbol of `component` (also as `_MODULE`) bold yellow:
+///
+/// ```
+/// use auto_script::core::report::highlight_snippet;
+///
+/// let colored = highlight_snippet("#ifdef CONFIG_SMP\nint cpus; // boot\n", "SMP");
+/// assert!(colored.contains("\x1b[1;33mCONFIG_SMP\x1b[0m"));
+/// assert!(colored.contains("\x1b[2mint\x1b[0m"));
+/// assert!(colored.contains("\x1b[32m// boot\x1b[0m"));
+/// ```
+pub fn highlight_snippet(code: &str, component: &str) -> String {
+    let symbol = format!("CONFIG_{}", component);
+    let module = format!("{}_MODULE", symbol);
+    let mut out = String::new();
+    // a `/* */` comment may span lines
+    let mut in_comment = false;
+    for line in code.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        let directive = !in_comment && line.trim_start().starts_with('#');
+        let base = if directive { DIRECTIVE } else { "" };
+        out.push_str(base);
+
+        let mut rest = line;
+        while !rest.is_empty() {
+            if in_comment || rest.starts_with("/*") {
+                let from = if in_comment { 0 } else { 2 };
+                let end = match rest[from..].find("*/") {
+                    Some(index) => from + index + 2,
+                    None => rest.len(),
+                };
+                in_comment = !rest[from..end].ends_with("*/");
+                push_styled(&mut out, COMMENT, &rest[..end], base);
+                rest = &rest[end..];
+            } else if rest.starts_with("//") {
+                push_styled(&mut out, COMMENT, rest, base);
+                rest = "";
+            } else if rest.starts_with(['"', '\'']) {
+                let end = literal_end(rest);
+                out.push_str(&rest[..end]);
+                rest = &rest[end..];
+            } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                if word == symbol || word == module {
+                    push_styled(&mut out, SYMBOL, word, base);
+                } else if !directive && C_KEYWORDS.contains(&word) {
+                    push_styled(&mut out, KEYWORD, word, base);
+                } else {
+                    out.push_str(word);
+                }
+                rest = &rest[end..];
+            } else {
+                let end = rest.chars().next().map_or(1, char::len_utf8);
+                out.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+        if directive {
+            out.push_str(RESET);
+        }
+        out.push_str(newline);
+    }
+    out
+}
+
+/// Makes the `file:line:` header above a snippet stand out, bold cyan.
+pub fn highlight_header(header: &str) -> String {
+    format!("{}{}{}", HEADER, header, RESET)
+}
+
+// the line's own style is restored after each token
+fn push_styled(out: &mut String, style: &str, text: &str, base: &str) {
+    out.push_str(style);
+    out.push_str(text);
+    out.push_str(RESET);
+    out.push_str(base);
+}
+
+// the length of the string or character literal `text` starts with, escapes included
+fn literal_end(text: &str) -> usize {
+    let quote = text.as_bytes()[0];
+    let mut escaped = false;
+    for (index, byte) in text.bytes().enumerate().skip(1) {
+        if byte == quote && !escaped {
+            return index + 1;
+        }
+        escaped = byte == b'\\' && !escaped;
+    }
+    text.len()
+}
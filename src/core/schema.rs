@@ -0,0 +1,58 @@
+//! The JSON documents written by `--format json`, `kconfig --export-kconfig`
+//! and `trend --trend-format json`.
+//!
+//! Each document is an object whose `schema_version` sits next to the
+//! report's own fields:
+//!
+//! | Command | Fields |
+//! |---|---|
+//! | `count` | `version`, `arches`, `all_arches`, `common`, `docs`, each a counter with `arch`, `version`, `languages`, `total`, `spdx`, `spdx_missing` and with `--todos` `todo_files`; every row has `files`, `blank`, `comment`, `code`, `bytes`, `average`, `max_code`, `max_path`, `markers` |
+//! | `diff <other>` | `old`, `new`, `removed`, `added`, `changed` |
+//! | `diff <other> --code-delta` | `old`, `new`, `changed`, `unchanged`, `only_old`, `only_new` |
+//! | `diff --compare-defconfig` | `version`, `arch`, `a`, `b`, `rows`, `gained`, `lost`, `unparsed` |
+//! | several `--arch` with `kconfig` | `version`, `arches`, `shared`, `partial`, `exclusive`, `divergent` |
+//! | `kconfig --by-dir` | `version`, `arch`, `dirs`, each with `dir`, `components` |
+//! | `kconfig --by-subsystem` | `version`, `arch`, `subsystems`, each with `subsystem`, `components`, `types` as `[type, count]` pairs, `code_lines` |
+//! | `kconfig --export-kconfig` | `arches`, each with `arch`, `version`, `components`, `choices`, `directories` |
+//! | `trend` | `points`, each with `version`, `arch`, `metric`, `value` |
+//!
+//! [`SCHEMA_VERSION`] is bumped whenever one of these fields is renamed,
+//! removed or changes meaning; adding a field keeps the version.
+
+use crate::core::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(data: T) -> Self {
+        Versioned {
+            schema_version: SCHEMA_VERSION,
+            data,
+        }
+    }
+
+    // a newer document may have changed the meaning of a field we read
+    pub fn into_data(self) -> Result<T> {
+        if self.schema_version > SCHEMA_VERSION {
+            return Err(Error::NewerVersion {
+                what: "schema",
+                found: self.schema_version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        Ok(self.data)
+    }
+}
+
+// pretty printed, `data` must serialize to a JSON object
+pub fn to_json<T: Serialize>(data: &T) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Versioned::new(data))
+}
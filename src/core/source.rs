@@ -0,0 +1,203 @@
+//! Where the counters read a kernel tree from.
+//!
+//! Paths handed to a [`SourceTree`] are the ones the counters build from the kernel
+//! path, e.g. `/opt/linux/arch/riscv/Kconfig`; a tree that is not a directory on disk
+//! maps them onto its own contents.
+
+use crate::core::cache::mtime_of;
+#[cfg(feature = "git")]
+use crate::core::error::{Error, Result};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    // bytes of a file, 0 for directories and unreadable entries
+    pub len: u64,
+    // only known on disk, entries without one bypass the scan cache
+    pub mtime: Option<(u64, u32)>,
+    // device and inode, hardlinks share them
+    pub inode: Option<(u64, u64)>,
+}
+
+pub trait SourceTree: fmt::Debug {
+    fn read(&self, path: &Path) -> io::Result<Box<dyn BufRead + '_>>;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+
+    // a file `read` can open
+    fn exists(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+}
+
+// `fs::read_dir` order depends on the filesystem, sorting by name makes it reproducible
+pub fn read_dir(tree: &dyn SourceTree, path: &Path, sorted: bool) -> io::Result<Vec<DirEntry>> {
+    let mut entries = tree.read_dir(path)?;
+    if sorted {
+        entries.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+    }
+    Ok(entries)
+}
+
+/// The working tree on disk.
+#[derive(Debug, Default)]
+pub struct FsTree;
+
+impl SourceTree for FsTree {
+    fn read(&self, path: &Path) -> io::Result<Box<dyn BufRead + '_>> {
+        Ok(Box::new(io::BufReader::new(File::open(path)?)))
+    }
+
+    // symlinks are followed, an entry whose target is gone counts as an empty file
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let metadata = fs::metadata(&path).ok();
+            #[cfg(unix)]
+            let inode = metadata.as_ref().map(|meta| {
+                use std::os::unix::fs::MetadataExt;
+                (meta.dev(), meta.ino())
+            });
+            #[cfg(not(unix))]
+            let inode = None;
+            entries.push(DirEntry {
+                is_dir: metadata.as_ref().is_some_and(|meta| meta.is_dir()),
+                len: metadata.as_ref().map_or(0, |meta| meta.len()),
+                mtime: metadata.as_ref().map(mtime_of),
+                inode,
+                path,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+}
+
+/// One revision of a git repository, `root` is the kernel path the counters are given.
+///
+/// Symlinks and submodules in the revision are left out.
+#[cfg(feature = "git")]
+pub struct GitTree {
+    repo: git2::Repository,
+    tree: git2::Oid,
+    root: PathBuf,
+    rev: String,
+}
+
+#[cfg(feature = "git")]
+impl GitTree {
+    // `root` may be a working tree or a bare repository
+    pub fn open(root: &Path, rev: &str) -> Result<Self> {
+        let git_error = |source| Error::Git {
+            path: root.to_path_buf(),
+            rev: rev.to_string(),
+            source,
+        };
+        let repo = git2::Repository::open(root).map_err(git_error)?;
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(git_error)?
+            .id();
+        Ok(GitTree {
+            repo,
+            tree,
+            root: root.to_path_buf(),
+            rev: rev.to_string(),
+        })
+    }
+
+    fn object(&self, path: &Path) -> io::Result<git2::Object<'_>> {
+        let relative = path.strip_prefix(&self.root).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is outside of {}", path.display(), self.root.display()),
+            )
+        })?;
+        let tree = self.repo.find_tree(self.tree).map_err(io::Error::other)?;
+        if relative.as_os_str().is_empty() {
+            return Ok(tree.into_object());
+        }
+        let entry = tree.get_path(relative).map_err(|err| match err.code() {
+            git2::ErrorCode::NotFound => io::Error::new(io::ErrorKind::NotFound, err),
+            _ => io::Error::other(err),
+        })?;
+        entry.to_object(&self.repo).map_err(io::Error::other)
+    }
+}
+
+#[cfg(feature = "git")]
+impl fmt::Debug for GitTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GitTree({} at {})", self.root.display(), self.rev)
+    }
+}
+
+#[cfg(feature = "git")]
+impl SourceTree for GitTree {
+    fn read(&self, path: &Path) -> io::Result<Box<dyn BufRead + '_>> {
+        let object = self.object(path)?;
+        let blob = object.as_blob().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file at {}", path.display(), self.rev),
+            )
+        })?;
+        Ok(Box::new(io::Cursor::new(blob.content().to_vec())))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let object = self.object(path)?;
+        let tree = object.as_tree().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory at {}", path.display(), self.rev),
+            )
+        })?;
+        let odb = self.repo.odb().map_err(io::Error::other)?;
+        let mut entries = Vec::new();
+        for entry in tree.iter() {
+            let is_dir = match entry.kind() {
+                Some(git2::ObjectType::Tree) => true,
+                Some(git2::ObjectType::Blob) if entry.filemode() != 0o120000 => false,
+                _ => continue,
+            };
+            let len = match is_dir {
+                true => 0,
+                false => odb
+                    .read_header(entry.id())
+                    .map_or(0, |(size, _)| size as u64),
+            };
+            entries.push(DirEntry {
+                path: path.join(String::from_utf8_lossy(entry.name_bytes()).as_ref()),
+                is_dir,
+                len,
+                mtime: None,
+                inode: None,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.object(path)
+            .is_ok_and(|object| object.kind() == Some(git2::ObjectType::Blob))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.object(path)
+            .is_ok_and(|object| object.kind() == Some(git2::ObjectType::Tree))
+    }
+}
@@ -0,0 +1,80 @@
+use crate::core::schema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum TrendFormat {
+    Csv,
+    Json,
+}
+
+// one row of the long format output, ready for plotting
+#[derive(Serialize)]
+pub struct TrendPoint {
+    pub version: String,
+    pub arch: String,
+    pub metric: String,
+    pub value: u64,
+}
+
+impl TrendPoint {
+    pub fn from_metrics(
+        version: &str,
+        arch: &str,
+        prefix: &str,
+        metrics: BTreeMap<String, u64>,
+    ) -> Vec<Self> {
+        metrics
+            .into_iter()
+            .map(|(metric, value)| TrendPoint {
+                version: version.to_string(),
+                arch: arch.to_string(),
+                metric: format!("{}.{}", prefix, metric),
+                value,
+            })
+            .collect()
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct TrendReport<'a> {
+    points: &'a [TrendPoint],
+}
+
+pub fn write_trend(
+    out: &mut dyn Write,
+    points: &[TrendPoint],
+    format: TrendFormat,
+) -> io::Result<()> {
+    match format {
+        TrendFormat::Csv => {
+            writeln!(out, "version,arch,metric,value")?;
+            for point in points {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    csv_field(&point.version),
+                    csv_field(&point.arch),
+                    csv_field(&point.metric),
+                    point.value
+                )?;
+            }
+        }
+        TrendFormat::Json => writeln!(
+            out,
+            "{}",
+            schema::to_json(&TrendReport { points }).map_err(io::Error::from)?
+        )?,
+    }
+    Ok(())
+}
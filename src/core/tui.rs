@@ -0,0 +1,408 @@
+use crate::core::error::{Error, Result};
+use crate::core::graph::DependencyGraph;
+use crate::core::kconfig_counter::KconfigCounter;
+use crate::core::utils::glob_match;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::{Block, List, ListState, Paragraph, Tabs};
+use ratatui::Frame;
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal};
+
+const TABS: [&str; 3] = ["Attributes", "Dependencies", "Snippets"];
+
+// levels of the dependency tree below the selected component
+const TREE_DEPTH: usize = 3;
+
+const HELP: &str = "↑/↓ move  Tab switch tab  / search  Enter open  Esc back  q quit";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Components,
+    Search,
+    Detail,
+}
+
+/// The state of the `--tui` browser, one component list and the tabs of the selected one.
+///
+/// Keys are fed through `handle_key` so the browser runs without a terminal too:
+///
+/// ```
+/// use auto_script::core::tui::Browser;
+/// use auto_script::KconfigCounter;
+/// use ratatui::crossterm::event::{KeyCode, KeyEvent};
+///
+/// let kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), "Kconfig".into());
+/// let mut browser = Browser::new(&kc);
+/// assert_eq!(browser.selected(), None);
+/// assert!(!browser.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+/// ```
+pub struct Browser<'a> {
+    kc: &'a KconfigCounter,
+    graph: DependencyGraph,
+    // sorted, the filter narrows it down to `visible`
+    names: Vec<&'a str>,
+    visible: Vec<&'a str>,
+    filter: String,
+    components: ListState,
+    tab: usize,
+    focus: Focus,
+    // (depth, symbol, edge label) rows of the dependency tab
+    tree: Vec<(usize, String, &'static str)>,
+    tree_state: ListState,
+    scroll: u16,
+    status: String,
+}
+
+impl<'a> Browser<'a> {
+    pub fn new(kc: &'a KconfigCounter) -> Self {
+        let mut names: Vec<&str> = kc.components().map(|(name, _)| name).collect();
+        names.sort();
+        let mut browser = Browser {
+            kc,
+            graph: kc.dependency_graph(),
+            visible: names.clone(),
+            names,
+            filter: String::new(),
+            components: ListState::default(),
+            tab: 0,
+            focus: Focus::Components,
+            tree: Vec::new(),
+            tree_state: ListState::default(),
+            scroll: 0,
+            status: HELP.to_string(),
+        };
+        browser.select(0);
+        browser
+    }
+
+    pub fn selected(&self) -> Option<&'a str> {
+        self.components
+            .selected()
+            .and_then(|index| self.visible.get(index).copied())
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    // the dependency tree of the selected component, one `(depth, symbol)` per row
+    pub fn tree(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.tree
+            .iter()
+            .map(|(depth, symbol, _)| (*depth, symbol.as_str()))
+    }
+
+    // `false` once the user quits
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.kind == KeyEventKind::Release {
+            return true;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return false;
+        }
+        match self.focus {
+            Focus::Search => self.search_key(key.code),
+            Focus::Components => return self.components_key(key.code),
+            Focus::Detail => self.detail_key(key.code),
+        }
+        true
+    }
+
+    fn components_key(&mut self, code: KeyCode) -> bool {
+        let selected = self.components.selected().unwrap_or(0);
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('/') => {
+                self.focus = Focus::Search;
+                self.status =
+                    "search: `*` and `?` are wildcards, Enter keeps the filter, Esc drops it"
+                        .to_string();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::PageDown => self.select(selected + 20),
+            KeyCode::PageUp => self.select(selected.saturating_sub(20)),
+            KeyCode::Tab | KeyCode::Right => self.tab = (self.tab + 1) % TABS.len(),
+            KeyCode::BackTab | KeyCode::Left => self.tab = (self.tab + TABS.len() - 1) % TABS.len(),
+            KeyCode::Enter if self.selected().is_some() => {
+                self.focus = Focus::Detail;
+                self.tree_state.select((!self.tree.is_empty()).then_some(0));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.apply_filter();
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.apply_filter();
+            }
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.apply_filter();
+                self.focus = Focus::Components;
+                self.status = HELP.to_string();
+            }
+            KeyCode::Enter => {
+                self.focus = Focus::Components;
+                self.status = format!("{} components match '{}'", self.visible.len(), self.filter);
+            }
+            _ => {}
+        }
+    }
+
+    // the dependency tab moves through the tree, the others scroll
+    fn detail_key(&mut self, code: KeyCode) {
+        let on_tree = TABS[self.tab] == "Dependencies";
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.focus = Focus::Components;
+                self.status = HELP.to_string();
+            }
+            KeyCode::Tab | KeyCode::Right => self.tab = (self.tab + 1) % TABS.len(),
+            KeyCode::BackTab | KeyCode::Left => self.tab = (self.tab + TABS.len() - 1) % TABS.len(),
+            KeyCode::Down | KeyCode::Char('j') if on_tree => self.tree_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') if on_tree => self.tree_state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Enter if on_tree => {
+                let row = self
+                    .tree_state
+                    .selected()
+                    .and_then(|row| self.tree.get(row));
+                if let Some((_, symbol, _)) = row {
+                    let symbol = symbol.clone();
+                    self.jump(&symbol);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // a dependency outside the filter drops the filter, an undefined one only says so
+    fn jump(&mut self, symbol: &str) {
+        let Some((name, _)) = self.kc.lookup(symbol) else {
+            self.status = format!("{} is not defined in arch {}", symbol, self.kc.arch());
+            return;
+        };
+        if !self.visible.contains(&name) {
+            self.filter.clear();
+            self.apply_filter();
+        }
+        if let Some(index) = self.visible.iter().position(|visible| *visible == name) {
+            self.select(index);
+        }
+        self.focus = Focus::Components;
+        self.status = format!("jumped to {}", name);
+    }
+
+    // without wildcards the filter matches as a substring, like `list` in the prompt does
+    fn apply_filter(&mut self) {
+        let selected = self.selected();
+        self.visible = if self.filter.contains(['*', '?']) {
+            self.names
+                .iter()
+                .copied()
+                .filter(|name| glob_match(&self.filter, name))
+                .collect()
+        } else {
+            self.kc.find(&self.filter)
+        };
+        let index = selected
+            .and_then(|name| self.visible.iter().position(|visible| *visible == name))
+            .unwrap_or(0);
+        self.select(index);
+    }
+
+    fn select(&mut self, index: usize) {
+        if self.visible.is_empty() {
+            self.components.select(None);
+        } else {
+            self.components
+                .select(Some(index.min(self.visible.len() - 1)));
+        }
+        self.scroll = 0;
+        self.tree = match self.selected() {
+            Some(name) => {
+                let mut tree = Vec::new();
+                let mut path = BTreeSet::from([name.to_string()]);
+                self.dependency_rows(name, 0, &mut path, &mut tree);
+                tree
+            }
+            None => Vec::new(),
+        };
+        self.tree_state = ListState::default();
+    }
+
+    // a symbol already on the path to the root is shown but not expanded again
+    fn dependency_rows(
+        &self,
+        symbol: &str,
+        depth: usize,
+        path: &mut BTreeSet<String>,
+        rows: &mut Vec<(usize, String, &'static str)>,
+    ) {
+        for (to, kind) in self.graph.edges_from(symbol) {
+            rows.push((depth, to.clone(), kind.label()));
+            if depth + 1 < TREE_DEPTH && path.insert(to.clone()) {
+                self.dependency_rows(to, depth + 1, path, rows);
+                path.remove(to);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Min(0)]).areas(main);
+        self.draw_components(frame, left);
+        self.draw_detail(frame, right);
+
+        let status_line = match self.focus {
+            Focus::Search => format!("/{}", self.filter),
+            _ => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn draw_components(&mut self, frame: &mut Frame, area: Rect) {
+        let title = if self.filter.is_empty() {
+            format!(" {} components ", self.visible.len())
+        } else {
+            format!(
+                " {} components matching '{}' ",
+                self.visible.len(),
+                self.filter
+            )
+        };
+        let mut block = Block::bordered().title(title);
+        if self.focus != Focus::Detail {
+            block = block.border_style(Style::new().cyan());
+        }
+        let list = List::new(self.visible.iter().copied())
+            .block(block)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.components);
+    }
+
+    fn draw_detail(&mut self, frame: &mut Frame, area: Rect) {
+        let [tabs, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(
+            Tabs::new(TABS)
+                .select(self.tab)
+                .highlight_style(Style::new().bold().cyan()),
+            tabs,
+        );
+
+        let mut block = Block::bordered().title(format!(" {} ", self.selected().unwrap_or("")));
+        if self.focus == Focus::Detail {
+            block = block.border_style(Style::new().cyan());
+        }
+        let Some(name) = self.selected() else {
+            frame.render_widget(Paragraph::new("no component").block(block), body);
+            return;
+        };
+        match TABS[self.tab] {
+            "Dependencies" => {
+                let rows = self.tree.iter().map(|(depth, symbol, label)| {
+                    format!("{}└ {} ({})", "  ".repeat(*depth), symbol, label)
+                });
+                let tree = List::new(rows)
+                    .block(block)
+                    .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+                frame.render_stateful_widget(tree, body, &mut self.tree_state);
+            }
+            tab => {
+                let text = if tab == "Attributes" {
+                    self.attributes(name)
+                } else {
+                    self.snippets(name)
+                };
+                let paragraph = Paragraph::new(text).block(block).scroll((self.scroll, 0));
+                frame.render_widget(paragraph, body);
+            }
+        }
+    }
+
+    fn attributes(&self, name: &str) -> Text<'static> {
+        let Some((_, stat)) = self.kc.lookup(name) else {
+            return Text::default();
+        };
+        let rows = [
+            ("Defined in", self.kc.display_path(stat.kconfig_file())),
+            ("Type", stat.value_type().to_string()),
+            ("Prompt", stat.prompt().unwrap_or("").to_string()),
+            ("Visible if", stat.visible_if().unwrap_or("").to_string()),
+            ("Depends on", stat.effective_depends()),
+            ("Select", stat.selects().join(", ")),
+            ("Default", stat.defaults().join("; ")),
+            ("Code lines", stat.code_lines().to_string()),
+        ];
+        rows.into_iter()
+            .map(|(attribute, value)| {
+                Line::from(vec![format!("{: <12}", attribute).bold(), value.into()])
+            })
+            .collect()
+    }
+
+    fn snippets(&self, name: &str) -> Text<'static> {
+        let Some((_, stat)) = self.kc.lookup(name) else {
+            return Text::default();
+        };
+        let mut lines = Vec::new();
+        for snippet in stat.code_snippets() {
+            let mut header = format!(
+                "{}:{}:",
+                self.kc.display_path(snippet.file()),
+                snippet.line()
+            );
+            if snippet.module() {
+                header.push_str(" [module]");
+            }
+            lines.push(Line::from(header.bold().cyan()));
+            lines.extend(
+                snippet
+                    .code()
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+            lines.push(Line::default());
+        }
+        if lines.is_empty() {
+            lines.push(Line::from("no guarded code"));
+        }
+        Text::from(lines)
+    }
+}
+
+// the browser owns the whole screen until `q`, the terminal is restored on every way out
+pub fn run(kc: &KconfigCounter) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(Error::NotATerminal("--tui"));
+    }
+    let mut terminal = ratatui::try_init().map_err(Error::Terminal)?;
+    let mut browser = Browser::new(kc);
+    let result = (|| -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| browser.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if !browser.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    ratatui::try_restore().map_err(Error::Terminal)?;
+    result.map_err(Error::Terminal)
+}
@@ -1,3 +1,63 @@
+use std::path::{Display, Path};
+
+// a line shorter than `skipped`, or cut inside a char, has no field
 pub fn get_filed(line: &str, skipped: &str) -> String {
-    line[skipped.len()..].trim().to_string()
+    line.get(skipped.len()..).unwrap_or("").trim().to_string()
+}
+
+pub fn strip_bom(line: &str) -> &str {
+    line.strip_prefix('\u{feff}').unwrap_or(line)
+}
+
+// Levenshtein distance over chars, ASCII case is ignored
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().map(|c| c.to_ascii_lowercase()).enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// `*` matches any run of chars and `?` a single one, ASCII case is ignored
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    glob_match_case(&pattern.to_ascii_lowercase(), &text.to_ascii_lowercase())
+}
+
+// `glob_match` with case kept, for patterns naming paths
+pub fn glob_match_case(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // the last `*` and the text position it has swallowed up to
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// `path` below `root` when one is given and contains it, otherwise as stored
+pub fn display_path<'a>(path: &'a Path, root: Option<&Path>) -> Display<'a> {
+    root.and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .display()
 }
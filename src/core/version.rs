@@ -0,0 +1,243 @@
+use crate::core::dotconfig::DotConfig;
+use crate::core::error::{Error, Result};
+use crate::core::source::SourceTree;
+use crate::core::utils::strip_bom;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+
+/// The release of a kernel tree, read from the header of its top level Makefile.
+///
+/// Displays like `make kernelrelease` without the SCM suffix, e.g. `6.9.5` or
+/// `6.10.0-rc3-custom`; [`KernelVersion::plain`] leaves out the local version.
+///
+/// ```
+/// use auto_script::core::version::KernelVersion;
+///
+/// let version = KernelVersion::from("6.10.0-rc3");
+/// assert_eq!((version.major, version.patch, version.sub), (6, 10, Some(0)));
+/// assert_eq!(version.extra, "-rc3");
+/// assert_eq!(version.tarball_dir(), "linux-6.10-rc3");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct KernelVersion {
+    pub major: u32,
+    pub patch: u32,
+    // an empty `SUBLEVEL` leaves it out of the version string
+    pub sub: Option<u32>,
+    // `EXTRAVERSION`, e.g. "-rc3", a distro suffix or empty
+    pub extra: String,
+    // `NAME`, flavor text only, never part of comparisons
+    pub name: Option<String>,
+    // `localversion*` files and `CONFIG_LOCALVERSION`, labels a build but not the release
+    pub local: String,
+}
+
+impl KernelVersion {
+    // release candidates come before their release, distro suffixes after it
+    fn stage(&self) -> (u8, u32) {
+        match self
+            .extra
+            .strip_prefix("-rc")
+            .and_then(|rc| rc.parse().ok())
+        {
+            Some(rc) => (0, rc),
+            None if self.extra.is_empty() => (1, 0),
+            None => (2, 0),
+        }
+    }
+
+    // `make kernelversion`, the release without the local version
+    pub fn plain(&self) -> String {
+        let mut version = format!("{}.{}", self.major, self.patch);
+        if let Some(sub) = self.sub {
+            version.push_str(&format!(".{}", sub));
+        }
+        version.push_str(&self.extra);
+        version
+    }
+
+    // kernel.org names the tree of `x.y.0` releases and release candidates `linux-x.y`
+    pub fn tarball_dir(&self) -> String {
+        match self.sub {
+            Some(sub) if sub != 0 => {
+                format!("linux-{}.{}.{}{}", self.major, self.patch, sub, self.extra)
+            }
+            _ => format!("linux-{}.{}{}", self.major, self.patch, self.extra),
+        }
+    }
+}
+
+impl fmt::Display for KernelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.plain(), self.local)
+    }
+}
+
+impl PartialEq for KernelVersion {
+    fn eq(&self, other: &Self) -> bool {
+        (self.major, self.patch, self.sub, &self.extra)
+            == (other.major, other.patch, other.sub, &other.extra)
+    }
+}
+
+impl Eq for KernelVersion {}
+
+// "6.10.0-rc3" sorts after "6.9.5" and before "6.10.0"
+impl Ord for KernelVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.patch, self.sub, self.stage(), &self.extra).cmp(&(
+            other.major,
+            other.patch,
+            other.sub,
+            other.stage(),
+            &other.extra,
+        ))
+    }
+}
+
+impl PartialOrd for KernelVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// a version string as displayed, e.g. from an export; missing numbers are 0
+// and whatever follows them is the extra version
+impl From<&str> for KernelVersion {
+    fn from(version: &str) -> Self {
+        let mut rest = version;
+        let mut numbers = Vec::new();
+        while numbers.len() < 3 {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let Ok(number) = rest[..digits].parse() else {
+                break;
+            };
+            numbers.push(number);
+            rest = &rest[digits..];
+            match rest.strip_prefix('.') {
+                Some(next)
+                    if numbers.len() < 3 && next.starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    rest = next
+                }
+                _ => break,
+            }
+        }
+        KernelVersion {
+            major: numbers.first().copied().unwrap_or(0),
+            patch: numbers.get(1).copied().unwrap_or(0),
+            sub: numbers.get(2).copied(),
+            extra: rest.to_string(),
+            name: None,
+            local: String::new(),
+        }
+    }
+}
+
+impl From<String> for KernelVersion {
+    fn from(version: String) -> Self {
+        KernelVersion::from(version.as_str())
+    }
+}
+
+// `VERSION`, `PATCHLEVEL`, `SUBLEVEL`, `EXTRAVERSION` and `NAME` of the top level Makefile
+pub fn fetch_kernel_version(tree: &dyn SourceTree, makefile: &Path) -> Result<KernelVersion> {
+    let reader = tree.read(makefile).map_err(Error::io(makefile))?;
+    read_kernel_version(reader, makefile)
+}
+
+const HEADER: [&str; 5] = ["VERSION", "PATCHLEVEL", "SUBLEVEL", "EXTRAVERSION", "NAME"];
+
+// the value of `key = value` with any make assignment operator, up to a trailing comment
+fn assignment<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line.trim_start().strip_prefix(key)?.trim_start();
+    let value = ["=", ":=", "::=", "?="]
+        .iter()
+        .find_map(|operator| rest.strip_prefix(operator))?;
+    let value = value.split_once('#').map_or(value, |(value, _)| value);
+    Some(value.trim())
+}
+
+pub fn read_kernel_version(reader: impl BufRead, makefile: &Path) -> Result<KernelVersion> {
+    let not_found = || Error::KernelVersionNotFound {
+        makefile: makefile.to_path_buf(),
+    };
+
+    let mut fields: [Option<String>; 5] = Default::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(Error::io(makefile))?;
+        let line = if index == 0 { strip_bom(&line) } else { &line };
+        let Some((slot, value)) = HEADER
+            .iter()
+            .enumerate()
+            .find_map(|(slot, key)| Some((slot, assignment(line, key)?)))
+        else {
+            continue;
+        };
+        // only the header counts, later assignments are not the release
+        fields[slot].get_or_insert_with(|| value.to_string());
+    }
+
+    let [Some(major), Some(patch), Some(sub), extra, name] = fields else {
+        return Err(not_found());
+    };
+    let number = |value: &str| value.parse::<u32>().map_err(|_| not_found());
+    Ok(KernelVersion {
+        major: number(&major)?,
+        patch: number(&patch)?,
+        sub: if sub.is_empty() {
+            None
+        } else {
+            Some(number(&sub)?)
+        },
+        extra: extra.unwrap_or_default(),
+        name: name.filter(|name| !name.is_empty()),
+        local: String::new(),
+    })
+}
+
+// the version a build of the tree would report: the Makefile release, the
+// `localversion*` files at its root and `CONFIG_LOCALVERSION` of `config`
+pub fn fetch_kernel_release(
+    tree: &dyn SourceTree,
+    kernel_path: &Path,
+    config: Option<&Path>,
+) -> Result<KernelVersion> {
+    let mut version = fetch_kernel_version(tree, &kernel_path.join("Makefile"))?;
+    version.local = read_localversion(tree, kernel_path)?;
+    if let Some(config) = config {
+        let config = DotConfig::load(config)?;
+        version.local += config.value("LOCALVERSION").unwrap_or_default();
+    }
+    Ok(version)
+}
+
+// concatenated in name order like `scripts/setlocalversion`, backups ending in `~` are skipped
+pub fn read_localversion(tree: &dyn SourceTree, kernel_path: &Path) -> Result<String> {
+    let mut files: Vec<PathBuf> = tree
+        .read_dir(kernel_path)
+        .map_err(Error::io(kernel_path))?
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path)
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("localversion") && !name.contains('~')
+        })
+        .collect();
+    files.sort();
+
+    let mut local = String::new();
+    for file in files {
+        let mut content = String::new();
+        tree.read(&file)
+            .and_then(|mut reader| reader.read_to_string(&mut content))
+            .map_err(Error::io(&file))?;
+        local.push_str(content.trim());
+    }
+    Ok(local)
+}
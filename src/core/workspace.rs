@@ -0,0 +1,146 @@
+use crate::core::error::{Error, Result};
+use crate::core::source::FsTree;
+use crate::core::version::{fetch_kernel_version, KernelVersion};
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// `$XDG_CONFIG_HOME/auto_script/config`, `~/.config/auto_script/config` without it
+pub fn default_config_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("auto_script").join("config"))
+}
+
+/// The settings of the config file, one `key = value` per line.
+///
+/// Blank lines and lines starting with `#` are skipped, unknown keys only warn so an older
+/// build keeps reading the file of a newer one.
+#[derive(Debug, Default)]
+pub struct Config {
+    // used when `--workspace` is not given, a leading `~/` is the home directory
+    pub workspace: Option<PathBuf>,
+}
+
+impl Config {
+    // a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(path, &text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(Error::io(path)(err)),
+        }
+    }
+
+    // `path` only names the file in errors and warnings
+    pub fn parse(path: &Path, text: &str) -> Result<Self> {
+        let mut config = Config::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason| Error::InvalidConfig {
+                path: path.to_path_buf(),
+                line: index + 1,
+                reason,
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`"))?;
+            let value = value.trim();
+            match key.trim() {
+                "workspace" if value.is_empty() => return Err(invalid("workspace is empty")),
+                "workspace" => config.workspace = Some(expand_home(value)),
+                key => warn!("{}:{}: unknown key {:?}", path.display(), index + 1, key),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// every direct child of `dir` with a top level Makefile is treated as a kernel tree
+pub fn discover_kernels(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut kernels = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.join("Makefile").is_file() {
+            kernels.push(path);
+        } else if path.is_dir() {
+            warn!("skip {:?}: no top level Makefile", path);
+        }
+    }
+    kernels.sort();
+    Ok(kernels)
+}
+
+// `6.9` matches 6.9, 6.9.5 and 6.9.0-rc3 but not 6.90
+fn version_matches(version: &KernelVersion, query: &str) -> bool {
+    let plain = version.plain();
+    plain.strip_prefix(query).is_some_and(|rest| {
+        rest.is_empty() || rest.starts_with(['.', '-']) || query.ends_with(['.', '-'])
+    })
+}
+
+/// The tree below `workspace` whose release matches `query`, the newest one when several do.
+///
+/// Trees whose Makefile carries no version are left out; without a single newest match
+/// the error lists every tree found.
+pub fn select_kernel(workspace: &Path, query: &str) -> Result<(KernelVersion, PathBuf)> {
+    let mut trees = Vec::new();
+    for kernel_path in discover_kernels(workspace).map_err(Error::io(workspace))? {
+        match fetch_kernel_version(&FsTree, &kernel_path.join("Makefile")) {
+            Ok(version) => trees.push((version, kernel_path)),
+            Err(err) => warn!("skip {:?}: {}", kernel_path, err),
+        }
+    }
+    sort_by_version(&mut trees);
+    let candidates = |trees: &[&(KernelVersion, PathBuf)]| {
+        let found: Vec<String> = trees
+            .iter()
+            .map(|(version, path)| format!("{} at {}", version, path.display()))
+            .collect();
+        match found.is_empty() {
+            true => "no kernel trees".to_string(),
+            false => found.join(", "),
+        }
+    };
+
+    let matching: Vec<&(KernelVersion, PathBuf)> = trees
+        .iter()
+        .filter(|(version, _)| version_matches(version, query))
+        .collect();
+    let Some((newest, _)) = matching.last() else {
+        return Err(Error::KernelNotFound {
+            workspace: workspace.to_path_buf(),
+            query: query.to_string(),
+            candidates: candidates(&trees.iter().collect::<Vec<_>>()),
+        });
+    };
+    let newest: Vec<&(KernelVersion, PathBuf)> = matching
+        .iter()
+        .copied()
+        .filter(|(version, _)| version == newest)
+        .collect();
+    match newest.as_slice() {
+        [(version, path)] => Ok((version.clone(), path.clone())),
+        _ => Err(Error::AmbiguousKernel {
+            workspace: workspace.to_path_buf(),
+            query: query.to_string(),
+            candidates: candidates(&newest),
+        }),
+    }
+}
+
+pub fn sort_by_version(trees: &mut [(KernelVersion, PathBuf)]) {
+    trees.sort_by(|(a, _), (b, _)| a.cmp(b));
+}
@@ -0,0 +1,48 @@
+//! Code and Kconfig statistics for Linux kernel source trees.
+//!
+//! The `auto_script` binary is a command line front end to this library,
+//! everything it prints is built from the counters and report types below.
+//! With `default-features = false` the library leaves out the `cli` feature
+//! (clap, the stderr logger in `core::log` and the rustyline prompt in
+//! `core::interactive`), `file-logging`
+//! (flexi_logger for `--log-file`) and `git` (libgit2 for `--git-rev`, see
+//! `core::source::GitTree`).
+//!
+//! Fallible functions return [`AutoScriptError`], whose variants name the file
+//! or option they are about; only the binary wraps them in `anyhow`.
+//!
+//! ```
+//! use auto_script::{FileCounter, KconfigCounter};
+//! use std::fs;
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let arch = dir.path().join("linux-6.9.5/arch/riscv");
+//! fs::create_dir_all(&arch).unwrap();
+//! fs::write(arch.join("Kconfig"), "config SMP\n\tbool \"smp\"\n").unwrap();
+//! fs::write(arch.join("smp.c"), "#ifdef CONFIG_SMP\nint smp;\n#endif\n").unwrap();
+//!
+//! let mut fc = FileCounter::new("riscv".into(), "6.9.5".into(), arch.clone());
+//! fc.search().unwrap();
+//! assert_eq!(fc.stats().values().map(|stat| stat.files()).sum::<usize>(), 2);
+//!
+//! let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), arch.join("Kconfig"));
+//! kc.parse_kconfig().unwrap();
+//! kc.analyze_code_dirs(&[arch]).unwrap();
+//! let (_, smp) = kc.components().next().unwrap();
+//! assert_eq!(smp.code_snippets().len(), 1);
+//! ```
+
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod core;
+
+pub use crate::core::dotconfig::{ConfigDiff, DotConfig};
+pub use crate::core::error::Error as AutoScriptError;
+pub use crate::core::file_counter::{FileCounter, FileStat, FileType, ScanFailure, ScanReport};
+pub use crate::core::graph::{DependencyGraph, EdgeKind};
+pub use crate::core::kconfig_counter::{
+    ArchComparison, ChoiceViolation, CodeDelta, CodeSnippet, ComponentDiff, DefconfigReport,
+    DirReport, Footprint, KconfigComponentType, KconfigCounter, KconfigCounterBuilder,
+    KconfigExport, KconfigExports, KconfigStat, SubsystemReport,
+};
+pub use crate::core::schema::SCHEMA_VERSION;
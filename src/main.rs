@@ -1,165 +1,798 @@
-mod core;
+use anyhow::{bail, Context, Result};
+use auto_script::cli::{
+    Cli, Command, CountArgs, DiffArgs, GlobalArgs, KconfigArgs, TraceArgs, TrendArgs, TrendMetrics,
+    ValidateArgs,
+};
+use auto_script::core::arch;
+use auto_script::core::baseline::Baseline;
+use auto_script::core::dotconfig::{diff_configs, DotConfig};
+#[cfg(feature = "fetch")]
+use auto_script::core::fetch::{default_cache_dir, Fetcher};
+use auto_script::core::file_counter::{CountReport, FileCounter};
+use auto_script::core::interactive;
+use auto_script::core::kconfig_counter::{DiffFormat, KconfigCounter, KconfigExports};
+use auto_script::core::log::{set_logger, LogFormat};
+use auto_script::core::markdown;
+use auto_script::core::output::{self, Output};
+use auto_script::core::pipeline::{scan_report_summary, Options, Pipeline};
+use auto_script::core::report::{AnalysisResult, Renderer, TextRenderer};
+use auto_script::core::schema::{self, Versioned};
+use auto_script::core::source::FsTree;
+#[cfg(feature = "git")]
+use auto_script::core::source::GitTree;
+use auto_script::core::trend::{write_trend, TrendPoint};
+use auto_script::core::tui;
+use auto_script::core::version::{fetch_kernel_release, fetch_kernel_version, KernelVersion};
+use auto_script::core::workspace::{
+    default_config_path, discover_kernels, select_kernel, sort_by_version, Config,
+};
+use auto_script::AutoScriptError;
+use clap::Parser;
+use log::info;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "git")]
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use crate::core::file_counter::FileCounter;
-use crate::core::kconfig_counter::KconfigCounter;
-use crate::core::log::set_logger;
-use anyhow::Result;
-use clap::{Arg, Parser};
-use log::{error, info, warn};
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
-use std::path::PathBuf;
+// a `--query` pattern matching more components asks before printing them all
+const QUERY_EXPANSION_CAP: usize = 50;
 
-fn parse_bool(s: &str) -> Result<bool, String> {
-    match s.to_lowercase().as_str() {
-        "true" | "t" | "yes" | "y" | "1" => Ok(true),
-        "false" | "f" | "no" | "n" | "0" => Ok(false),
-        _ => Err(format!("invalid value for a boolean: {}", s)),
+// without a terminal to ask on, the answer is no
+fn confirm_expansion(pattern: &str, matches: usize) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
     }
+    eprint!(
+        "{:?} matches {} components, print them all? [y/N] ",
+        pattern, matches
+    );
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
 }
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about)]
-struct Args {
-    /// 指定需要解析的模块架构
-    #[arg(long, short = 'a', value_delimiter = ',', default_value = "riscv")]
-    arch: Vec<PathBuf>,
+// `--width`, otherwise the terminal's width when stdout is one
+fn text_renderer(global: &GlobalArgs) -> TextRenderer {
+    let renderer = match global.width {
+        Some(width) => TextRenderer::with_width(width.into()),
+        None => TextRenderer::for_terminal(),
+    };
+    renderer.with_color(global.color.enabled(io::stdout().is_terminal()))
+}
+
+// per-file log lines would only get in the way of machine readable output
+fn summarize_logs(cli: &Cli) -> bool {
+    cli.global.quiet
+        || matches!(cli.command, Command::Trend(_))
+        || !matches!(cli.global.format, DiffFormat::Text)
+        || matches!(cli.global.log_format, LogFormat::Json)
+}
+
+// what every subcommand shares, each one applies its own arguments on top
+fn pipeline_options(cli: &Cli) -> Result<Options> {
+    let global = &cli.global;
+    let options = Options {
+        kernel_path: global.kernel_path.clone(),
+        arches: global.arch.clone(),
+        arch_template: global.arch_template.clone(),
+        verbose: global.verbose > 0,
+        sorted: global.sorted,
+        relative: global.relative,
+        summarize: summarize_logs(cli),
+        strict: global.strict,
+        fail_on_empty: global.fail_on_empty,
+        defconfig: global.dotconfig.clone(),
+        ..Options::default()
+    };
+    let options = match &global.kernel {
+        Some(query) => {
+            if matches!(cli.command, Command::Trend(_) | Command::Trace(_)) {
+                bail!("--kernel picks one kernel tree, trend and trace read several");
+            }
+            let workspace = match &global.workspace {
+                Some(workspace) => workspace.clone(),
+                None => config_workspace()?,
+            };
+            let (version, kernel_path) = select_kernel(&workspace, query)?;
+            info!("select Linux-{} at {:?}", version, kernel_path);
+            Options {
+                kernel_path,
+                ..options
+            }
+        }
+        None => options,
+    };
+    #[cfg(feature = "fetch")]
+    let options = match &global.fetch {
+        Some(version) => {
+            if matches!(cli.command, Command::Trend(_) | Command::Trace(_)) {
+                bail!("--fetch reads one kernel tree, trend and trace read several");
+            }
+            let cache_dir = default_cache_dir()
+                .context("neither XDG_CACHE_HOME nor HOME is set, nowhere to cache --fetch")?;
+            let kernel_path = Fetcher::new(cache_dir)
+                .offline(global.offline)
+                .fetch(&KernelVersion::from(version.as_str()))?;
+            Options {
+                kernel_path,
+                ..options
+            }
+        }
+        None => options,
+    };
+    #[cfg(feature = "git")]
+    if let Some(rev) = &global.git_rev {
+        if matches!(cli.command, Command::Trend(_) | Command::Trace(_)) {
+            bail!("--git-rev reads one kernel tree, trend and trace read several");
+        }
+        let tree = GitTree::open(&options.kernel_path, rev)?;
+        return Ok(Options {
+            source_tree: Rc::new(tree),
+            ..options
+        });
+    }
+    Ok(options)
+}
+
+// `--kernel` without `--workspace` falls back to the `workspace` of the config file
+fn config_workspace() -> Result<PathBuf> {
+    let path = default_config_path()
+        .context("--kernel needs --workspace, neither XDG_CONFIG_HOME nor HOME is set")?;
+    Config::load(&path)?.workspace.with_context(|| {
+        format!(
+            "--kernel needs --workspace or `workspace = DIR` in {}",
+            path.display()
+        )
+    })
+}
 
-    /// 是否需要解析代码
-    #[arg(long, short = 'c')]
-    code: bool,
+// every problem with `--kernel-path` at once, before any counter starts; `kconfig`
+// also wants the Kconfig of each arch
+fn validate_kernel_tree(options: &Options, kconfig: bool) -> Result<()> {
+    let path = &options.kernel_path;
+    let tree = options.source_tree.as_ref();
+    let mut problems = Vec::new();
 
-    /// 是否需要解析Kconfig
-    #[arg(long, short = 'k')]
-    kconfig: bool,
+    let makefile = path.join("Makefile");
+    if !tree.exists(&makefile) {
+        problems.push(format!("no top level Makefile at {}", makefile.display()));
+    } else if let Err(err) = fetch_kernel_version(tree, &makefile) {
+        problems.push(err.to_string());
+    }
+
+    let arch_root = options.arch_root(path);
+    match arch_root.clone().filter(|root| !tree.is_dir(root)) {
+        Some(root) => problems.push(format!("no arch directory at {}", root.display())),
+        None => {
+            let known_arches: Vec<String> = arch_root
+                .and_then(|root| tree.read_dir(&root).ok())
+                .into_iter()
+                .flatten()
+                .filter(|entry| entry.is_dir)
+                .filter_map(|entry| Some(entry.path.file_name()?.to_string_lossy().into_owned()))
+                .collect();
+            for arch in &options.arches {
+                let dir = options.arch_subdir(path, arch);
+                let found = match kconfig {
+                    true => tree.exists(&dir.join("Kconfig")),
+                    false => tree.is_dir(&dir),
+                };
+                if found {
+                    continue;
+                }
+                let looked_for = if kconfig { dir.join("Kconfig") } else { dir };
+                let arch = arch.to_string_lossy().into_owned();
+                let suggestions = arch::suggestions(&arch, &known_arches);
+                let mut problem = AutoScriptError::ArchNotFound {
+                    arch: arch.clone(),
+                    path: looked_for,
+                }
+                .to_string();
+                if !suggestions.is_empty() {
+                    problem.push_str(&format!(", did you mean {}?", suggestions.join(", ")));
+                }
+                problems.push(problem);
+            }
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = format!("{} is not a usable kernel tree:", path.display());
+    for problem in &problems {
+        message.push_str(&format!("\n  - {}", problem));
+    }
+    // pointing at the directory the tree was unpacked into
+    let trees: Vec<_> = fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|tree| fetch_kernel_version(&FsTree, &tree.join("Makefile")).is_ok())
+        .collect();
+    if let [tree] = trees.as_slice() {
+        message.push_str(&format!(
+            "\nit holds one kernel tree, try --kernel-path {}",
+            tree.display()
+        ));
+    }
+    bail!(message)
+}
+
+fn open_output(global: &GlobalArgs) -> Result<Output> {
+    match &global.output {
+        Some(path) => {
+            Output::create(path).with_context(|| format!("failed to create report {:?}", path))
+        }
+        None => Ok(Output::stdout()),
+    }
+}
 
-    /// 是否需要解析对应代码，该选项必须依赖于`kconfig`的设定
-    #[arg(long, short = 'r')]
-    kconfig_code: bool,
+// the first Ctrl-C stops the scan and keeps what it counted, the second one quits at once
+fn install_interrupt_handler(stop: Arc<AtomicBool>) -> Result<()> {
+    ctrlc::set_handler(move || {
+        if stop.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+        eprintln!("interrupted, printing what was counted so far, press Ctrl-C again to quit");
+    })
+    .context("failed to install the Ctrl-C handler")
+}
 
-    /// 指定需要解析的内核位置
-    #[arg(long, short = 'p', default_value = "/opt/linux-6.9.5")]
-    kernel_path: PathBuf,
+fn print_warnings(pipeline: &Pipeline) {
+    for warning in pipeline.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+}
 
-    /// 是否需要解析全部Kconfig
-    #[arg(long, short = 'f')]
-    full: bool,
+// the per-counter extras printed below each code table
+fn show_code_extras(args: &CountArgs, fc: &FileCounter, renderer: &TextRenderer) -> Result<()> {
+    let mut out = io::stdout();
+    if args.spdx {
+        renderer.render(&AnalysisResult::Spdx(fc.spdx_report()), &mut out)?;
+    }
+    if args.header_guards {
+        renderer.render(
+            &AnalysisResult::HeaderGuards(fc.header_guard_report()),
+            &mut out,
+        )?;
+    }
+    if args.file_health {
+        renderer.render(
+            &AnalysisResult::FileHealth(fc.file_health_report()),
+            &mut out,
+        )?;
+    }
+    if let Some(top) = args.todos {
+        renderer.render(&AnalysisResult::Todos(fc.todo_report(top)), &mut out)?;
+    }
+    Ok(())
 }
 
-fn fetch_kernel_version(kernel_path: &PathBuf) -> Result<String> {
-    let file = File::open(kernel_path)?;
-    let reader = io::BufReader::new(file);
+fn count(global: &GlobalArgs, args: &CountArgs, mut options: Options) -> Result<()> {
+    if let (DiffFormat::Json, Some(_)) = (global.format, &args.compare) {
+        bail!("--compare prints a text table only, it has no JSON form");
+    }
+    args.code.apply_to(&mut options);
+    let mut pipeline = Pipeline::new(options)?;
+    pipeline.run_code_analysis()?;
+    print_warnings(&pipeline);
 
-    let mut version = None;
-    let mut patch_level = None;
-    let mut sublevel = None;
+    let results = pipeline.results();
+    if let DiffFormat::Json = global.format {
+        let report = CountReport {
+            version: results.version.to_string(),
+            arches: results
+                .code
+                .iter()
+                .map(|fc| fc.export(args.todos))
+                .collect(),
+            all_arches: results.all_arches.map(|fc| fc.export(args.todos)),
+            common: results.common.map(|fc| fc.export(args.todos)),
+            docs: results.docs.map(|fc| fc.export(args.todos)),
+        };
+        let mut out = open_output(global)?;
+        writeln!(out, "{}", schema::to_json(&report)?)?;
+        out.finish()
+            .with_context(|| format!("failed to write report to {:?}", global.output))?;
+        if pipeline.interrupted() {
+            bail!("interrupted, the counts written are partial");
+        }
+        return Ok(());
+    }
 
-    for line in reader.lines() {
-        let line = line?;
-        if line.trim_start().starts_with('#') {
-            continue;
+    let renderer = text_renderer(global);
+    let options = pipeline.options();
+    for fc in results.code {
+        fc.print(&renderer)?;
+        show_code_extras(args, fc, &renderer)?;
+        if let Some(compare_path) = args.compare.as_ref().filter(|_| !pipeline.interrupted()) {
+            let arch = Path::new(fc.arch());
+            let compare_version = fetch_kernel_release(&FsTree, compare_path, None)?;
+            let mut compare_fc = options.on_disk().file_counter(
+                fc.arch(),
+                &compare_version,
+                options.arch_subdir(compare_path, arch),
+            );
+            let report = compare_fc.search().with_context(|| {
+                format!(
+                    "failed to search arch {:?} directory of {:?}",
+                    arch, compare_path
+                )
+            })?;
+            let root = global.relative.then_some(compare_path.as_path());
+            if let Some(summary) = scan_report_summary(fc.arch(), &report, root) {
+                if global.strict {
+                    bail!(summary);
+                }
+                eprintln!("warning: {}", summary);
+            }
+            renderer.render(
+                &AnalysisResult::LanguageDelta(fc.delta_report(&compare_fc)),
+                &mut io::stdout(),
+            )?;
         }
-        if line.trim().starts_with("VERSION = ") {
-            version = Some(line["VERSION = ".len()..].trim().to_string());
-            // info!("fetch kernel version: {:?}", version);
+    }
+
+    if let Some(all_arches) = results.all_arches {
+        all_arches.print(&renderer)?;
+        show_code_extras(args, all_arches, &renderer)?;
+    }
+
+    if let Some(common) = results.common {
+        common.print(&renderer)?;
+        show_code_extras(args, common, &renderer)?;
+        renderer.render(
+            &AnalysisResult::WholeTree(common.whole_tree_report(results.code)),
+            &mut io::stdout(),
+        )?;
+    }
+
+    if let Some(docs) = results.docs {
+        docs.print(&renderer)?;
+        show_code_extras(args, docs, &renderer)?;
+    }
+
+    if pipeline.interrupted() {
+        bail!("interrupted, the counts above are partial");
+    }
+    Ok(())
+}
+
+fn show_kconfig(
+    global: &GlobalArgs,
+    args: &KconfigArgs,
+    top_snippets: Option<usize>,
+    kc: &KconfigCounter,
+    out: &mut Output,
+) -> Result<()> {
+    if let Some(symbol) = &args.reverse_deps {
+        kc.dependency_graph().print_reverse_deps(symbol);
+    } else if args.cycles {
+        kc.dependency_graph().print_cycles();
+    } else if let Some(top) = top_snippets {
+        text_renderer(global).render(
+            &AnalysisResult::Snippets(kc.snippet_report(top)),
+            &mut io::stdout(),
+        )?;
+    } else if args.by_dir {
+        let report = kc.dir_report();
+        match global.format {
+            DiffFormat::Text => {
+                text_renderer(global).render(&AnalysisResult::Dirs(report), &mut io::stdout())?
+            }
+            DiffFormat::Json => writeln!(out, "{}", schema::to_json(&report)?)?,
+            DiffFormat::Markdown => write!(out, "{}", markdown::dir_report(&report))?,
         }
-        if line.trim().starts_with("PATCHLEVEL = ") {
-            patch_level = Some(line["PATCHLEVEL = ".len()..].trim().to_string());
-            // info!("fetch kernel patchlevel: {:?}", patch_level);
+    } else if args.by_subsystem {
+        let report = kc.subsystem_report();
+        match global.format {
+            DiffFormat::Text => text_renderer(global)
+                .render(&AnalysisResult::Subsystems(report), &mut io::stdout())?,
+            DiffFormat::Json => writeln!(out, "{}", schema::to_json(&report)?)?,
+            DiffFormat::Markdown => write!(out, "{}", markdown::subsystem_report(&report))?,
         }
-        if line.trim().starts_with("SUBLEVEL = ") {
-            sublevel = Some(line["SUBLEVEL = ".len()..].trim().to_string());
-            // info!("fetch kernel sublevel: {:?}", sublevel);
+    } else if args.dangling_depends {
+        text_renderer(global).render(
+            &AnalysisResult::Dangling(kc.dangling_report()),
+            &mut io::stdout(),
+        )?;
+    } else if args.dot {
+        write!(out, "{}", kc.dependency_graph().to_dot(&kc.label()))?;
+    } else if !args.query.is_empty() {
+        for name in &args.query {
+            if name.contains(['*', '?']) {
+                let names = kc.expand_glob(name);
+                if names.is_empty() {
+                    bail!("no component matches {:?} in arch {}", name, kc.arch());
+                }
+                if names.len() > QUERY_EXPANSION_CAP && !confirm_expansion(name, names.len())? {
+                    bail!(
+                        "{:?} matches {} components in arch {}, narrow the pattern or use --list-components",
+                        name,
+                        names.len(),
+                        kc.arch()
+                    );
+                }
+                for name in names {
+                    kc.print_component(name);
+                }
+                continue;
+            }
+            if !kc.print_component(name) {
+                let suggestions = kc.suggestions(name);
+                if suggestions.is_empty() {
+                    bail!("component {:?} not found in arch {}", name, kc.arch());
+                }
+                bail!(
+                    "component {:?} not found in arch {}, did you mean {}?",
+                    name,
+                    kc.arch(),
+                    suggestions.join(", ")
+                );
+            }
         }
+    } else if args.list_components {
+        kc.list_components(args.list_types);
+    } else if args.summary_only {
+        kc.print(&text_renderer(global))?;
+    } else if args.tui {
+        tui::run(kc)?;
+    } else {
+        kc.print(&text_renderer(global))?;
+        let history = args.history.then(interactive::history_path).flatten();
+        interactive::run(kc, history, global.color)?;
     }
+    Ok(())
+}
 
-    if let (Some(v), Some(p), Some(s)) = (version, patch_level, sublevel) {
-        Ok(format!("{}.{}.{}", v, p, s))
+// `kconfig`, and `analyze` which also counts the guarded code
+fn kconfig(
+    global: &GlobalArgs,
+    args: &KconfigArgs,
+    top_snippets: Option<usize>,
+    with_code: bool,
+    mut options: Options,
+) -> Result<()> {
+    args.parse.apply_to(&mut options);
+    options.group_by = args.group_by;
+    options.maintainers = args.maintainers;
+    let pipeline;
+    let piped;
+    let kconfig_counters = if args.stdin {
+        // the version only labels the report, a tree is not needed
+        let version = fetch_kernel_release(
+            options.source_tree.as_ref(),
+            &options.kernel_path,
+            options.defconfig.as_deref(),
+        )
+        .unwrap_or_default();
+        let arch = options.arches.first().context("no arch given")?;
+        piped = [options
+            .kconfig_counter_from_reader(arch, &version, io::stdin().lock())
+            .context("failed to parse Kconfig from stdin")?];
+        &piped[..]
     } else {
-        Err(anyhow::anyhow!("Failed to read version information"))
+        let mut analysis = Pipeline::new(options)?;
+        // `--summary-only` never walks the code, not even under `analyze`
+        analysis.run_kconfig_analysis(with_code && !args.summary_only)?;
+        // neither queries nor the interactive mode make sense on half a tree
+        if analysis.interrupted() {
+            for kc in analysis.results().kconfig {
+                kc.print(&text_renderer(global))?;
+            }
+            bail!("interrupted, the summary above is partial");
+        }
+        pipeline = analysis;
+        pipeline.results().kconfig
+    };
+
+    let mut out = open_output(global)?;
+    for kc in kconfig_counters {
+        show_kconfig(global, args, top_snippets, kc, &mut out)?;
     }
+
+    if kconfig_counters.len() > 1 {
+        let comparison = KconfigCounter::compare_arches(kconfig_counters);
+        match global.format {
+            DiffFormat::Text => text_renderer(global).render(
+                &AnalysisResult::Comparison {
+                    comparison,
+                    show_exclusive: args.show_exclusive,
+                },
+                &mut io::stdout(),
+            )?,
+            DiffFormat::Json => writeln!(out, "{}", schema::to_json(&comparison)?)?,
+            DiffFormat::Markdown => write!(out, "{}", markdown::arch_comparison(&comparison))?,
+        }
+    }
+
+    out.finish()
+        .with_context(|| format!("failed to write report to {:?}", global.output))?;
+
+    if let Some(path) = &args.export_kconfig {
+        let mut export = Output::create(path)
+            .with_context(|| format!("failed to export Kconfig components to {:?}", path))?;
+        let exports = KconfigExports {
+            arches: kconfig_counters.iter().map(|kc| kc.export()).collect(),
+        };
+        serde_json::to_writer_pretty(&mut export, &Versioned::new(&exports))?;
+        export
+            .finish()
+            .with_context(|| format!("failed to export Kconfig components to {:?}", path))?;
+        info!("export Kconfig components -> {:?}", path);
+    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    set_logger();
-    // env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace")).init();
-    let args = Args::parse();
-
-    info!("fetch linux kernel directory: {:?}", args.kernel_path);
-
-    let mut version_file = args.kernel_path.clone();
-    version_file.push("Makefile");
-
-    let version = fetch_kernel_version(&version_file)?;
-    info!("fetch linux kernel version: {:?}", version);
-
-    if args.code {
-        for arg in &args.arch {
-            info!("fetch arch: {:?}", arg);
-            let mut arch_dir = args.kernel_path.clone();
-            arch_dir.push("arch");
-            arch_dir.push(arg);
-            warn!("fetch {:?} arch directory path -> {:?}", arg, arch_dir);
-
-            let mut fc = FileCounter::new(
-                arg.clone().to_string_lossy().into_owned(),
-                version.clone(),
-                arch_dir,
-            );
-            fc.search();
-            fc.print();
+// the other side of `diff` is either a kernel tree or an `--export-kconfig` file
+fn load_diff_side(
+    options: &Options,
+    arch: &str,
+    path: &Path,
+    with_code: bool,
+) -> Result<KconfigCounter> {
+    if path.is_file() {
+        let content = output::read_to_string(path)?;
+        let exports: Versioned<KconfigExports> = serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse Kconfig export {:?}", path))?;
+        let exports = exports
+            .into_data()
+            .with_context(|| format!("unsupported Kconfig export {:?}", path))?;
+        let Some(export) = exports
+            .arches
+            .into_iter()
+            .find(|export| export.arch() == arch)
+        else {
+            bail!("Kconfig export {:?} has no arch {:?}", path, arch);
+        };
+        return Ok(KconfigCounter::from_export(export));
+    }
+
+    let options = options.on_disk();
+    let version = fetch_kernel_release(&FsTree, path, None)?;
+    let mut kc = options.kconfig_counter(Path::new(arch), &version, path)?;
+    kc.parse_kconfig()
+        .with_context(|| format!("failed to parse Kconfig of {:?}", path))?;
+    if with_code {
+        options.analyze_kconfig_code(&mut kc, path)?;
+    }
+    Ok(kc)
+}
+
+fn diff(global: &GlobalArgs, args: &DiffArgs, mut options: Options) -> Result<()> {
+    args.parse.apply_to(&mut options);
+    args.code.apply_to(&mut options);
+    if args.defconfig.is_some() {
+        options.defconfig = args.defconfig.clone();
+    }
+    let with_code = args.guarded_code || args.code_delta;
+    let mut pipeline = Pipeline::new(options)?;
+    pipeline.run_kconfig_analysis(with_code)?;
+
+    let mut out = open_output(global)?;
+    for kc in pipeline.results().kconfig {
+        if let Some(path) = &args.other {
+            let other = load_diff_side(pipeline.options(), kc.arch(), path, with_code)?;
+            if args.code_delta {
+                let delta = kc.code_delta(&other);
+                match global.format {
+                    DiffFormat::Text => text_renderer(global)
+                        .render(&AnalysisResult::CodeDelta(delta), &mut io::stdout())?,
+                    DiffFormat::Json => writeln!(out, "{}", schema::to_json(&delta)?)?,
+                    DiffFormat::Markdown => write!(out, "{}", markdown::code_delta(&delta))?,
+                }
+                continue;
+            }
+            let diff = kc.diff_components(&other);
+            match global.format {
+                DiffFormat::Text => text_renderer(global)
+                    .render(&AnalysisResult::ComponentDiff(diff), &mut io::stdout())?,
+                DiffFormat::Json => writeln!(out, "{}", schema::to_json(&diff)?)?,
+                DiffFormat::Markdown => write!(out, "{}", markdown::component_diff(&diff))?,
+            }
+        } else if let (Some(a), Some(b)) = (kc.defconfig(), &args.compare_defconfig) {
+            let (a_config, b_config) = (DotConfig::load(a)?, DotConfig::load(b)?);
+            for (path, config) in [(a, &a_config), (b.as_path(), &b_config)] {
+                for violation in kc.validate_dotconfig_choices(config) {
+                    eprintln!(
+                        "warning: {:?} enables {} of the same choice {:?}",
+                        path,
+                        violation.enabled.join(", "),
+                        violation.prompt.as_deref().unwrap_or("<no prompt>")
+                    );
+                }
+            }
+            let diff = diff_configs(&a_config, &b_config);
+            let report = kc.defconfig_report(&diff, &a.to_string_lossy(), &b.to_string_lossy());
+            match global.format {
+                DiffFormat::Text => text_renderer(global)
+                    .render(&AnalysisResult::Defconfig(report), &mut io::stdout())?,
+                DiffFormat::Json => writeln!(out, "{}", schema::to_json(&report)?)?,
+                DiffFormat::Markdown => write!(out, "{}", markdown::defconfig_report(&report))?,
+            }
         }
     }
+    out.finish()
+        .with_context(|| format!("failed to write report to {:?}", global.output))
+}
+
+fn validate(global: &GlobalArgs, args: &ValidateArgs, mut options: Options) -> Result<()> {
+    args.code.apply_to(&mut options);
+    args.parse.apply_to(&mut options);
+    args.guarded.apply_to(&mut options);
+    let mut pipeline = Pipeline::new(options)?;
+    pipeline.run_code_analysis()?;
+    print_warnings(&pipeline);
+    pipeline.run_kconfig_analysis(true)?;
+    if pipeline.interrupted() {
+        bail!("interrupted, a partial count is neither written nor checked as a baseline");
+    }
 
-    if args.kconfig && !args.kconfig_code {
-        for arg in &args.arch {
-            info!("fetch arch: {:?}", arg);
-            let mut arch_path = args.kernel_path.clone();
-            arch_path.push("arch");
-            arch_path.push(arg);
-            arch_path.push("Kconfig");
-            warn!("fetch {:?} arch Kconfig path -> {:?}", arg, arch_path);
+    let results = pipeline.results();
+    let mut baseline = Baseline::new(results.version.to_string());
+    for fc in results.code {
+        baseline.record(&format!("code.{}", fc.arch()), fc.metrics());
+    }
+    if let Some(common) = results.common {
+        baseline.record("code.common", common.metrics());
+    }
+    if let Some(docs) = results.docs {
+        baseline.record("code.docs", docs.metrics());
+    }
+    for kc in results.kconfig {
+        baseline.record(&format!("kconfig.{}", kc.arch()), kc.metrics());
+    }
 
-            let mut kc = KconfigCounter::new(
-                arg.clone().to_string_lossy().into_owned(),
-                version.clone(),
-                arch_path,
+    if let Some(path) = &args.write_baseline {
+        baseline.save(path)?;
+        info!("write baseline -> {:?}", path);
+    }
+
+    if let Some(path) = &args.check_baseline {
+        let stored =
+            Baseline::load(path).with_context(|| format!("failed to load baseline {:?}", path))?;
+        let report = stored.check(&baseline, args.tolerance);
+        let exceeded = report.exceeded();
+        text_renderer(global).render(&AnalysisResult::Baseline(report), &mut io::stdout())?;
+        if exceeded > 0 {
+            bail!(
+                "{} metrics drifted beyond the {}% tolerance of baseline {:?}",
+                exceeded,
+                args.tolerance,
+                path
             );
-            if args.full {
-                kc.set_check_all();
+        }
+    }
+    Ok(())
+}
+
+fn trend_tree(
+    options: &Options,
+    metrics: &[TrendMetrics],
+    version: &KernelVersion,
+    kernel_path: &Path,
+) -> Result<Vec<TrendPoint>> {
+    let analyze = metrics.contains(&TrendMetrics::Analyze);
+    let release = version.to_string();
+    let mut points = Vec::new();
+    for arch in &options.arches {
+        let arch_name = arch.to_string_lossy();
+        if metrics.contains(&TrendMetrics::Code) {
+            let mut fc =
+                options.file_counter(&arch_name, version, options.arch_subdir(kernel_path, arch));
+            fc.search()
+                .with_context(|| format!("failed to search arch {:?} directory", arch))?;
+            points.extend(TrendPoint::from_metrics(
+                &release,
+                &arch_name,
+                "code",
+                fc.metrics(),
+            ));
+        }
+        if analyze || metrics.contains(&TrendMetrics::Kconfig) {
+            let mut kc = options.kconfig_counter(arch, version, kernel_path)?;
+            kc.parse_kconfig()?;
+            if analyze {
+                kc.analyze_code()?;
             }
-            kc.parse_kconfig();
-            kc.print();
+            points.extend(TrendPoint::from_metrics(
+                &release,
+                &arch_name,
+                "kconfig",
+                kc.metrics(),
+            ));
         }
     }
+    Ok(points)
+}
 
-    if args.kconfig_code {
-        if !args.kconfig {
-            error!("Error: --kconfig_code (-r) requires --kconfig (-k) to be set");
-            std::process::exit(1);
+// a tree that fails is reported and left out, the sweep goes on
+fn trend(global: &GlobalArgs, args: &TrendArgs, mut options: Options) -> Result<()> {
+    args.parse.apply_to(&mut options);
+    let dir = &args.dir;
+    let mut trees = Vec::new();
+    for kernel_path in discover_kernels(dir).with_context(|| format!("failed to read {:?}", dir))? {
+        match fetch_kernel_release(&FsTree, &kernel_path, None) {
+            Ok(version) => trees.push((version, kernel_path)),
+            Err(err) => eprintln!("warning: skip {:?}: {:#}", kernel_path, err),
         }
-        for arg in &args.arch {
-            info!("fetch arch: {:?}", arg);
-            let mut arch_path = args.kernel_path.clone();
-            arch_path.push("arch");
-            arch_path.push(arg);
-            arch_path.push("Kconfig");
-            warn!("fetch {:?} arch Kconfig path -> {:?}", arg, arch_path);
+    }
+    sort_by_version(&mut trees);
 
-            let mut kc = KconfigCounter::new(
-                arg.clone().to_string_lossy().into_owned(),
-                version.clone(),
-                arch_path,
-            );
-            if args.full {
-                kc.set_check_all();
-            }
-            kc.parse_kconfig();
-            kc.analyze_code();
-            kc.print();
+    let mut points = Vec::new();
+    for (version, kernel_path) in &trees {
+        info!("fetch trend tree Linux-{} -> {:?}", version, kernel_path);
+        match trend_tree(&options, &args.metrics, version, kernel_path) {
+            Ok(tree_points) => points.extend(tree_points),
+            Err(err) => eprintln!("warning: skip {:?}: {:#}", kernel_path, err),
         }
     }
+    let mut out = open_output(global)?;
+    write_trend(&mut out, &points, args.trend_format)?;
+    out.finish()?;
+    Ok(())
+}
 
+// follow one symbol through every given kernel tree, oldest kernel first
+fn trace_symbol(global: &GlobalArgs, args: &TraceArgs, mut options: Options) -> Result<()> {
+    args.parse.apply_to(&mut options);
+    for arch in &options.arches {
+        let mut counters = Vec::new();
+        for kernel_path in &args.kernel_paths {
+            let version = fetch_kernel_release(&FsTree, kernel_path, None)
+                .with_context(|| format!("failed to read kernel version of {:?}", kernel_path))?;
+            let mut kc = options.kconfig_counter(arch, &version, kernel_path)?;
+            kc.parse_kconfig()
+                .with_context(|| format!("failed to parse Kconfig of {:?}", kernel_path))?;
+            counters.push(kc);
+        }
+        let report = KconfigCounter::trace(&args.symbol, &mut counters);
+        text_renderer(global).render(&AnalysisResult::Trace(report), &mut io::stdout())?;
+    }
     Ok(())
 }
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let global = &cli.global;
+    let _logger = set_logger(
+        global.log_file.as_deref(),
+        global.verbose,
+        global.log_filter.as_deref(),
+        global.log_format,
+    )?;
+
+    let options = pipeline_options(&cli)?;
+    if matches!(
+        cli.command,
+        Command::Count(_) | Command::Analyze(_) | Command::Validate(_)
+    ) {
+        install_interrupt_handler(options.stop.clone())?;
+    }
+    match &cli.command {
+        Command::Count(_) => validate_kernel_tree(&options, false)?,
+        Command::Kconfig(args) if args.stdin => {}
+        Command::Analyze(args) if args.kconfig.stdin => {}
+        Command::Kconfig(_) | Command::Analyze(_) | Command::Validate(_) => {
+            validate_kernel_tree(&options, true)?
+        }
+        Command::Diff(_) | Command::Trend(_) | Command::Trace(_) => {}
+    }
+    match &cli.command {
+        Command::Count(args) => count(global, args, options),
+        Command::Kconfig(args) => kconfig(global, args, None, false, options),
+        Command::Analyze(args) => {
+            let mut options = options;
+            args.code.apply_to(&mut options);
+            kconfig(global, &args.kconfig, args.top_snippets, true, options)
+        }
+        Command::Diff(args) => diff(global, args, options),
+        Command::Validate(args) => validate(global, args, options),
+        Command::Trend(args) => trend(global, args, options),
+        Command::Trace(args) => trace_symbol(global, args, options),
+    }
+}
@@ -0,0 +1,211 @@
+#![cfg(feature = "cli")]
+
+use auto_script::cli::{Cli, Command, TrendMetrics};
+use clap::error::ErrorKind;
+use clap::Parser;
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from([&["auto_script"], args].concat())
+        .unwrap_or_else(|err| panic!("{:?}: {}", args, err))
+}
+
+fn reject(args: &[&str]) -> ErrorKind {
+    match Cli::try_parse_from([&["auto_script"], args].concat()) {
+        Ok(cli) => panic!("{:?} parsed as {:?}", args, cli.command),
+        Err(err) => err.kind(),
+    }
+}
+
+#[test]
+fn count_takes_the_code_options() {
+    let cli = parse(&[
+        "count",
+        "--whole-tree",
+        "--todos",
+        "--lang-map",
+        "dtso=DeviceTree",
+    ]);
+    let Command::Count(count) = cli.command else {
+        panic!("not count");
+    };
+    assert!(count.code.whole_tree);
+    assert_eq!(count.todos, Some(10));
+    assert_eq!(count.code.lang_map, [("dtso".into(), "DeviceTree".into())]);
+
+    assert_eq!(reject(&["count", "--full"]), ErrorKind::UnknownArgument);
+    assert_eq!(
+        reject(&["count", "--lang-map", "dtso"]),
+        ErrorKind::ValueValidation
+    );
+}
+
+#[test]
+fn global_options_go_before_or_after_the_subcommand() {
+    let before = parse(&["-a", "riscv,arm64", "-p", "/tmp/linux", "kconfig", "--dot"]);
+    let after = parse(&["kconfig", "--dot", "-a", "riscv,arm64", "-p", "/tmp/linux"]);
+    for cli in [before, after] {
+        assert_eq!(cli.global.arch, [PathBuf::from("riscv"), "arm64".into()]);
+        assert_eq!(cli.global.kernel_path, PathBuf::from("/tmp/linux"));
+        assert!(matches!(cli.command, Command::Kconfig(args) if args.dot));
+    }
+}
+
+#[test]
+fn kernel_picks_a_tree_of_the_workspace_instead_of_the_kernel_path() {
+    let cli = parse(&["count", "--workspace", "/srv/kernels", "--kernel", "6.9"]);
+    assert_eq!(cli.global.workspace, Some(PathBuf::from("/srv/kernels")));
+    assert_eq!(cli.global.kernel.as_deref(), Some("6.9"));
+
+    // the workspace may come from the config file instead
+    let cli = parse(&["count", "--kernel", "6.9"]);
+    assert_eq!(cli.global.workspace, None);
+    assert_eq!(
+        reject(&["count", "--workspace", "/srv/kernels"]),
+        ErrorKind::MissingRequiredArgument
+    );
+    assert_eq!(
+        reject(&[
+            "count",
+            "-p",
+            "/tmp/linux",
+            "--workspace",
+            "/srv",
+            "--kernel",
+            "6.9"
+        ]),
+        ErrorKind::ArgumentConflict
+    );
+}
+
+#[test]
+fn kconfig_and_analyze_split_on_guarded_code() {
+    let cli = parse(&["kconfig", "--list-components", "--list-types", "--full"]);
+    let Command::Kconfig(kconfig) = cli.command else {
+        panic!("not kconfig");
+    };
+    assert!(kconfig.list_components && kconfig.list_types && kconfig.parse.full);
+    assert_eq!(
+        reject(&["kconfig", "--list-types"]),
+        ErrorKind::MissingRequiredArgument
+    );
+    assert_eq!(
+        reject(&["kconfig", "--top-snippets", "3"]),
+        ErrorKind::UnknownArgument
+    );
+
+    let cli = parse(&["analyze", "--top-snippets", "3", "--code-dir", "drivers,fs"]);
+    let Command::Analyze(analyze) = cli.command else {
+        panic!("not analyze");
+    };
+    assert_eq!(analyze.top_snippets, Some(3));
+    assert_eq!(
+        analyze.code.code_dir,
+        [PathBuf::from("drivers"), "fs".into()]
+    );
+}
+
+#[test]
+fn diff_needs_exactly_one_other_side() {
+    let cli = parse(&["diff", "/tmp/old", "--code-delta", "--format", "json"]);
+    let Command::Diff(diff) = cli.command else {
+        panic!("not diff");
+    };
+    assert_eq!(diff.other, Some(PathBuf::from("/tmp/old")));
+    assert!(diff.code_delta);
+
+    let cli = parse(&["diff", "--defconfig", "a", "--compare-defconfig", "b"]);
+    assert!(matches!(cli.command, Command::Diff(diff) if diff.other.is_none()));
+
+    assert_eq!(reject(&["diff"]), ErrorKind::MissingRequiredArgument);
+    assert_eq!(
+        reject(&["diff", "--defconfig", "a"]),
+        ErrorKind::MissingRequiredArgument
+    );
+    assert_eq!(
+        reject(&[
+            "diff",
+            "/tmp/old",
+            "--defconfig",
+            "a",
+            "--compare-defconfig",
+            "b"
+        ]),
+        ErrorKind::ArgumentConflict
+    );
+    assert_eq!(
+        reject(&[
+            "diff",
+            "--defconfig",
+            "a",
+            "--compare-defconfig",
+            "b",
+            "--code-delta"
+        ]),
+        ErrorKind::ArgumentConflict
+    );
+}
+
+#[test]
+fn validate_needs_a_baseline() {
+    let cli = parse(&[
+        "validate",
+        "--write-baseline",
+        "new.json",
+        "--check-baseline",
+        "old.json",
+        "--tolerance",
+        "5%",
+    ]);
+    let Command::Validate(validate) = cli.command else {
+        panic!("not validate");
+    };
+    assert_eq!(validate.write_baseline, Some(PathBuf::from("new.json")));
+    assert_eq!(validate.tolerance, 5.0);
+
+    assert_eq!(reject(&["validate"]), ErrorKind::MissingRequiredArgument);
+    assert_eq!(
+        reject(&[
+            "validate",
+            "--write-baseline",
+            "new.json",
+            "--tolerance",
+            "5%"
+        ]),
+        ErrorKind::MissingRequiredArgument
+    );
+}
+
+#[test]
+fn trend_and_trace_take_their_trees_as_positionals() {
+    let cli = parse(&["trend", "/srv/kernels", "--metrics", "code,analyze"]);
+    let Command::Trend(trend) = cli.command else {
+        panic!("not trend");
+    };
+    assert_eq!(trend.dir, PathBuf::from("/srv/kernels"));
+    assert_eq!(trend.metrics, [TrendMetrics::Code, TrendMetrics::Analyze]);
+
+    let cli = parse(&["trace", "SMP", "/opt/linux-6.8", "/opt/linux-6.9.5"]);
+    let Command::Trace(trace) = cli.command else {
+        panic!("not trace");
+    };
+    assert_eq!(trace.symbol, "SMP");
+    assert_eq!(trace.kernel_paths.len(), 2);
+    assert_eq!(
+        reject(&["trace", "SMP"]),
+        ErrorKind::MissingRequiredArgument
+    );
+}
+
+#[cfg(all(feature = "fetch", feature = "git"))]
+#[test]
+fn fetch_conflicts_with_git_rev() {
+    assert!(parse(&["count", "--fetch", "6.9.5"])
+        .global
+        .git_rev
+        .is_none());
+    assert_eq!(
+        reject(&["count", "--fetch", "6.9.5", "--git-rev", "v6.9"]),
+        ErrorKind::ArgumentConflict
+    );
+}
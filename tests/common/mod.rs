@@ -0,0 +1,324 @@
+// shared by every integration test binary, not all of them use every helper
+#![allow(dead_code)]
+
+use auto_script::core::source::{DirEntry, SourceTree};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+pub const VERSION: &str = "6.9.5";
+
+const MAKEFILE: &str = "\
+# SPDX-License-Identifier: GPL-2.0
+VERSION = 6
+PATCHLEVEL = 9
+SUBLEVEL = 5
+EXTRAVERSION =
+NAME = Hurr durr I'ma ninja sloth
+";
+
+const ARCH_KCONFIG: &str = "\
+# SPDX-License-Identifier: GPL-2.0-only
+config RISCV
+\tdef_bool y
+\tselect GENERIC_BAR
+
+config MMU
+\tbool \"MMU-based Paged Memory Management Support\"
+\tdefault y
+
+config SMP
+\tbool \"Symmetric Multi-Processing\"
+\tdepends on MMU
+
+config NR_CPUS
+\tint \"Maximum number of CPUs (2-512)\"
+\tdepends on SMP
+\tdefault \"8\"
+
+source \"arch/riscv/kernel/Kconfig\"
+source \"init/Kconfig\"
+";
+
+const KERNEL_KCONFIG: &str = "\
+config FOO_DRV
+\ttristate \"foo driver\"
+\tdepends on MMU
+";
+
+const INIT_KCONFIG: &str = "\
+config INIT_ONLY
+\tbool \"outside of arch/, not followed by default\"
+";
+
+const SMP_C: &str = "\
+// SPDX-License-Identifier: GPL-2.0
+#include <linux/smp.h>
+
+#ifdef CONFIG_SMP
+int smp_boot(void)
+{
+\treturn 0;
+}
+#endif
+
+#if IS_ENABLED(CONFIG_FOO_DRV_MODULE)
+int foo;
+#endif
+";
+
+const SMP_H: &str = "\
+/* SPDX-License-Identifier: GPL-2.0 */
+#ifndef _ASM_SMP_H
+#define _ASM_SMP_H
+
+#ifdef CONFIG_SMP
+void smp_boot(void);
+#endif
+
+#endif
+";
+
+// a kernel tree materialized into a tempdir, removed again on drop
+pub struct FakeKernel {
+    dir: TempDir,
+    root: PathBuf,
+}
+
+pub struct FakeKernelBuilder {
+    files: Vec<(PathBuf, Vec<u8>)>,
+    dir_name: String,
+}
+
+impl FakeKernelBuilder {
+    // only the top level Makefile, enough for `fetch_kernel_version`
+    pub fn empty() -> Self {
+        FakeKernelBuilder {
+            files: vec![(PathBuf::from("Makefile"), MAKEFILE.into())],
+            dir_name: format!("linux-{}", VERSION),
+        }
+    }
+
+    // Makefile, an arch Kconfig sourcing two files and guarded C sources
+    pub fn riscv() -> Self {
+        FakeKernelBuilder::empty()
+            .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+            .file("arch/riscv/kernel/Kconfig", KERNEL_KCONFIG)
+            .file("arch/riscv/kernel/smp.c", SMP_C)
+            .file("arch/riscv/kernel/smp.h", SMP_H)
+            .file("init/Kconfig", INIT_KCONFIG)
+    }
+
+    pub fn file(mut self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        self.files.retain(|(existing, _)| *existing != path);
+        self.files.push((path, content.as_ref().to_vec()));
+        self
+    }
+
+    // the tree is unpacked as `linux-<version>` unless renamed here
+    pub fn dir_name(mut self, dir_name: &str) -> Self {
+        self.dir_name = dir_name.to_string();
+        self
+    }
+
+    // the same files below `root` in memory, nothing is written to disk
+    pub fn tree(self, root: impl AsRef<Path>) -> MapTree {
+        let root = root.as_ref();
+        MapTree {
+            files: self
+                .files
+                .into_iter()
+                .map(|(path, content)| (root.join(path), content))
+                .collect(),
+        }
+    }
+
+    pub fn build(self) -> FakeKernel {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let root = dir.path().join(&self.dir_name);
+        for (path, content) in self.files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).expect("create fixture dir");
+            fs::write(&path, content).expect("write fixture file");
+        }
+        FakeKernel { dir, root }
+    }
+}
+
+// directories exist as long as a file lies below them
+#[derive(Debug)]
+pub struct MapTree {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl SourceTree for MapTree {
+    fn read(&self, path: &Path) -> io::Result<Box<dyn BufRead + '_>> {
+        match self.files.get(path) {
+            Some(content) => Ok(Box::new(content.as_slice())),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        if !self.is_dir(path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        let mut children = BTreeMap::new();
+        for (file, content) in &self.files {
+            let Ok(relative) = file.strip_prefix(path) else {
+                continue;
+            };
+            let mut components = relative.components();
+            let Some(child) = components.next() else {
+                continue;
+            };
+            let is_dir = components.next().is_some();
+            children.entry(path.join(child)).or_insert(DirEntry {
+                path: path.join(child),
+                is_dir,
+                len: if is_dir { 0 } else { content.len() as u64 },
+                mtime: None,
+                inode: None,
+            });
+        }
+        Ok(children.into_values().collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+}
+
+pub struct Output {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl FakeKernel {
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // runs the binary against this tree; stdin answers the interactive prompt
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.run_with_input(args, "q\n")
+    }
+
+    pub fn run_with_input(&self, args: &[&str], input: &str) -> Output {
+        self.run_at(&self.root, args, input)
+    }
+
+    // `--kernel-path` pointing elsewhere, e.g. at the directory holding the tree
+    pub fn run_at(&self, kernel_path: &Path, args: &[&str], input: &str) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_auto_script"))
+            .arg("--kernel-path")
+            .arg(kernel_path)
+            .args(args)
+            // the log directory is created in the working directory
+            .current_dir(self.dir.path())
+            // keeps `--history` away from the real home directory
+            .env("HOME", self.dir.path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn auto_script");
+        // non-interactive runs may exit before reading stdin at all
+        if let Err(err) = child.stdin.take().unwrap().write_all(input.as_bytes()) {
+            assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe, "write stdin");
+        }
+        let output = child.wait_with_output().expect("wait for auto_script");
+        Output {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    pub fn run_ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.success,
+            "auto_script {:?} failed:\n{}",
+            args, output.stderr
+        );
+        output.stdout
+    }
+
+    // FileCounter table of the first arch, keyed by language
+    pub fn count_code(&self, args: &[&str]) -> Vec<LanguageRow> {
+        let stdout = self.run_ok(&[&["count"], args].concat());
+        language_rows(&stdout)
+    }
+
+    // `--list-components --list-types` as (name, type) pairs
+    pub fn list_components(&self, args: &[&str]) -> Vec<(String, String)> {
+        let stdout =
+            self.run_ok(&[&["kconfig", "--list-components", "--list-types"], args].concat());
+        stdout
+            .lines()
+            .map(|line| {
+                let (name, value_type) = line.split_once('\t').expect("tab separated line");
+                (name.to_string(), value_type.to_string())
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct LanguageRow {
+    pub language: String,
+    pub files: usize,
+    pub blank: usize,
+    pub comment: usize,
+    pub code: usize,
+}
+
+// language names are padded to 16 columns and may contain spaces
+pub fn language_rows(stdout: &str) -> Vec<LanguageRow> {
+    let mut rows = Vec::new();
+    let mut in_table = false;
+    for line in stdout.lines() {
+        if line.starts_with("Language") {
+            in_table = true;
+            continue;
+        }
+        if !in_table || line.starts_with('-') {
+            continue;
+        }
+        if line.starts_with("SUM:") {
+            break;
+        }
+        let (language, columns) = line.split_at(16.min(line.len()));
+        let columns: Vec<usize> = columns
+            .split_whitespace()
+            .take(4)
+            .map(|column| column.parse().expect("numeric column"))
+            .collect();
+        rows.push(LanguageRow {
+            language: language.trim().to_string(),
+            files: columns[0],
+            blank: columns[1],
+            comment: columns[2],
+            code: columns[3],
+        });
+    }
+    rows
+}
+
+pub fn row<'a>(rows: &'a [LanguageRow], language: &str) -> &'a LanguageRow {
+    rows.iter()
+        .find(|row| row.language == language)
+        .unwrap_or_else(|| panic!("no {} row in {:?}", language, rows))
+}
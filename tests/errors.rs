@@ -0,0 +1,286 @@
+mod common;
+
+use auto_script::core::pipeline::{Options, Pipeline};
+use auto_script::core::workspace::{select_kernel, Config};
+use auto_script::AutoScriptError;
+use common::FakeKernelBuilder;
+use std::io::ErrorKind;
+use std::process::Command;
+
+#[test]
+fn missing_kernel_version_names_the_makefile() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "Makefile",
+            "# SPDX-License-Identifier: GPL-2.0\nVERSION = 6\n",
+        )
+        .build();
+    let output = kernel.run(&["kconfig"]);
+
+    assert!(!output.success);
+    let makefile = kernel.root().join("Makefile");
+    assert!(
+        output.stderr.contains(&format!(
+            "no VERSION, PATCHLEVEL and SUBLEVEL found in {}",
+            makefile.display()
+        )),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn unknown_arch_names_the_missing_path() {
+    let kernel = FakeKernelBuilder::riscv().build();
+
+    let output = kernel.run(&["kconfig", "--arch", "arm64"]);
+    assert!(!output.success);
+    let kconfig = kernel.root().join("arch/arm64/Kconfig");
+    assert!(
+        output.stderr.contains(&format!(
+            "arch \"arm64\" not found: {} does not exist",
+            kconfig.display()
+        )),
+        "{}",
+        output.stderr
+    );
+
+    let output = kernel.run(&["count", "--arch", "arm64"]);
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains("arch \"arm64\" not found"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn wrong_kernel_path_lists_every_problem_and_the_tree_below() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let parent = kernel.root().parent().unwrap();
+    let output = kernel.run_at(parent, &["kconfig", "--arch", "riscv,arm64"], "");
+
+    assert!(!output.success);
+    for expected in [
+        format!("{} is not a usable kernel tree:", parent.display()),
+        format!(
+            "  - no top level Makefile at {}",
+            parent.join("Makefile").display()
+        ),
+        format!(
+            "  - no arch directory at {}",
+            parent.join("arch/").display()
+        ),
+        format!(
+            "it holds one kernel tree, try --kernel-path {}",
+            kernel.root().display()
+        ),
+    ] {
+        assert!(output.stderr.contains(&expected), "{}", output.stderr);
+    }
+
+    // inside the tree, each missing arch is named
+    let output = kernel.run(&["kconfig", "--arch", "arm64,x86"]);
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains("arch \"arm64\" not found"),
+        "{}",
+        output.stderr
+    );
+    assert!(
+        output.stderr.contains("arch \"x86\" not found"),
+        "{}",
+        output.stderr
+    );
+    assert!(
+        !output.stderr.contains("try --kernel-path"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn missing_source_names_both_kconfig_files() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\nsource \"arch/riscv/kvm/Kconfig\"\n",
+        )
+        .build();
+    let output = kernel.run(&["kconfig"]);
+
+    assert!(!output.success);
+    let referenced_from = kernel.root().join("arch/riscv/kernel/Kconfig");
+    let missing = kernel.root().join("arch/riscv/kvm/Kconfig");
+    assert!(
+        output.stderr.contains(&format!(
+            "{} sources {}, which does not exist",
+            referenced_from.display(),
+            missing.display()
+        )),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn library_errors_keep_their_variant() {
+    let dir = tempfile::tempdir().unwrap();
+    let makefile = dir.path().join("Makefile");
+    let pipeline = || {
+        Pipeline::new(Options {
+            kernel_path: dir.path().to_path_buf(),
+            ..Options::default()
+        })
+    };
+
+    match pipeline() {
+        Err(AutoScriptError::Io { path, source }) => {
+            assert_eq!(path, makefile);
+            assert_eq!(source.kind(), ErrorKind::NotFound);
+        }
+        Err(err) => panic!("unexpected error {:?}", err),
+        Ok(_) => panic!("no Makefile, no pipeline"),
+    }
+
+    std::fs::write(&makefile, "VERSION = 6\n").unwrap();
+    match pipeline() {
+        Err(AutoScriptError::KernelVersionNotFound { makefile: path }) => {
+            assert_eq!(path, makefile)
+        }
+        Err(err) => panic!("unexpected error {:?}", err),
+        Ok(_) => panic!("no PATCHLEVEL, no pipeline"),
+    }
+}
+
+#[test]
+fn kernel_selects_the_newest_matching_tree_of_a_workspace() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let workspace = kernel.root().parent().unwrap();
+    let add_tree = |dir: &str, version: (u32, u32, u32)| {
+        let makefile = format!(
+            "VERSION = {}\nPATCHLEVEL = {}\nSUBLEVEL = {}\nEXTRAVERSION =\n",
+            version.0, version.1, version.2
+        );
+        std::fs::create_dir(workspace.join(dir)).unwrap();
+        std::fs::write(workspace.join(dir).join("Makefile"), makefile).unwrap();
+    };
+    add_tree("linux-6.9.1", (6, 9, 1));
+    add_tree("linux-6.90.0", (6, 90, 0));
+    add_tree("stable-6.8", (6, 8, 12));
+    add_tree("mainline-6.8", (6, 8, 12));
+
+    let (version, path) = select_kernel(workspace, "6.9").unwrap();
+    assert_eq!(version.to_string(), "6.9.5");
+    assert_eq!(path, kernel.root());
+    assert_eq!(
+        select_kernel(workspace, "6.9.1").unwrap().1,
+        workspace.join("linux-6.9.1")
+    );
+    assert_eq!(
+        select_kernel(workspace, "6").unwrap().0.to_string(),
+        "6.90.0"
+    );
+
+    let err = select_kernel(workspace, "5.15").unwrap_err();
+    assert!(matches!(err, AutoScriptError::KernelNotFound { .. }));
+    assert!(err.to_string().contains("6.9.1 at "), "{}", err);
+    let err = select_kernel(workspace, "6.8").unwrap_err();
+    assert!(matches!(err, AutoScriptError::AmbiguousKernel { .. }));
+    assert!(err.to_string().contains("mainline-6.8"), "{}", err);
+    assert!(!err.to_string().contains("6.9.1"), "{}", err);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_auto_script"))
+        .args(["count", "--workspace"])
+        .arg(workspace)
+        .args(["--kernel", "6.9.5"])
+        .current_dir(workspace)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("Linux-6.9.5 Arch RISCV"), "{}", stdout);
+}
+
+#[test]
+fn kernel_falls_back_to_the_workspace_of_the_config_file() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let workspace = kernel.root().parent().unwrap();
+    let config_home = tempfile::tempdir().unwrap();
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_auto_script"))
+            .args(["count", "--kernel", "6.9"])
+            .current_dir(workspace)
+            .env("XDG_CONFIG_HOME", config_home.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("`workspace = DIR` in "), "{}", stderr);
+
+    std::fs::create_dir(config_home.path().join("auto_script")).unwrap();
+    std::fs::write(
+        config_home.path().join("auto_script/config"),
+        format!("# kernels\nworkspace = {}\n", workspace.display()),
+    )
+    .unwrap();
+    let output = run();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("Linux-6.9.5 Arch RISCV"), "{}", stdout);
+
+    let path = std::path::Path::new("config");
+    let config = Config::parse(
+        path,
+        "\n# comment\ncolor = always\n workspace=/srv/kernels \n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.workspace.unwrap(),
+        std::path::Path::new("/srv/kernels")
+    );
+    let err = Config::parse(path, "workspace\n").unwrap_err();
+    assert!(matches!(
+        err,
+        AutoScriptError::InvalidConfig { line: 1, .. }
+    ));
+    assert_eq!(err.to_string(), "config:1: expected `key = value`");
+    assert!(Config::parse(path, "workspace =\n").is_err());
+    assert!(Config::load(&config_home.path().join("missing"))
+        .unwrap()
+        .workspace
+        .is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_summary_writes_fail_the_run() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let workdir = tempfile::tempdir().unwrap();
+    for args in [&["count"][..], &["kconfig", "--summary-only"]] {
+        let full = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/full")
+            .unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_auto_script"))
+            .arg("--kernel-path")
+            .arg(kernel.root())
+            .args(args)
+            .current_dir(workdir.path())
+            .stdout(full)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{:?}: {}", args, stderr);
+        assert!(
+            stderr.contains("No space left on device"),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+        assert!(!stderr.contains("panicked"), "{:?}: {}", args, stderr);
+    }
+}
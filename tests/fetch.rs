@@ -0,0 +1,198 @@
+#![cfg(feature = "fetch")]
+
+mod common;
+
+use auto_script::core::fetch::Fetcher;
+use auto_script::core::version::KernelVersion;
+use auto_script::AutoScriptError;
+use common::FakeKernelBuilder;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// the fixture tree packed like a kernel.org release
+fn tarball() -> Vec<u8> {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let mut builder = tar::Builder::new(xz2::write::XzEncoder::new(Vec::new(), 1));
+    builder
+        .append_dir_all("linux-6.9.5", kernel.root())
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+// serves `v6.x/linux-6.9.5.tar.xz` and `v6.x/sha256sums.asc`, which lists the tarball
+// only with a `sha256`, until the test ends; returns the base url and how often the
+// tarball was requested
+fn serve(tarball: Vec<u8>, sha256: Option<String>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let downloads = Arc::new(AtomicUsize::new(0));
+    let counter = downloads.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = String::new();
+            BufReader::new(&stream).read_line(&mut request).unwrap();
+            let sums = match &sha256 {
+                Some(sha256) => format!(
+                    "-----BEGIN PGP SIGNED MESSAGE-----\n\n{}  linux-6.9.5.tar.xz\n",
+                    sha256
+                ),
+                None => "-----BEGIN PGP SIGNED MESSAGE-----\n\n".to_string(),
+            };
+            let (status, body) = match request.split_whitespace().nth(1) {
+                Some("/v6.x/linux-6.9.5.tar.xz") => {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    ("200 OK", tarball.clone())
+                }
+                Some("/v6.x/sha256sums.asc") => ("200 OK", sums.into_bytes()),
+                _ => ("404 Not Found", Vec::new()),
+            };
+            let header = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+    (base_url, downloads)
+}
+
+#[test]
+fn fetch_downloads_once_and_reuses_the_cache() {
+    let tarball = tarball();
+    let sha256: String = Sha256::digest(&tarball)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let (base_url, downloads) = serve(tarball, Some(sha256));
+    let cache = tempfile::tempdir().unwrap();
+    let fetcher = Fetcher::new(cache.path()).base_url(&base_url);
+
+    let version = KernelVersion::from("6.9.5");
+    let tree = fetcher.fetch(&version).unwrap();
+    assert_eq!(tree, cache.path().join("linux-6.9.5"));
+    assert!(tree.join("arch/riscv/Kconfig").is_file());
+    assert_eq!(fetcher.fetch(&version).unwrap(), tree);
+    assert_eq!(downloads.load(Ordering::SeqCst), 1);
+
+    // nothing for 6.10 on the server, and nothing in the cache offline
+    let missing = KernelVersion::from("6.10");
+    assert!(matches!(
+        fetcher.fetch(&missing),
+        Err(AutoScriptError::Download { ref url, .. }) if url.ends_with("/v6.x/linux-6.10.tar.xz")
+    ));
+    let offline = Fetcher::new(cache.path()).base_url(&base_url).offline(true);
+    assert_eq!(offline.fetch(&version).unwrap(), tree);
+    let err = offline.fetch(&missing).unwrap_err();
+    assert!(err.to_string().contains("run without --offline"), "{}", err);
+}
+
+#[test]
+fn a_checksum_mismatch_discards_the_download() {
+    let (base_url, _) = serve(tarball(), Some("0".repeat(64)));
+    let cache = tempfile::tempdir().unwrap();
+    let err = Fetcher::new(cache.path())
+        .base_url(base_url)
+        .fetch(&KernelVersion::from("6.9.5"))
+        .unwrap_err();
+    assert!(
+        matches!(err, AutoScriptError::Corrupted { what: "sha256", .. }),
+        "{}",
+        err
+    );
+    assert_eq!(std::fs::read_dir(cache.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn a_tampered_cached_tarball_is_not_unpacked() {
+    let tarball = tarball();
+    let sha256: String = Sha256::digest(&tarball)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let (base_url, downloads) = serve(tarball, Some(sha256.clone()));
+    let cache = tempfile::tempdir().unwrap();
+    let fetcher = Fetcher::new(cache.path()).base_url(&base_url);
+    let version = KernelVersion::from("6.9.5");
+    let cached = cache.path().join("linux-6.9.5.tar.xz");
+    let record = cache.path().join("linux-6.9.5.tar.xz.sha256");
+    // only the tarball is left, as after an interrupted unpack
+    let tamper = |keep_record: bool| {
+        std::fs::remove_dir_all(cache.path().join("linux-6.9.5")).unwrap();
+        let mut bytes = std::fs::read(&cached).unwrap();
+        bytes.push(0);
+        std::fs::write(&cached, bytes).unwrap();
+        if !keep_record {
+            std::fs::remove_file(&record).unwrap();
+        }
+    };
+
+    fetcher.fetch(&version).unwrap();
+    assert_eq!(std::fs::read_to_string(&record).unwrap().trim(), sha256);
+
+    // against the digest recorded on download, and against the sums without it
+    for keep_record in [true, false] {
+        tamper(keep_record);
+        let err = fetcher.fetch(&version).unwrap_err();
+        assert!(
+            matches!(err, AutoScriptError::Corrupted { what: "sha256", ref expected, .. } if *expected == sha256),
+            "{}",
+            err
+        );
+        assert!(!cached.exists() && !record.exists());
+        assert!(!cache.path().join("linux-6.9.5").exists());
+        // the next fetch downloads it again
+        fetcher.fetch(&version).unwrap();
+    }
+    assert_eq!(downloads.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn an_unverified_download_records_no_digest() {
+    // the release sums do not list the tarball, only its size is checked
+    let (base_url, _) = serve(tarball(), None);
+    let cache = tempfile::tempdir().unwrap();
+    let tree = Fetcher::new(cache.path())
+        .base_url(base_url)
+        .fetch(&KernelVersion::from("6.9.5"))
+        .unwrap();
+    assert!(tree.join("arch/riscv/Kconfig").is_file());
+    assert!(cache.path().join("linux-6.9.5.tar.xz").is_file());
+    assert!(!cache.path().join("linux-6.9.5.tar.xz.sha256").exists());
+}
+
+#[test]
+fn fetch_offline_runs_on_the_cached_tree() {
+    let kernel = FakeKernelBuilder::riscv()
+        .dir_name("auto_script/linux-6.9.5")
+        .build();
+    let cache_home = kernel.root().parent().unwrap().parent().unwrap();
+    let run = |version: &str| {
+        Command::new(env!("CARGO_BIN_EXE_auto_script"))
+            .args(["count", "--fetch", version, "--offline"])
+            .current_dir(cache_home)
+            .env("XDG_CACHE_HOME", cache_home)
+            .output()
+            .unwrap()
+    };
+
+    let output = run("6.9.5");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("Linux-6.9.5 Arch RISCV"), "{}", stdout);
+
+    let output = run("6.8.1");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("linux-6.8.1 is not in the cache"),
+        "{}",
+        stderr
+    );
+}
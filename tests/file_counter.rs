@@ -0,0 +1,720 @@
+mod common;
+
+use auto_script::core::cache::ScanCache;
+use auto_script::core::source::{DirEntry, SourceTree};
+use auto_script::{FileCounter, FileType};
+use common::{language_rows, row, FakeKernel, FakeKernelBuilder, LanguageRow, MapTree};
+use std::cell::Cell;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn counts_the_fake_riscv_tree() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let rows = kernel.count_code(&[]);
+
+    let c = row(&rows, "C");
+    assert_eq!((c.files, c.blank, c.comment, c.code), (1, 2, 1, 10));
+    let header = row(&rows, "C Header");
+    assert_eq!(
+        (header.files, header.blank, header.comment, header.code),
+        (1, 2, 1, 6)
+    );
+    assert_eq!(row(&rows, "kconfig").files, 2);
+    // init/ lies outside of arch/riscv
+    assert_eq!(rows.iter().map(|row| row.files).sum::<usize>(), 4);
+}
+
+#[test]
+fn cpp_and_c_land_in_distinct_buckets() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/tools/a.c", "int a;\n")
+        .file("arch/riscv/tools/b.cpp", "int b;\nint c;\n")
+        .file("arch/riscv/tools/c.cc", "int d;\n")
+        .file("arch/riscv/tools/a.h", "int a;\n")
+        .file("arch/riscv/tools/b.hpp", "int b;\n")
+        .build();
+    let rows = kernel.count_code(&[]);
+
+    assert_eq!(row(&rows, "C").files, 1);
+    assert_eq!(row(&rows, "C++").files, 2);
+    assert_eq!(row(&rows, "C++").code, 3);
+    assert_eq!(row(&rows, "C Header").files, 1);
+    assert_eq!(row(&rows, "C++ Header").files, 1);
+}
+
+#[test]
+fn comments_follow_the_language_syntax() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/a.c",
+            "#include <x.h>\n/*\n * block\n */\nint a; /* open\n   still */\n// line\nint b;\n",
+        )
+        .file(
+            "arch/riscv/b.S",
+            "# comment\n; comment\nli a0, 1\n/* c */\n",
+        )
+        .file("arch/riscv/c.py", "# comment\nx = 1 ; y = 2\n// code\n")
+        .file("arch/riscv/Makefile", "# comment\nobj-y += a.o\n; code\n")
+        .build();
+    let rows = kernel.count_code(&[]);
+
+    let c = row(&rows, "C");
+    assert_eq!((c.comment, c.code), (5, 3));
+    let asm = row(&rows, "Assembly");
+    assert_eq!((asm.comment, asm.code), (3, 1));
+    let python = row(&rows, "Python");
+    assert_eq!((python.comment, python.code), (1, 2));
+    let makefile = row(&rows, "Makefile");
+    assert_eq!((makefile.comment, makefile.code), (1, 2));
+}
+
+fn export(kernel: &FakeKernel, args: &[&str]) -> serde_json::Value {
+    let stdout = kernel.run_ok(args);
+    serde_json::from_str(&stdout).expect("parse the JSON export")
+}
+
+// the row of `language` in one counter of the JSON export
+fn language<'a>(export: &'a serde_json::Value, language: &str) -> &'a serde_json::Value {
+    export["languages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|row| row["language"] == language)
+        .unwrap_or_else(|| panic!("no {} row in {}", language, export))
+}
+
+#[test]
+fn spdx_licenses_are_tallied_per_arch() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/a.c",
+            "// SPDX-License-Identifier: GPL-2.0\nint a;\n",
+        )
+        .file(
+            "arch/riscv/b.c",
+            "// SPDX-License-Identifier: GPL-2.0\nint b;\n",
+        )
+        .file(
+            "arch/riscv/c.S",
+            "/* SPDX-License-Identifier: GPL-2.0 or MIT */\nnop\n",
+        )
+        .file("arch/riscv/d.c", "int d;\n")
+        .build();
+
+    let json = export(&kernel, &["count", "--format", "json"]);
+    assert_eq!(
+        json["arches"][0]["spdx"],
+        serde_json::json!({"GPL-2.0": 2, "GPL-2.0 OR MIT": 1})
+    );
+    assert_eq!(json["arches"][0]["spdx_missing"], 1);
+
+    let stdout = kernel.run_ok(&["count", "--spdx"]);
+    let table = stdout.split_once("SPDX License").expect("SPDX table").1;
+    let rows: Vec<Vec<&str>> = table
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            vec!["files"],
+            vec!["GPL-2.0", "2"],
+            vec!["GPL-2.0", "OR", "MIT", "1"],
+            vec!["(none)", "1"]
+        ]
+    );
+}
+
+#[test]
+fn all_arches_sums_overlapping_and_keeps_disjoint_languages() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "int a;\n\n")
+        .file("arch/riscv/b.py", "# py\nx = 1\n")
+        .file("arch/arm64/c.c", "// c\nint c;\nint d;\n")
+        .file("arch/arm64/d.txt", "d\n")
+        .build();
+    let json = export(
+        &kernel,
+        &["count", "--format", "json", "--arch", "riscv,arm64"],
+    );
+
+    let all = &json["all_arches"];
+    assert_eq!(all["arch"], "all arches");
+    let c = language(all, "C");
+    assert_eq!(
+        [&c["files"], &c["blank"], &c["comment"], &c["code"]],
+        [2, 1, 1, 3]
+    );
+    assert_eq!(language(all, "Python")["code"], 1);
+    assert_eq!(language(all, "Documentation")["code"], 1);
+    assert_eq!(all["total"]["files"], 4);
+    // the merged counters are left alone
+    assert_eq!(json["arches"][0]["total"]["files"], 2);
+    assert_eq!(json["arches"][1]["total"]["files"], 2);
+
+    // a single arch has nothing to sum up
+    let json = export(&kernel, &["count", "--format", "json"]);
+    assert!(json.get("all_arches").is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_dirs_are_reported_without_aborting_the_walk() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "int a;\n")
+        .file("arch/riscv/locked/b.c", "int b;\n")
+        .build();
+    let locked = kernel.root().join("arch/riscv/locked");
+    let set_mode = |mode| fs::set_permissions(&locked, fs::Permissions::from_mode(mode)).unwrap();
+    set_mode(0o000);
+    // root reads the directory anyway, nothing to report then
+    if fs::read_dir(&locked).is_ok() {
+        set_mode(0o755);
+        return;
+    }
+
+    let output = kernel.run(&["count", "--format", "json"]);
+    let strict = kernel.run(&["count", "--strict"]);
+    set_mode(0o755);
+
+    assert!(output.success, "{}", output.stderr);
+    assert!(
+        output
+            .stderr
+            .contains("riscv: 1 unreadable directories, 0 unreadable files"),
+        "{}",
+        output.stderr
+    );
+    assert!(
+        output.stderr.contains("arch/riscv/locked"),
+        "{}",
+        output.stderr
+    );
+    let json: serde_json::Value = serde_json::from_str(&output.stdout).unwrap();
+    assert_eq!(language(&json["arches"][0], "C")["files"], 1);
+    assert!(!strict.success);
+}
+
+#[test]
+fn export_carries_bytes_average_and_the_largest_file() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "int a;\n")
+        .file("arch/riscv/b.c", "int b;\nint c;\nint d;\n")
+        .build();
+    let json = export(&kernel, &["count", "--format", "json"]);
+
+    let c = language(&json["arches"][0], "C");
+    assert_eq!(c["bytes"], 7 + 21);
+    assert_eq!(c["average"], 2.0);
+    assert_eq!(c["max_code"], 3);
+    assert_eq!(
+        c["max_path"],
+        kernel.root().join("arch/riscv/b.c").to_str().unwrap()
+    );
+    assert_eq!(json["arches"][0]["total"]["bytes"], 28);
+    assert_eq!(json["arches"][0]["total"]["max_code"], 3);
+}
+
+#[test]
+fn extensionless_scripts_are_classified_by_their_shebang() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/tools/gen", "#!/bin/sh\n# comment\necho gen\n")
+        .file("arch/riscv/tools/build", "#!/bin/bash -e\nmake\n")
+        .file(
+            "arch/riscv/tools/check",
+            "#!/usr/bin/env python3\n# comment\nprint(1)\n",
+        )
+        .file("arch/riscv/tools/report", "#!/usr/bin/perl -w\nprint 1;\n")
+        .file(
+            "arch/riscv/tools/fields",
+            "#!/usr/bin/awk -f\n{ print $1 }\n",
+        )
+        .file("arch/riscv/tools/notes", "no shebang here\n")
+        .build();
+    let rows = kernel.count_code(&[]);
+    let counts = |name: &str| {
+        let row = row(&rows, name);
+        (row.files, row.comment, row.code)
+    };
+
+    // the shebang line is a comment in all of these languages
+    assert_eq!(counts("Shell"), (2, 3, 2));
+    assert_eq!(counts("Python"), (1, 2, 1));
+    assert_eq!(counts("Perl"), (1, 1, 1));
+    assert_eq!(counts("Awk"), (1, 1, 1));
+    assert_eq!(counts("Other").0, 1);
+}
+
+#[test]
+fn json_export_carries_the_markers_and_the_todo_hotspots() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "// TODO: FIXME\nint a; /* XXX */\n")
+        .file("arch/riscv/b.c", "// TODO\nint b;\n")
+        .file("arch/riscv/s.sh", "# FIXME\necho\n")
+        .build();
+    let json = export(&kernel, &["count", "--format", "json", "--todos", "1"]);
+
+    assert_eq!(
+        language(&json["arches"][0], "C")["markers"],
+        serde_json::json!({"todo": 2, "fixme": 1, "xxx": 0})
+    );
+    assert_eq!(json["arches"][0]["total"]["markers"]["fixme"], 2);
+    assert_eq!(
+        json["arches"][0]["todo_files"],
+        serde_json::json!([{"path": kernel.root().join("arch/riscv/a.c"), "markers": 2}])
+    );
+
+    // the hotspots are only listed with `--todos`
+    let json = export(&kernel, &["count", "--format", "json"]);
+    assert!(json["arches"][0].get("todo_files").is_none());
+}
+
+#[test]
+fn compare_prints_signed_deltas_per_language() {
+    let a = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "int a;\n\n// c\n")
+        .file("arch/riscv/p.py", "x = 1\n")
+        .build();
+    let b = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "int a;\nint b;\n")
+        .file("arch/riscv/b.c", "int b;\n")
+        .file("arch/riscv/s.sh", "echo\n")
+        .build();
+    let other = b.root().to_str().unwrap();
+    let stdout = a.run_ok(&["count", "--compare", other]);
+
+    let deltas: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip_while(|line| !line.contains(" -> "))
+        .skip_while(|line| !line.starts_with("Language"))
+        .skip(2)
+        .take_while(|line| !line.starts_with('-'))
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(
+        deltas,
+        [
+            ["C", "+1", "-1", "-1", "+2"],
+            // only on one side, the whole count is the delta
+            ["Python", "-1", "+0", "+0", "-1"],
+            ["Shell", "+1", "+0", "+0", "+1"],
+        ],
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.split_whitespace().collect::<Vec<_>>()
+                == ["SUM:", "+1", "-1", "-1", "+2"]),
+        "{}",
+        stdout
+    );
+
+    // the deltas have no JSON form
+    assert!(
+        !a.run(&["count", "--format", "json", "--compare", other])
+            .success
+    );
+}
+
+#[test]
+fn every_cpp_extension_maps_to_the_cpp_buckets() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/tools/a.cxx", "// c++\nint a;\n")
+        .file("arch/riscv/tools/b.cc", "int b;\n")
+        .file("arch/riscv/tools/c.hh", "int c;\n")
+        .file("arch/riscv/tools/d.hxx", "/* c++ */\nint d;\n")
+        .file("arch/riscv/tools/e.c", "int e;\n")
+        .build();
+    let rows = kernel.count_code(&[]);
+    let counts = |name: &str| {
+        let row = row(&rows, name);
+        (row.files, row.comment, row.code)
+    };
+
+    assert_eq!(counts("C++"), (2, 1, 2));
+    assert_eq!(counts("C++ Header"), (2, 1, 2));
+    assert_eq!(counts("C").0, 1);
+    assert!(!rows.iter().any(|row| row.language == "C Header"));
+}
+
+#[test]
+fn comment_markers_depend_on_the_language() {
+    let split = |rows: &[LanguageRow], name: &str| {
+        let row = row(rows, name);
+        (row.comment, row.code)
+    };
+
+    // the same lines under each language's markers
+    let text = "; semicolon\n# hash\n// slashes\n/* block */\n* star\n";
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", text)
+        .file("arch/riscv/a.S", text)
+        .file("arch/riscv/a.py", text)
+        .file("arch/riscv/Makefile", text)
+        .build();
+    let rows = kernel.count_code(&[]);
+    assert_eq!(split(&rows, "C"), (2, 3));
+    assert_eq!(split(&rows, "Assembly"), (4, 1));
+    assert_eq!(split(&rows, "Python"), (1, 4));
+    assert_eq!(split(&rows, "Makefile"), (1, 4));
+
+    // a block comment only spans lines where the language has one
+    let block = "/*\nx = 1\n*/\n";
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/b.c", block)
+        .file("arch/riscv/b.py", block)
+        .build();
+    let rows = kernel.count_code(&[]);
+    assert_eq!(split(&rows, "C"), (3, 0));
+    assert_eq!(split(&rows, "Python"), (0, 3));
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_files_are_reported_while_others_still_count() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/a.c", "int a;\n")
+        .build();
+    // a dangling symlink fails to open even when running as root
+    std::os::unix::fs::symlink(
+        kernel.root().join("missing.c"),
+        kernel.root().join("arch/riscv/broken.c"),
+    )
+    .unwrap();
+
+    let output = kernel.run(&["count"]);
+    assert!(output.success);
+    assert!(
+        output.stderr.contains("1 unreadable files"),
+        "{}",
+        output.stderr
+    );
+    assert!(
+        output.stderr.contains("broken.c: No such file"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(row(&common::language_rows(&output.stdout), "C").files, 1);
+
+    let strict = kernel.run(&["count", "--strict"]);
+    assert!(!strict.success);
+}
+
+#[test]
+fn header_guard_styles_are_tallied() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/once.h", "/* once */\n#pragma once\nint a;\n")
+        .file(
+            "arch/riscv/guard.h",
+            "#ifndef _GUARD_H\n#define _GUARD_H\nint b;\n#endif /* _GUARD_H */\n",
+        )
+        .file(
+            "arch/riscv/defined.h",
+            "# if !defined(_DEFINED_H)\n# define _DEFINED_H\nint c;\n# endif\n\n",
+        )
+        .file("arch/riscv/bare.h", "int d;\n")
+        // the guard does not cover the whole header
+        .file(
+            "arch/riscv/partial.h",
+            "#ifndef _PARTIAL_H\n#define _PARTIAL_H\n#endif\nint e;\n",
+        )
+        .build();
+    let stdout = kernel.run_ok(&["count", "--header-guards"]);
+
+    let tally = |label: &str| -> usize {
+        let line = stdout
+            .lines()
+            .find(|line| line.starts_with(label))
+            .unwrap_or_else(|| panic!("no {} row in\n{}", label, stdout));
+        line[label.len()..].trim().parse().expect("numeric column")
+    };
+    assert_eq!(tally("#pragma once"), 1);
+    assert_eq!(tally("#ifndef guard"), 2);
+    assert_eq!(tally("(none)"), 2);
+    assert!(stdout.lines().any(|line| line.ends_with("bare.h")));
+    assert!(stdout.lines().any(|line| line.ends_with("partial.h")));
+}
+
+#[test]
+fn lang_map_adds_named_languages() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/boot/dts/overlay.dtso",
+            "/* overlay */\n/ {\n};\n",
+        )
+        .file("arch/riscv/boot/dts/extra.dtso", "/ {\n};\n")
+        .file("arch/riscv/tools/gen.foo", "# generated\nfoo\n")
+        .file("arch/riscv/tools/gen.inc", "inc\n")
+        .file("arch/riscv/tools/gen.bar", "bar\n")
+        .build();
+    let rows = kernel.count_code(&["--lang-map", ".dtso=DeviceTree,foo=Foo,.inc=c"]);
+
+    let dts = row(&rows, "DeviceTree");
+    assert_eq!((dts.files, dts.comment, dts.code), (2, 1, 4));
+    assert_eq!(row(&rows, "Foo").files, 1);
+    // a built-in language name reuses that row instead of creating a new one
+    assert_eq!(row(&rows, "C").files, 1);
+    assert_eq!(row(&rows, "Other").files, 1);
+}
+
+#[test]
+fn relative_paths_are_shown_below_the_kernel_root() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file("arch/riscv/bare.h", "int d; /* TODO */\n")
+        .file("arch/riscv/todo.c", "// TODO\nint t;\n")
+        .build();
+    let root = kernel.root().to_str().unwrap();
+
+    let stdout = kernel.run_ok(&["--relative", "count", "-v", "--header-guards", "--todos"]);
+    assert!(
+        stdout.lines().any(|line| line == "arch/riscv/bare.h"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(" arch/riscv/kernel/smp.c"), "{}", stdout);
+    assert!(!stdout.contains(root), "{}", stdout);
+
+    // the count document names the same paths
+    let json = export(
+        &kernel,
+        &["--relative", "count", "--todos", "1", "--format", "json"],
+    );
+    let riscv = &json["arches"][0];
+    assert_eq!(riscv["todo_files"][0]["path"], "arch/riscv/todo.c");
+    assert_eq!(language(riscv, "C")["max_path"], "arch/riscv/kernel/smp.c");
+
+    let stdout = kernel.run_ok(&["analyze", "--relative", "--query", "SMP"]);
+    assert!(stdout.contains("Defined in: arch/riscv/"), "{}", stdout);
+    assert!(stdout.contains("  arch/riscv/kernel/smp.c:"), "{}", stdout);
+    assert!(!stdout.contains(root), "{}", stdout);
+
+    // without the flag the paths are printed as counted
+    let stdout = kernel.run_ok(&["analyze", "--query", "SMP"]);
+    assert!(stdout.contains(root), "{}", stdout);
+}
+
+#[test]
+fn binary_files_count_bytes_but_no_lines() {
+    let blob: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0int looks_like_code;\n// and a comment\n";
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/firmware/blob.bin", blob)
+        .file("arch/riscv/firmware/loader.c", "int load;\n")
+        .build();
+    let mut fc = FileCounter::new(
+        "riscv".into(),
+        "6.9.5".into(),
+        kernel.root().join("arch/riscv"),
+    );
+    fc.search().unwrap();
+
+    let binary = &fc.stats()[&FileType::TypeBinary];
+    assert_eq!(binary.files(), 1);
+    assert_eq!(binary.bytes(), blob.len() as u64);
+    assert_eq!((binary.blank(), binary.comment(), binary.code()), (0, 0, 0));
+    assert_eq!(binary.empty_files(), 0);
+    assert!(!fc.stats().contains_key(&FileType::TypeOther));
+    assert_eq!(fc.stats()[&FileType::TypeC].code(), 1);
+}
+
+#[test]
+fn include_docs_counts_documentation_as_text() {
+    let rst = "\
+.. SPDX-License-Identifier: GPL-2.0
+
+==============
+RISC-V support
+==============
+
+// not a comment in prose
+# nor is this
+";
+    let kernel = FakeKernelBuilder::riscv()
+        .file("Documentation/arch/riscv/boot.rst", rst)
+        .file("Documentation/arch/riscv/features.txt", "isa: rv64gc\n")
+        .file("arch/riscv/kernel/README.md", "# smp\n\nboot notes\n")
+        .build();
+
+    // documentation inside the arch tree is counted with it
+    let rows = kernel.count_code(&[]);
+    let arch = row(&rows, "Documentation");
+    assert_eq!(
+        (arch.files, arch.blank, arch.comment, arch.code),
+        (1, 1, 0, 2)
+    );
+
+    let stdout = kernel.run_ok(&["count", "--include-docs"]);
+    let (_, docs) = stdout
+        .split_once("Arch DOCS")
+        .expect("a documentation table");
+    let rows = language_rows(docs);
+    assert_eq!(rows.len(), 1, "{}", docs);
+    let text = row(&rows, "Documentation");
+    assert_eq!(
+        (text.files, text.blank, text.comment, text.code),
+        (2, 2, 0, 7)
+    );
+    let json = export(&kernel, &["count", "--include-docs", "--format", "json"]);
+    assert_eq!(json["docs"]["total"]["files"], 2);
+
+    let bare = FakeKernelBuilder::riscv().build();
+    let output = bare.run(&["count", "--include-docs"]);
+    assert!(!output.success);
+    assert!(output.stderr.contains("Documentation"), "{}", output.stderr);
+}
+
+#[test]
+fn file_health_counts_files_without_code() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/kernel/empty.c", "")
+        .file("arch/riscv/kernel/blank.c", "\n\n")
+        .file(
+            "arch/riscv/kernel/placeholder.c",
+            "/*\n * nothing yet\n */\n",
+        )
+        .file("arch/riscv/kernel/code.c", "// setup\nint a;\n")
+        .file("arch/riscv/tools/doc.py", "# only a comment\n")
+        .build();
+    let stdout = kernel.run_ok(&["count", "--file-health"]);
+
+    let health = |language: &str| -> Vec<usize> {
+        let line = stdout
+            .lines()
+            .rev()
+            .find(|line| line.starts_with(&format!("{} ", language)))
+            .unwrap_or_else(|| panic!("no {} row in\n{}", language, stdout));
+        line[language.len()..]
+            .split_whitespace()
+            .map(|column| column.parse().expect("numeric column"))
+            .collect()
+    };
+    assert_eq!(health("C"), [4, 2, 1]);
+    assert_eq!(health("Python"), [1, 0, 1]);
+    assert_eq!(health("SUM:"), [5, 2, 2]);
+}
+
+fn cached_scan(root: &Path, cache: ScanCache) -> (FileCounter, ScanCache) {
+    let mut fc = FileCounter::new("riscv".into(), "6.9.5".into(), root.join("arch/riscv"));
+    fc.set_cache(cache);
+    fc.search().expect("scan fixture");
+    let cache = fc.take_cache().expect("cache handed back");
+    (fc, cache)
+}
+
+#[test]
+fn cache_skips_reading_unchanged_files() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let root = kernel.root();
+
+    let (first, cache) = cached_scan(root, ScanCache::default());
+    assert_eq!(first.files_read(), 4);
+    let (second, cache) = cached_scan(root, cache);
+    assert_eq!(second.files_read(), 0);
+    assert_eq!(second.metrics(), first.metrics());
+
+    // a changed size invalidates the entry of that file only
+    std::fs::write(
+        root.join("arch/riscv/kernel/smp.h"),
+        "int grown;\nint more;\n",
+    )
+    .unwrap();
+    let (third, _) = cached_scan(root, cache);
+    assert_eq!(third.files_read(), 1);
+    assert_ne!(third.metrics(), first.metrics());
+}
+
+// raises `stop` once `after` files were opened, like a Ctrl-C in the middle of a scan
+#[derive(Debug)]
+struct StopAfter {
+    tree: MapTree,
+    after: usize,
+    reads: Cell<usize>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SourceTree for StopAfter {
+    fn read(&self, path: &Path) -> io::Result<Box<dyn BufRead + '_>> {
+        self.reads.set(self.reads.get() + 1);
+        if self.reads.get() == self.after {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        self.tree.read(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        self.tree.read_dir(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.tree.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.tree.is_dir(path)
+    }
+}
+
+#[test]
+fn a_raised_stop_flag_ends_the_scan_with_whole_files() {
+    let root = Path::new("/linux-6.9.5");
+    let stop = Arc::new(AtomicBool::new(false));
+    let tree = StopAfter {
+        tree: FakeKernelBuilder::empty()
+            .file("arch/riscv/a/one.c", "int one;\n\n// one\n")
+            .file("arch/riscv/b/two.c", "int two;\nint too;\n")
+            .file("arch/riscv/c/three.c", "int three;\n")
+            .file("arch/riscv/d/four.h", "int four;\n")
+            .tree(root),
+        after: 2,
+        reads: Cell::new(0),
+        stop: stop.clone(),
+    };
+    let mut fc = FileCounter::new("riscv".into(), "6.9.5".into(), root.join("arch/riscv"));
+    fc.set_sorted();
+    fc.set_source_tree(Rc::new(tree));
+    fc.set_stop_flag(stop);
+
+    let report = fc.search().unwrap();
+    assert!(report.interrupted);
+    // the second file is finished before the walker notices the flag
+    let c = &fc.stats()[&FileType::TypeC];
+    assert_eq!((c.files(), c.blank(), c.comment(), c.code()), (2, 1, 1, 3));
+    assert!(!fc.stats().contains_key(&FileType::TypeH));
+    assert_eq!(fc.metrics()["total.code"], 3);
+
+    let mut whole = FileCounter::new("riscv".into(), "6.9.5".into(), root.join("arch/riscv"));
+    whole.set_source_tree(Rc::new(
+        FakeKernelBuilder::empty()
+            .file("arch/riscv/a/one.c", "int one;\n")
+            .tree(root),
+    ));
+    assert!(!whole.search().unwrap().interrupted);
+}
+
+// a version mismatch is only logged as a warning
+#[test]
+fn merge_across_kernel_versions_still_sums() {
+    let root = Path::new("/kernel");
+    let counted = |version: &str, content: &str| {
+        let mut fc = FileCounter::new("riscv".into(), version.into(), root.join("arch/riscv"));
+        fc.set_source_tree(Rc::new(
+            FakeKernelBuilder::empty()
+                .file("arch/riscv/a.c", content)
+                .tree(root),
+        ));
+        fc.search().expect("scan in-memory tree");
+        fc
+    };
+    let mut old = counted("6.8.1", "int a;\n");
+    old.merge(&counted("6.9.5", "int a;\nint b;\n"));
+    assert_eq!(old.stats()[&FileType::TypeC].code(), 3);
+}
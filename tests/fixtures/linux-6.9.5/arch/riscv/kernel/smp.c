@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: GPL-2.0-only
+/*
+ * SMP support
+ */
+#include <linux/smp.h>
+
+#ifdef CONFIG_SMP
+int smp_cpus = 4;
+#ifdef CONFIG_MMU
+int mmu = 1;
+#endif
+#endif
+
+int main(void)
+{
+	return 0;
+}
+#ifdef CONFIG_FOO_DRV_MODULE
+int foo_mod;
+#endif
@@ -0,0 +1,7 @@
+/* SPDX-License-Identifier: (GPL-2.0 or MIT) */
+#ifndef _SMP_H
+#define _SMP_H
+#ifdef CONFIG_RISCV_ISA_C
+#define C 1
+#endif
+#endif
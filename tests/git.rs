@@ -0,0 +1,90 @@
+#![cfg(feature = "git")]
+
+mod common;
+
+use common::FakeKernelBuilder;
+use git2::{IndexAddOption, Repository, Signature};
+use std::fs;
+
+// commits the whole working tree and tags it
+fn commit_and_tag(repo: &Repository, tag: &str) {
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("auto_script", "auto_script@example.com").unwrap();
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<_> = parent.iter().collect();
+    let commit = repo
+        .commit(Some("HEAD"), &signature, &signature, tag, &tree, &parents)
+        .unwrap();
+    repo.tag_lightweight(tag, &repo.find_object(commit, None).unwrap(), false)
+        .unwrap();
+}
+
+#[test]
+fn git_rev_reads_the_tagged_tree_instead_of_the_worktree() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let repo = Repository::init(kernel.root()).unwrap();
+    commit_and_tag(&repo, "v6.9.5");
+
+    // the worktree moves on to another release without FOO_DRV and smp.c
+    let makefile = kernel.root().join("Makefile");
+    let bumped = fs::read_to_string(&makefile)
+        .unwrap()
+        .replace("SUBLEVEL = 5", "SUBLEVEL = 6");
+    fs::write(&makefile, bumped).unwrap();
+    fs::write(
+        kernel.root().join("arch/riscv/kernel/Kconfig"),
+        "config BAR_DRV\n\tbool\n",
+    )
+    .unwrap();
+    fs::remove_file(kernel.root().join("arch/riscv/kernel/smp.c")).unwrap();
+
+    let tagged = kernel.list_components(&["--git-rev", "v6.9.5"]);
+    assert!(
+        tagged.iter().any(|(name, _)| name == "FOO_DRV"),
+        "{:?}",
+        tagged
+    );
+    assert!(!tagged.iter().any(|(name, _)| name == "BAR_DRV"));
+    assert!(kernel
+        .list_components(&[])
+        .iter()
+        .any(|(name, _)| name == "BAR_DRV"));
+
+    let stdout = kernel.run_ok(&["count", "--git-rev", "v6.9.5"]);
+    assert!(stdout.contains("Linux-6.9.5 Arch RISCV"), "{}", stdout);
+    assert_eq!(common::row(&common::language_rows(&stdout), "C").files, 1);
+    assert!(kernel.run_ok(&["count"]).contains("Linux-6.9.6 Arch RISCV"));
+
+    let output = kernel.run(&["count", "--git-rev", "v7.0"]);
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains("failed to read revision \"v7.0\""),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn git_rev_reads_a_bare_repository() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let repo = Repository::init(kernel.root()).unwrap();
+    commit_and_tag(&repo, "v6.9.5");
+
+    let bare = kernel.root().with_file_name("linux.git");
+    git2::build::RepoBuilder::new()
+        .bare(true)
+        .clone(kernel.root().to_str().unwrap(), &bare)
+        .unwrap();
+    assert!(!bare.join("Makefile").exists());
+
+    let output = kernel.run_at(
+        &bare,
+        &["analyze", "--git-rev", "v6.9.5", "--query", "SMP"],
+        "",
+    );
+    assert!(output.success, "{}", output.stderr);
+    assert!(output.stdout.contains("smp.c:4:"), "{}", output.stdout);
+}
@@ -0,0 +1,254 @@
+#![cfg(feature = "cli")]
+
+use auto_script::core::interactive::execute;
+use auto_script::core::report::highlight_snippet;
+use auto_script::{KconfigComponentType, KconfigCounter};
+use std::path::PathBuf;
+
+const KCONFIG: &str = "\
+config SMP
+\tbool \"smp\"
+\tdepends on MMU
+
+config SMP_ON_UP
+\tbool
+\tdepends on SMP
+\tselect NR_CPUS
+
+config NR_CPUS
+\tint
+\tdefault 8 if SMP
+
+config MMU
+\tdef_bool y
+";
+
+const SOURCE: &str = "\
+#ifdef CONFIG_SMP
+int smp_boot(void);
+int smp_cpus;
+#endif
+#ifdef CONFIG_MMU
+int mmu_enabled;
+#endif
+";
+
+fn counter() -> KconfigCounter {
+    let kconfig = PathBuf::from("arch/riscv/Kconfig");
+    let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), kconfig.clone());
+    kc.parse_kconfig_reader(KCONFIG.as_bytes(), &kconfig)
+        .expect("parse in-memory Kconfig");
+    kc.parse_code_reader(SOURCE.as_bytes(), &PathBuf::from("arch/riscv/smp.c"))
+        .expect("parse in-memory source");
+    kc
+}
+
+fn answer(kc: &KconfigCounter, line: &str) -> String {
+    let mut out = Vec::new();
+    assert!(
+        execute(kc, line, &mut out).unwrap(),
+        "{} left the loop",
+        line
+    );
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn show_accepts_a_bare_name_or_the_command() {
+    let kc = counter();
+    assert_eq!(answer(&kc, "smp"), answer(&kc, "show CONFIG_SMP"));
+    assert!(answer(&kc, "SMP").starts_with("Component: SMP\n"));
+    assert_eq!(
+        answer(&kc, "SMPP"),
+        "Component 'SMPP' not found, did you mean SMP?\n"
+    );
+}
+
+#[test]
+fn list_matches_globs_and_substrings() {
+    let kc = counter();
+    assert_eq!(answer(&kc, "list smp*"), "SMP\nSMP_ON_UP\n2 components\n");
+    assert_eq!(answer(&kc, "list ?MU"), "MMU\n1 components\n");
+    assert_eq!(answer(&kc, "list CPU"), "NR_CPUS\n1 components\n");
+
+    // a lookup with wildcards lists instead of showing one component
+    assert_eq!(answer(&kc, "config_smp*"), answer(&kc, "list smp*"));
+    assert_eq!(answer(&kc, "show ?MU"), "MMU\n1 components\n");
+}
+
+#[test]
+fn deps_and_rdeps_follow_the_graph() {
+    let kc = counter();
+    let deps = answer(&kc, "deps SMP_ON_UP");
+    assert!(
+        deps.contains("NR_CPUS") && deps.contains("selects"),
+        "{}",
+        deps
+    );
+    assert!(
+        deps.ends_with("SMP_ON_UP references 2 symbols\n"),
+        "{}",
+        deps
+    );
+
+    let rdeps = answer(&kc, "rdeps smp");
+    assert!(rdeps.contains("SMP_ON_UP") && rdeps.contains("NR_CPUS"));
+    assert!(rdeps.ends_with("2 components reference SMP\n"), "{}", rdeps);
+}
+
+#[test]
+fn top_and_grep_read_the_guarded_code() {
+    let kc = counter();
+    let top = answer(&kc, "top 1");
+    assert!(
+        top.starts_with("SMP ") && top.trim_end().ends_with('3'),
+        "{}",
+        top
+    );
+    assert_eq!(top.lines().count(), 1);
+
+    let grep = answer(&kc, "grep int (smp|mmu)_");
+    assert_eq!(
+        grep,
+        "MMU arch/riscv/smp.c: int mmu_enabled;\n\
+         SMP arch/riscv/smp.c: int smp_boot(void);\n\
+         SMP arch/riscv/smp.c: int smp_cpus;\n\
+         3 matching lines\n"
+    );
+}
+
+#[test]
+fn export_writes_the_detail_view() {
+    let kc = counter();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("smp.txt");
+    let reply = answer(&kc, &format!("export smp {}", path.display()));
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written, answer(&kc, "SMP"));
+    assert!(reply.starts_with(&format!("wrote {} bytes", written.len())));
+}
+
+#[test]
+fn save_refuses_to_overwrite_without_a_bang() {
+    let kc = counter();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("smp.txt");
+    let path = path.to_str().unwrap();
+
+    let detail = answer(&kc, "SMP");
+    assert_eq!(
+        answer(&kc, &format!("save config_smp {}", path)),
+        format!("wrote {} bytes (1 snippets) to {}\n", detail.len(), path)
+    );
+    assert_eq!(std::fs::read_to_string(path).unwrap(), detail);
+
+    assert_eq!(
+        answer(&kc, &format!("save MMU {}", path)),
+        format!("{} already exists, use `save!` to overwrite it\n", path)
+    );
+    assert_eq!(std::fs::read_to_string(path).unwrap(), detail);
+
+    assert!(answer(&kc, &format!("save! MMU {}", path)).starts_with("wrote "));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), answer(&kc, "MMU"));
+    assert_eq!(answer(&kc, "save MMU"), "usage: save[!] NAME FILE\n");
+}
+
+#[test]
+fn footprint_sums_up_what_a_symbol_adds() {
+    let mut kc = counter();
+    let makefile = "\
+obj-$(CONFIG_SMP) += smp.o smpboot.o
+obj-$(CONFIG_SMP_ON_UP) += \\
+\tup.o \\
+\tsmp.o
+# obj-$(CONFIG_SMP) += commented.o
+ifdef CONFIG_SMP
+ccflags-y += -DSMP
+endif
+";
+    kc.parse_makefile_reader(makefile.as_bytes(), &PathBuf::from("arch/riscv/Makefile"))
+        .expect("parse in-memory Makefile");
+
+    let smp = kc.footprint("config_smp").unwrap();
+    assert_eq!(smp.name, "SMP");
+    assert_eq!(smp.value_type, KconfigComponentType::Bool);
+    assert_eq!(smp.depends, "MMU");
+    assert!(smp.selects.is_empty());
+    assert_eq!((smp.code_lines, smp.snippets, smp.files), (3, 1, 1));
+    assert_eq!(smp.objects, ["arch/riscv/smp.o", "arch/riscv/smpboot.o"]);
+
+    let up = kc.footprint("SMP_ON_UP").unwrap();
+    assert_eq!(
+        (up.depends.as_str(), up.selects.as_slice()),
+        ("SMP", &["NR_CPUS".to_string()][..])
+    );
+    assert_eq!(up.code_lines, 0);
+    assert_eq!(up.objects, ["arch/riscv/up.o", "arch/riscv/smp.o"]);
+    assert!(kc.footprint("NO_SUCH").is_none());
+
+    assert_eq!(
+        answer(&kc, "footprint smp"),
+        "Footprint: SMP
+  Value Type: boolean
+  Depends on: MMU
+  Selects: <none>
+  Guarded code: 3 lines in 1 snippets, 1 files
+  Objects: arch/riscv/smp.o, arch/riscv/smpboot.o
+"
+    );
+    assert!(answer(&kc, "footprint SMPP").contains("did you mean SMP"));
+    assert_eq!(answer(&kc, "footprint"), "usage: footprint NAME\n");
+}
+
+#[test]
+fn bad_input_prints_usage_and_keeps_going() {
+    let kc = counter();
+    assert_eq!(answer(&kc, "deps"), "usage: deps NAME\n");
+    assert_eq!(answer(&kc, "top -1"), "usage: top N\n");
+    assert!(answer(&kc, "grep (").starts_with("invalid pattern"));
+    assert_eq!(
+        answer(&kc, "frobnicate SMP"),
+        "unknown command 'frobnicate'\n"
+    );
+    assert!(answer(&kc, "help").contains("rdeps NAME"));
+    assert!(!execute(&kc, "quit", &mut Vec::new()).unwrap());
+}
+
+#[test]
+fn styled_detail_view_highlights_snippet_headers() {
+    let kc = counter();
+    let mut styled = Vec::new();
+    assert!(kc.write_component("SMP", &mut styled, true).unwrap());
+    let styled = String::from_utf8(styled).unwrap();
+    assert!(
+        styled.contains("\x1b[1;36m  arch/riscv/smp.c:1:\x1b[0m\n"),
+        "{}",
+        styled
+    );
+
+    // what `--query` and `export` write stays plain
+    assert!(!answer(&kc, "SMP").contains('\x1b'));
+}
+
+#[test]
+fn highlighting_marks_the_owning_symbol_comments_and_keywords() {
+    let snippet = "#if defined(CONFIG_SMP) && !CONFIG_MMU\n\
+                   static int cpus; /* boot\n\
+                   cpus */ char *s = \"if // no\";\n";
+    assert_eq!(
+        highlight_snippet(snippet, "SMP"),
+        "\x1b[35m#if defined(\x1b[1;33mCONFIG_SMP\x1b[0m\x1b[35m) && !CONFIG_MMU\x1b[0m\n\
+         \x1b[2mstatic\x1b[0m \x1b[2mint\x1b[0m cpus; \x1b[32m/* boot\x1b[0m\n\
+         \x1b[32mcpus */\x1b[0m \x1b[2mchar\x1b[0m *s = \"if // no\";\n"
+    );
+
+    // the detail view only colors on request
+    let kc = counter();
+    let mut styled = Vec::new();
+    kc.write_component("SMP", &mut styled, true).unwrap();
+    assert!(String::from_utf8(styled)
+        .unwrap()
+        .contains("\x1b[1;33mCONFIG_SMP\x1b[0m"));
+    assert!(!answer(&kc, "show SMP").contains('\x1b'));
+}
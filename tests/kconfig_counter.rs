@@ -0,0 +1,1229 @@
+mod common;
+
+use auto_script::core::source::FsTree;
+use auto_script::core::version::fetch_kernel_release;
+use common::{FakeKernel, FakeKernelBuilder};
+use std::collections::HashSet;
+
+#[test]
+fn parses_components_through_source() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let components = kernel.list_components(&[]);
+
+    assert!(components.contains(&("SMP".to_string(), "boolean".to_string())));
+    assert!(components.contains(&("NR_CPUS".to_string(), "integer".to_string())));
+    // sourced from arch/riscv/kernel/Kconfig
+    assert!(components.contains(&("FOO_DRV".to_string(), "tristate".to_string())));
+    // init/ is not followed without --full
+    assert!(!components.iter().any(|(name, _)| name == "INIT_ONLY"));
+
+    let full = kernel.list_components(&["--full"]);
+    assert!(full.iter().any(|(name, _)| name == "INIT_ONLY"));
+}
+
+#[test]
+fn no_recurse_source_parses_the_top_kconfig_only() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let arch = kernel.root().join("arch/riscv");
+    let mut kc = auto_script::KconfigCounter::builder()
+        .arch("riscv")
+        .version(common::VERSION)
+        .kconfig_path(arch.join("Kconfig"))
+        .check_all(true)
+        .recurse_source(false)
+        .build()
+        .expect("all required options are set");
+    kc.parse_kconfig().expect("parse Kconfig");
+
+    let mut names: Vec<&str> = kc.components().map(|(name, _)| name).collect();
+    names.sort();
+    assert_eq!(names, ["MMU", "NR_CPUS", "RISCV", "SMP"]);
+    assert_eq!(kc.code_dirs().count(), 0);
+    assert_eq!(
+        kc.skipped_sources(),
+        [
+            kernel.root().join("arch/riscv/kernel/Kconfig"),
+            kernel.root().join("init/Kconfig")
+        ]
+    );
+
+    let shallow = kernel.list_components(&["--no-recurse-source"]);
+    assert_eq!(shallow.len(), 4);
+    assert!(!shallow.iter().any(|(name, _)| name == "FOO_DRV"));
+}
+
+#[test]
+fn list_components_prints_one_valid_symbol_per_line() {
+    let kernel = FakeKernelBuilder::riscv()
+        // a symbol defined twice is still listed once
+        .file("init/Kconfig", "config SMP\n\tbool\n")
+        .build();
+    let stdout = kernel.run_ok(&["kconfig", "--list-components", "--full"]);
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    let unique: HashSet<&str> = lines.iter().copied().collect();
+    assert_eq!(lines.len(), unique.len());
+    assert_eq!(lines.len(), 5);
+    for line in lines {
+        assert!(
+            !line.is_empty()
+                && line
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'),
+            "{:?} is not a symbol name",
+            line
+        );
+    }
+}
+
+#[test]
+fn kconfig_code_collects_guarded_lines() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let stdout = kernel.run_ok(&["analyze"]);
+
+    let total = stdout
+        .lines()
+        .find(|line| line.contains("Total Code Lines"))
+        .expect("total code lines row");
+    let lines: usize = total
+        .split_whitespace()
+        .find_map(|word| word.parse().ok())
+        .expect("numeric total");
+    assert!(lines > 0, "{}", total);
+}
+
+const ARCH_KCONFIG: &str = "\
+config RISCV
+\tdef_bool y
+
+config MMU
+\tbool \"MMU-based Paged Memory Management Support\"
+
+config SMP
+\tbool \"Symmetric Multi-Processing\"
+
+config NR_CPUS
+\tint \"Maximum number of CPUs (2-512)\"
+
+source \"arch/riscv/kernel/Kconfig\"
+";
+
+// group headers as `[..]`, then the names of each two-column row left to right
+fn listing(kernel: &FakeKernel, args: &[&str]) -> Vec<String> {
+    let stdout = kernel.run_ok(args);
+    let rows = stdout
+        .lines()
+        .skip_while(|line| !line.contains("Component"))
+        .skip(2)
+        .take_while(|line| !line.starts_with('-'));
+    rows.flat_map(|row| match row.starts_with('[') {
+        true => vec![row.to_string()],
+        false => row
+            .split('|')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect(),
+    })
+    .collect()
+}
+
+#[test]
+fn print_sorts_components_and_groups_them_on_request() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\nconfig ZETA\n\tbool\n\nconfig ALPHA\n\tbool\n\nconfig MAX\n\tbool\n",
+        )
+        .build();
+
+    assert_eq!(
+        listing(&kernel, &["kconfig"]),
+        ["ALPHA", "FOO_DRV", "MAX", "MMU", "NR_CPUS", "RISCV", "SMP", "ZETA"]
+    );
+    assert_eq!(
+        listing(&kernel, &["kconfig", "--group-by", "letter"]),
+        [
+            "[A]", "ALPHA", "[F]", "FOO_DRV", "[M]", "MAX", "MMU", "[N]", "NR_CPUS", "[R]",
+            "RISCV", "[S]", "SMP", "[Z]", "ZETA"
+        ]
+    );
+    assert_eq!(
+        listing(&kernel, &["kconfig", "--group-by", "file"]),
+        [
+            "[arch/riscv/Kconfig]",
+            "MMU",
+            "NR_CPUS",
+            "RISCV",
+            "SMP",
+            "[arch/riscv/kernel/Kconfig]",
+            "ALPHA",
+            "FOO_DRV",
+            "MAX",
+            "ZETA"
+        ]
+    );
+}
+
+// the `SUM:` row of the code lines below the component table
+fn total_code_lines(stdout: &str) -> usize {
+    let row = stdout
+        .lines()
+        .find(|line| line.ends_with("Total Code Lines"))
+        .expect("code lines row");
+    row.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+#[test]
+fn negated_guards_are_ignored_like_ifndef() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/Kconfig",
+            "config OUTER\n\tbool\n\nconfig INNER\n\tbool\n\nsource \"arch/riscv/kernel/Kconfig\"\n",
+        )
+        .file("arch/riscv/kernel/Kconfig", "")
+        .file(
+            "arch/riscv/kernel/negated.c",
+            "#if !defined(CONFIG_OUTER)\nint a;\n#endif\n#if !IS_ENABLED(CONFIG_OUTER)\nint b;\n#endif\n#if ! defined (CONFIG_OUTER)\nint c;\n#endif\n#ifndef CONFIG_OUTER\nint d;\n#endif\n",
+        )
+        .file(
+            "arch/riscv/kernel/nested.c",
+            "#if !IS_ENABLED(CONFIG_OUTER)\n#if defined(CONFIG_INNER)\nint e;\n#endif\n#endif\n",
+        )
+        .build();
+
+    let output = kernel.run_with_input(&["analyze"], "OUTER\nINNER\nq\n");
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    // only the `CONFIG_INNER` block below the negated guard counts
+    assert_eq!(total_code_lines(&stdout), 2, "{}", stdout);
+    let (outer, inner) = stdout.split_once("Component: INNER").unwrap();
+    assert!(!outer.contains("int "), "{}", outer);
+    assert!(inner.contains("int e;"), "{}", inner);
+}
+
+#[test]
+fn fail_on_empty_names_the_empty_analysis_and_arch() {
+    let empty = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", "# nothing configurable\n")
+        .build();
+    let populated = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file("arch/riscv/kernel/Kconfig", "")
+        .build();
+    let output = empty.run(&["kconfig", "--fail-on-empty"]);
+    assert!(!output.success);
+    let message = output.stderr;
+    assert!(
+        message.contains("kconfig analysis of arch \"riscv\" found no components"),
+        "{}",
+        message
+    );
+    // without the flag an empty table is not an error
+    assert!(empty.run(&["kconfig"]).success);
+    assert!(populated.run(&["kconfig", "--fail-on-empty"]).success);
+
+    std::fs::create_dir(empty.root().join("arch/x86")).unwrap();
+    let output = empty.run(&["count", "--fail-on-empty", "--arch", "riscv,x86"]);
+    assert!(!output.success);
+    let message = output.stderr;
+    assert!(
+        message.contains("code analysis of arch \"x86\" counted no files"),
+        "{}",
+        message
+    );
+    assert!(populated.run(&["count", "--fail-on-empty"]).success);
+}
+
+#[test]
+fn kconfig_types_display_readable_labels() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/Kconfig",
+            "config BOOL_SYM\n\tbool \"a bool\"\n\nconfig DEF_BOOL_SYM\n\tdef_bool y\n\nconfig TRI_SYM\n\ttristate \"a tristate\"\n\nconfig DEF_TRI_SYM\n\tdef_tristate m\n\nconfig INT_SYM\n\tint \"an int\"\n\nconfig HEX_SYM\n\thex \"a hex\"\n\nconfig STRING_SYM\n\tstring \"a string\"\n\nconfig UNTYPED_SYM\n\tdefault y\n",
+        )
+        .build();
+    let labels = [
+        ("BOOL_SYM", "boolean"),
+        ("DEF_BOOL_SYM", "boolean"),
+        ("TRI_SYM", "tristate"),
+        ("DEF_TRI_SYM", "tristate"),
+        ("INT_SYM", "integer"),
+        ("HEX_SYM", "hex"),
+        ("STRING_SYM", "string"),
+        ("UNTYPED_SYM", "unknown"),
+    ];
+    let input: String = labels
+        .iter()
+        .map(|(name, _)| format!("{}\n", name))
+        .collect();
+
+    let output = kernel.run_with_input(&["kconfig"], &format!("{}q\n", input));
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    for (name, label) in labels {
+        let detail = stdout
+            .split_once(&format!("Component: {}\n", name))
+            .unwrap_or_else(|| panic!("no details of {} in {}", name, stdout))
+            .1;
+        let detail = detail.split("\nComponent: ").next().unwrap();
+        assert!(
+            detail.contains(&format!("  Value Type: {}\n", label)),
+            "{}: {}",
+            name,
+            detail
+        );
+    }
+}
+
+#[test]
+fn depends_lines_combine_into_one_expression() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/Kconfig",
+            "config OR_SYM\n\tbool\n\tdepends on A ||   B\n\tdepends on C\n\tdepends on C\n\nconfig ONE_SYM\n\tbool\n\tdepends on A || B\n\nconfig FREE_SYM\n\tbool\n",
+        )
+        .build();
+    let output = kernel.run_with_input(&["kconfig"], "OR_SYM\nONE_SYM\nFREE_SYM\nq\n");
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    let effective: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("  Effective depends:"))
+        .map(str::trim)
+        .collect();
+
+    // alternatives are grouped, whitespace is normalized and repeats dropped
+    assert_eq!(effective, ["(A || B) && C", "A || B", ""]);
+}
+
+#[test]
+fn source_include_follows_only_the_allowed_prefixes() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\nsource \"init/Kconfig\"\nsource \"kernel/Kconfig.smp\"\nsource \"drivers/Kconfig\"\nsource \"mm/Kconfig\"\n",
+        )
+        .file("init/Kconfig", "config INIT_ONLY\n\tbool\n")
+        .file("kernel/Kconfig.smp", "config KERNEL_ONLY\n\tbool\n")
+        .file("drivers/Kconfig", "config DRIVERS_ONLY\n\tbool\n")
+        .file("mm/Kconfig", "config MM_ONLY\n\tbool\n")
+        .build();
+    let names = |args: &[&str]| -> Vec<String> {
+        let args: Vec<&str> = ["kconfig"].iter().chain(args).copied().collect();
+        listing(&kernel, &args)
+    };
+    let outside = |args: &[&str]| -> Vec<String> {
+        names(args)
+            .into_iter()
+            .filter(|name| name.ends_with("_ONLY"))
+            .collect()
+    };
+
+    assert!(outside(&[]).is_empty());
+    assert_eq!(
+        outside(&["--source-include", "arch/,init/,kernel/"]),
+        ["INIT_ONLY", "KERNEL_ONLY"]
+    );
+    assert_eq!(outside(&["--source-include", "arch/,mm/"]), ["MM_ONLY"]);
+    // the arch Kconfig itself is always read, the files it sources need a match too
+    let drivers = names(&["--source-include", "drivers/"]);
+    assert!(drivers.iter().any(|name| name == "SMP"));
+    assert!(!drivers
+        .iter()
+        .any(|name| name == "FOO_DRV" || name == "DRIVERS_ONLY"));
+    assert_eq!(
+        outside(&["--full"]),
+        ["DRIVERS_ONLY", "INIT_ONLY", "KERNEL_ONLY", "MM_ONLY"]
+    );
+}
+
+#[test]
+fn repeated_runs_print_byte_identical_output() {
+    // equal line counts and many symbols leave the order to the tie-breaks
+    let mut extra = String::new();
+    for name in [
+        "ZETA", "ALPHA", "MU", "BETA", "OMEGA", "DELTA", "KAPPA", "GAMMA",
+    ] {
+        extra.push_str(&format!("config {}\n\tbool \"{}\"\n\n", name, name));
+    }
+    let mut builder = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "source \"arch/riscv/extra/Kconfig\"\n",
+        )
+        .file("arch/riscv/extra/Kconfig", extra);
+    for ext in ["c", "h", "S", "py", "sh", "rs", "pl", "awk"] {
+        builder = builder.file(
+            format!("arch/riscv/extra/file.{}", ext),
+            "#ifdef CONFIG_MU\na\n#endif\n",
+        );
+    }
+    let kernel = builder.build();
+
+    let runs: [&[&str]; 4] = [
+        &["count", "-v", "--spdx"],
+        &["count", "--format", "json"],
+        &["kconfig", "--group-by", "letter"],
+        &["analyze"],
+    ];
+    for args in runs {
+        let first = kernel.run_with_input(args, "MU\nq\n");
+        let second = kernel.run_with_input(args, "MU\nq\n");
+        assert!(first.success, "{:?}: {}", args, first.stderr);
+        assert!(!first.stdout.is_empty(), "{:?}", args);
+        assert_eq!(first.stdout, second.stdout, "{:?}", args);
+    }
+}
+
+#[test]
+fn type_histogram_counts_every_value_type() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\nsource \"init/Kconfig\"\n",
+        )
+        .file(
+            "init/Kconfig",
+            "config INIT_ONLY\n\tbool\n\nconfig PAGE_OFFSET\n\thex\n\tdefault 0xc0000000\n\nconfig CMDLINE\n\tstring \"Built-in kernel command line\"\n",
+        )
+        .build();
+    let output = kernel.run(&["kconfig", "--full"]);
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    let histogram: Vec<(String, usize)> = stdout
+        .lines()
+        .skip_while(|line| !line.contains("Type"))
+        .skip(2)
+        .take_while(|line| !line.starts_with('-'))
+        .map(|line| {
+            let (label, count) = line.trim().rsplit_once(' ').unwrap();
+            (label.trim().to_string(), count.parse().unwrap())
+        })
+        .collect();
+
+    // RISCV through def_bool, MMU, SMP and INIT_ONLY
+    assert_eq!(
+        histogram,
+        [
+            ("boolean".to_string(), 4),
+            ("tristate".to_string(), 1),
+            ("integer".to_string(), 1),
+            ("hex".to_string(), 1),
+            ("string".to_string(), 1),
+            ("unknown".to_string(), 0),
+        ]
+    );
+}
+
+#[test]
+fn code_dirs_override_the_sourced_directories() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file("arch/riscv/kernel/Kconfig", "config FOO_DRV\n\ttristate \"foo driver\"\n")
+        .file("arch/riscv/kernel/smp.c", "#ifdef CONFIG_SMP\nint smp_boot;\n#endif\n")
+        .file(
+            "drivers/foo/foo.c",
+            "#ifdef CONFIG_FOO_DRV\nint foo;\n#endif\n#if IS_ENABLED(CONFIG_SMP)\nint smp;\nint cpus;\n#endif\n",
+        )
+        .file("drivers/foo/sub/bar.c", "#ifdef CONFIG_MMU\nint mmu;\n#endif\n")
+        .build();
+
+    // the nested directory is walked once, through its parent
+    let output = kernel.run_with_input(
+        &["analyze", "--code-dir", "drivers/foo,drivers/foo/sub"],
+        "SMP\nq\n",
+    );
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    // arch/riscv/kernel is not walked when the directories are given
+    assert_eq!(total_code_lines(&stdout), 7, "{}", stdout);
+    let smp = stdout.split_once("Component: SMP").unwrap().1;
+    assert!(smp.contains("int cpus;"), "{}", smp);
+    assert!(!smp.contains("smp_boot"), "{}", smp);
+
+    let output = kernel.run(&["analyze"]);
+    let stdout = output.stdout;
+    assert_eq!(total_code_lines(&stdout), 2, "{}", stdout);
+}
+
+#[test]
+fn choices_record_their_default_and_members() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/riscv/Kconfig",
+            "choice\n\tprompt \"CPU type\"\n\tdefault CPU_B\n\nconfig CPU_A\n\tbool \"a\"\n\nconfig CPU_B\n\tbool \"b\"\n\nconfig CPU_C\n\tbool \"c\"\n\nendchoice\n\nchoice\n\tprompt \"Endianness\"\n\nconfig LITTLE\n\tbool \"little\"\n\nconfig BIG\n\tbool \"big\"\n\nendchoice\n\nconfig AFTER\n\tbool \"after\"\n",
+        )
+        .build();
+    let output = kernel.run_with_input(&["kconfig"], "CPU_A\nCPU_B\nCPU_C\nBIG\nAFTER\nq\n");
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    // the details follow the prompt on the same line
+    let choices: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| match line.find("Component: ") {
+            Some(start) => Some(&line[start..]),
+            None => line.starts_with("  Choice:").then_some(line),
+        })
+        .collect();
+
+    // every member links back to the same block, without a `default` the first
+    // member is selected
+    let cpu = "  Choice: CPU type (default: CPU_B, members: CPU_A, CPU_B, CPU_C)";
+    assert_eq!(
+        choices,
+        [
+            "Component: CPU_A",
+            cpu,
+            "Component: CPU_B",
+            cpu,
+            "Component: CPU_C",
+            cpu,
+            "Component: BIG",
+            "  Choice: Endianness (default: LITTLE, members: LITTLE, BIG)",
+            "Component: AFTER",
+        ]
+    );
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.contains("SUM:") && line.ends_with(" 2 Choices")),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn a_leading_bom_does_not_hide_the_first_entry() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("Makefile", "\u{feff}VERSION = 6\nPATCHLEVEL = 9\nSUBLEVEL = 5\nEXTRAVERSION =\n")
+        .file(
+            "arch/riscv/Kconfig",
+            "\u{feff}config FIRST\n\tbool \"first\"\n\nconfig SECOND\n\tint\n\nsource \"arch/riscv/kernel/Kconfig\"\n",
+        )
+        .file("arch/riscv/kernel/Kconfig", "\u{feff}config OUTER\n\tbool\n")
+        .file("arch/riscv/kernel/guard.c", "\u{feff}#ifdef CONFIG_OUTER\nint a;\n#endif\n")
+        .file("arch/riscv/kernel/spdx.c", "\u{feff}// SPDX-License-Identifier: GPL-2.0\nint a;\n")
+        .file("arch/riscv/kernel/script", "\u{feff}#!/bin/sh\necho hi\n")
+        .build();
+
+    let output = kernel.run_with_input(&["analyze"], "FIRST\nq\n");
+    assert!(output.success, "{}", output.stderr);
+    let stdout = output.stdout;
+    assert!(stdout.contains("Linux-6.9.5 Arch RISCV"), "{}", stdout);
+    let detail = stdout
+        .split_once("Component: FIRST\n")
+        .expect("details of FIRST")
+        .1;
+    assert!(detail.contains("  Value Type: boolean\n"), "{}", stdout);
+    assert_eq!(total_code_lines(&stdout), 2, "{}", stdout);
+
+    let export: serde_json::Value =
+        serde_json::from_str(&kernel.run_ok(&["count", "--format", "json"])).unwrap();
+    assert_eq!(
+        export["arches"][0]["spdx"],
+        serde_json::json!({"GPL-2.0": 1})
+    );
+    let shell = export["arches"][0]["languages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|row| row["language"] == "Shell")
+        .expect("the script is classified by its shebang");
+    assert_eq!((&shell["comment"], &shell["code"]), (&1.into(), &1.into()));
+}
+
+#[test]
+fn list_types_appends_a_tab_separated_type_column() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/Kconfig", ARCH_KCONFIG)
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n",
+        )
+        .build();
+    // commands on stdin are never read, the dump exits without the prompt
+    let output = kernel.run_with_input(
+        &["kconfig", "--list-components", "--list-types"],
+        "SMP\nq\n",
+    );
+    assert!(output.success, "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        "FOO_DRV\ttristate\nMMU\tboolean\nNR_CPUS\tinteger\nRISCV\tboolean\nSMP\tboolean\n"
+    );
+}
+
+#[test]
+fn sorted_traversal_visits_files_by_name() {
+    let mut builder = FakeKernelBuilder::riscv();
+    for name in ["c", "a", "d", "b"] {
+        builder = builder.file(
+            format!("arch/riscv/kernel/{}.c", name),
+            format!("#ifdef CONFIG_MMU\nint from_{};\n#endif\n", name),
+        );
+    }
+    let kernel = builder.build();
+    let output = kernel.run_with_input(&["analyze", "--sorted"], "MMU\nq\n");
+    assert!(output.success, "{}", output.stderr);
+
+    let visited: Vec<&str> = output
+        .stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("int from_"))
+        .collect();
+    assert_eq!(visited, ["a;", "b;", "c;", "d;"]);
+}
+
+#[test]
+fn default_references_are_graph_edges() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\tdepends on MMU\n\nconfig A\n\tbool\n\tdefault B\n\nconfig B\n\tbool \"b\"\n\nconfig WIDE\n\tbool\n\tdepends on 64BIT && MMU\n\tdefault y if 64BIT\n",
+        )
+        .build();
+
+    let reverse = kernel.run_ok(&["kconfig", "--reverse-deps", "B"]);
+    assert!(
+        reverse
+            .lines()
+            .any(|line| line.starts_with("A ") && line.ends_with("defaults from")),
+        "{}",
+        reverse
+    );
+
+    let dot = kernel.run_ok(&["kconfig", "--dot"]);
+    assert!(dot.contains("\"A\" -> \"B\" [style=dotted"), "{}", dot);
+    assert!(
+        dot.contains("\"FOO_DRV\" -> \"MMU\" [style=solid]"),
+        "{}",
+        dot
+    );
+    // symbols starting with a digit are edges too, the `y` of a default is not
+    assert!(
+        dot.contains("\"WIDE\" -> \"64BIT\" [style=solid]"),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("\"WIDE\" -> \"64BIT\" [style=dotted"),
+        "{}",
+        dot
+    );
+    assert!(!dot.contains("\"WIDE\" -> \"y\""), "{}", dot);
+}
+
+#[test]
+fn code_delta_reports_guarded_line_changes() {
+    let old = FakeKernelBuilder::riscv().build();
+    let new = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\tdepends on MMU\n\nconfig BAR\n\tbool \"bar\"\n",
+        )
+        .file(
+            "arch/riscv/kernel/smp.c",
+            "#ifdef CONFIG_SMP\nint smp_boot(void)\n{\n\tint cpu = 0;\n\n\treturn cpu;\n}\n#endif\n\n#if IS_ENABLED(CONFIG_FOO_DRV_MODULE)\nint foo;\n#endif\n",
+        )
+        .build();
+
+    let stdout = old.run_ok(&[
+        "diff",
+        new.root().to_str().unwrap(),
+        "--code-delta",
+        "--format",
+        "json",
+    ]);
+    let delta: serde_json::Value = serde_json::from_str(&stdout).expect("json output");
+
+    // smp.c grows by two lines, smp.h is unchanged
+    let changed = delta["changed"].as_array().unwrap();
+    assert_eq!(changed.len(), 1, "{}", stdout);
+    assert_eq!(changed[0]["name"], "SMP");
+    assert_eq!(changed[0]["delta"], 2);
+    assert_eq!(delta["only_new"][0]["name"], "BAR");
+    assert_eq!(delta["only_old"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn arch_template_locates_non_standard_layouts() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "platforms/riscv/Kconfig",
+            "config RISCV\n\tdef_bool y\n\nsource \"platforms/riscv/kernel/Kconfig\"\n",
+        )
+        .file(
+            "platforms/riscv/kernel/Kconfig",
+            "config SMP\n\tbool \"smp\"\n",
+        )
+        .file(
+            "platforms/riscv/kernel/smp.c",
+            "#ifdef CONFIG_SMP\nint smp;\n#endif\n",
+        )
+        .build();
+    let layout = [
+        "--arch-template",
+        "platforms/{arch}",
+        "--source-include",
+        "platforms/",
+    ];
+
+    let components = kernel.list_components(&layout);
+    let names: Vec<&str> = components.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["RISCV", "SMP"]);
+
+    let rows = kernel.count_code(&layout[..2]);
+    assert_eq!(common::row(&rows, "C").files, 1);
+    assert_eq!(common::row(&rows, "kconfig").files, 2);
+
+    // the default template still points at arch/
+    assert!(!kernel.run(&["kconfig", "--list-components"]).success);
+}
+
+#[test]
+fn arch_names_map_to_their_srcarch_directory() {
+    let kernel = FakeKernelBuilder::empty()
+        .file(
+            "arch/x86/Kconfig",
+            "config X86\n\tdef_bool y\n\nsource \"arch/$(SRCARCH)/Kconfig.cpu\"\n",
+        )
+        .file("arch/x86/Kconfig.cpu", "config MCORE2\n\tbool \"Core 2\"\n")
+        .file("arch/x86/kernel/smp.c", "int smp;\n")
+        .file("arch/riscv/Kconfig", "config RISCV\n\tdef_bool y\n")
+        .build();
+
+    let components = kernel.list_components(&["--arch", "x86_64"]);
+    let names: Vec<&str> = components.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["MCORE2", "X86"]);
+
+    // the header keeps the spelling that was asked for
+    let stdout = kernel.run_ok(&["count", "--arch", "i386"]);
+    assert!(stdout.contains("Linux-6.9.5 Arch I386"), "{}", stdout);
+    assert_eq!(common::row(&common::language_rows(&stdout), "C").files, 1);
+
+    // unknown arches are still reported, with the directories they resemble
+    let output = kernel.run(&["kconfig", "--arch", "riscv64"]);
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains("arch \"riscv64\" not found")
+            && output.stderr.contains("did you mean riscv?"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn stdin_kconfig_is_parsed_without_the_arch_kconfig() {
+    let kernel = FakeKernelBuilder::empty().build();
+    let output = kernel.run_with_input(
+        &["kconfig", "--stdin", "--list-components", "--list-types"],
+        "config PIPED\n\ttristate \"piped\"\n",
+    );
+    assert!(output.success, "{}", output.stderr);
+    assert_eq!(output.stdout, "PIPED\ttristate\n");
+}
+
+#[test]
+fn kernel_directory_name_does_not_matter() {
+    let kernel = FakeKernelBuilder::riscv().dir_name("my-kernel").build();
+
+    // arch/riscv/kernel/Kconfig is sourced relative to the given root
+    let components = kernel.list_components(&[]);
+    assert!(components.contains(&("FOO_DRV".to_string(), "tristate".to_string())));
+
+    let query = kernel.run_ok(&["--relative", "analyze", "--query", "SMP"]);
+    assert!(
+        query.contains("  Defined in: arch/riscv/Kconfig\n"),
+        "{}",
+        query
+    );
+    assert!(query.contains("arch/riscv/kernel/smp.c"), "{}", query);
+    assert!(!query.contains("my-kernel"), "{}", query);
+
+    let rows = kernel.count_code(&[]);
+    assert_eq!(common::row(&rows, "kconfig").files, 2);
+}
+
+#[test]
+fn interactive_commands_answer_on_stdout() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let output = kernel.run_with_input(&["kconfig"], "SMP\nNO_SUCH\nhelp\n?\nq\n");
+    assert!(output.success, "{}", output.stderr);
+
+    let stdout = &output.stdout;
+    assert!(stdout.contains("Component: SMP"), "{}", stdout);
+    assert!(
+        stdout.contains("Component 'NO_SUCH' not found"),
+        "{}",
+        stdout
+    );
+    assert_eq!(stdout.matches("Available commands:").count(), 2);
+    assert!(!output.stderr.contains("NO_SUCH"), "{}", output.stderr);
+
+    // the end of stdin leaves the loop like `q` does
+    let output = kernel.run_with_input(&["kconfig"], "SMP\n");
+    assert!(output.success, "{}", output.stderr);
+}
+
+#[test]
+fn interactive_history_is_kept_on_request() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let history = kernel.root().with_file_name(".auto_script_history");
+
+    kernel.run_with_input(&["kconfig"], "SMP\nq\n");
+    assert!(!history.exists());
+
+    let output = kernel.run_with_input(&["kconfig", "--history"], "SMP\n\nMMU\n");
+    assert!(output.success, "{}", output.stderr);
+    let saved = std::fs::read_to_string(&history).unwrap();
+    let entries: Vec<&str> = saved
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    assert_eq!(entries, ["SMP", "MMU"]);
+}
+
+#[test]
+fn builder_applies_env_exclude_and_snippet_options() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/Kconfig",
+            "config SMP\n\tbool \"smp\"\n\nsource \"arch/$(SRCARCH)/kernel/Kconfig\"\nsource \"arch/riscv/mm/Kconfig\"\n",
+        )
+        .file("arch/riscv/mm/Kconfig", "config MM_ONLY\n\tbool \"mm\"\n")
+        .build();
+    let arch = kernel.root().join("arch/riscv");
+
+    let mut kc = auto_script::KconfigCounter::builder()
+        .arch("riscv")
+        .version(common::VERSION)
+        .kconfig_path(arch.join("Kconfig"))
+        .kconfig_env("SRCARCH", "riscv")
+        .exclude(arch.join("mm"))
+        .store_snippets(false)
+        .build()
+        .expect("all required options are set");
+    kc.parse_kconfig().expect("parse Kconfig");
+    kc.analyze_code().expect("analyze code");
+
+    let names: HashSet<&str> = kc.components().map(|(name, _)| name).collect();
+    assert!(names.contains("FOO_DRV"));
+    assert!(!names.contains("MM_ONLY"));
+    let (_, smp) = kc.components().find(|(name, _)| *name == "SMP").unwrap();
+    assert_eq!(smp.code_lines(), 7);
+    assert!(smp
+        .code_snippets()
+        .iter()
+        .all(|snippet| snippet.code().is_empty()));
+
+    let missing = auto_script::KconfigCounter::builder().arch("riscv").build();
+    assert!(missing.err().unwrap().to_string().contains("`version`"));
+}
+
+#[test]
+fn dangling_depends_are_reported() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\tdepends on MMU\n\nconfig OLD_DRV\n\tbool \"old\"\n\tdepends on !REMOVED_BUS || (SMP && GONE = \"y\")\n\nconfig WIDE_DRV\n\tbool \"wide\"\n\tdepends on 64BIT && MMU\n",
+        )
+        .build();
+    let stdout = kernel.run_ok(&["kconfig", "--dangling-depends"]);
+
+    let rows: Vec<(&str, &str)> = stdout
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .map(|(name, missing)| (name, missing.trim()))
+        .filter(|(name, _)| name.chars().all(|c| c.is_ascii_uppercase() || c == '_'))
+        .collect();
+    assert!(rows.contains(&("OLD_DRV", "REMOVED_BUS")), "{}", stdout);
+    assert!(rows.contains(&("OLD_DRV", "GONE")), "{}", stdout);
+    // symbols may start with a digit, only `y`/`m`/`n`, numbers and strings are constants
+    assert!(rows.contains(&("WIDE_DRV", "64BIT")), "{}", stdout);
+    assert!(!rows
+        .iter()
+        .any(|(_, missing)| *missing == "SMP" || *missing == "MMU"));
+    assert!(stdout.contains("3 Dependencies"), "{}", stdout);
+}
+
+#[test]
+fn top_snippets_rank_the_largest_guarded_blocks() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/mmu.c",
+            "#ifdef CONFIG_MMU\nint a;\nint b;\nint c;\nint d;\nint e;\nint f;\n#endif\n\n#ifdef CONFIG_NR_CPUS\nint n;\n#endif\n",
+        )
+        .build();
+    let stdout = kernel.run_ok(&["analyze", "--top-snippets", "2"]);
+
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|columns| columns.len() == 3 && columns[1].parse::<usize>().is_ok())
+        .collect();
+    assert_eq!(rows.len(), 2, "{}", stdout);
+    assert_eq!(rows[0], ["MMU", "7", "arch/riscv/kernel/mmu.c"]);
+    // smp.c guards five lines with CONFIG_SMP, smp.h only two
+    assert_eq!(rows[1], ["SMP", "5", "arch/riscv/kernel/smp.c"]);
+}
+
+#[test]
+fn help_text_is_tallied_apart_from_structural_lines() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/Kconfig",
+            "config SMP\n\tbool \"smp\"\n\thelp\n\t  Line one.\n\n\t  config IN_HELP is prose here.\n\t  # not a comment either\n\n# a real comment\nconfig MMU\n\tbool \"mmu\"\n\t---help---\n\t  mmu help\nsource \"arch/riscv/kernel/Kconfig\"\n",
+        )
+        .build();
+    let stdout = kernel.run_ok(&["kconfig"]);
+
+    let tally = |label: &str| -> usize {
+        let line = stdout
+            .lines()
+            .find(|line| line.trim_end().ends_with(label))
+            .unwrap_or_else(|| panic!("no {} line in\n{}", label, stdout));
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        columns[columns.len() - 3].parse().expect("numeric column")
+    };
+    assert_eq!(tally("Help Lines"), 4);
+    // 7 in arch/riscv/Kconfig, 3 in the sourced FOO_DRV Kconfig
+    assert_eq!(tally("Structural Lines"), 10);
+
+    let components = kernel.list_components(&[]);
+    assert!(!components.iter().any(|(name, _)| name == "IN_HELP"));
+}
+
+#[test]
+fn query_accepts_c_style_names_and_suggests_near_misses() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let stdout = kernel.run_ok(&["kconfig", "--query", "CONFIG_SMP,nr_cpus"]);
+    assert!(stdout.contains("Component: SMP"), "{}", stdout);
+    assert!(stdout.contains("Component: NR_CPUS"), "{}", stdout);
+
+    let output = kernel.run(&["kconfig", "--query", "CONFIG_SMQ"]);
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains("did you mean SMP?"),
+        "{}",
+        output.stderr
+    );
+
+    let output = kernel.run_with_input(&["kconfig"], "config_mmu\nMMUU\nq\n");
+    assert!(
+        output.stdout.contains("Component: MMU"),
+        "{}",
+        output.stdout
+    );
+    assert!(
+        output
+            .stdout
+            .contains("Component 'MMUU' not found, did you mean MMU?"),
+        "{}",
+        output.stdout
+    );
+}
+
+#[test]
+fn summary_only_skips_the_code_walk_and_the_prompt() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    // walking a missing code directory fails, so the plain run proves the walk is skipped
+    let args = ["analyze", "--code-dir", "no/such/dir"];
+    assert!(!kernel.run_with_input(&args, "q\n").success);
+
+    let output = kernel.run_with_input(&[&args[..], &["--summary-only"]].concat(), "SMP\nq\n");
+    assert!(output.success, "{}", output.stderr);
+    assert!(
+        output.stdout.contains("Total Code Lines"),
+        "{}",
+        output.stdout
+    );
+    assert!(
+        !output.stdout.contains("Component: SMP"),
+        "{}",
+        output.stdout
+    );
+}
+
+#[test]
+fn color_follows_the_color_option() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    // stdout is a pipe, `auto` leaves the tables and the snippets plain
+    for args in [&["analyze"][..], &["analyze", "--color", "never"]] {
+        let output = kernel.run_with_input(args, "SMP\nq\n");
+        assert!(output.success, "{}", output.stderr);
+        assert!(!output.stdout.contains('\x1b'), "{}", output.stdout);
+    }
+
+    let output = kernel.run_with_input(&["analyze", "--color", "always"], "SMP\nq\n");
+    assert!(output.success, "{}", output.stderr);
+    assert!(
+        output
+            .stdout
+            .lines()
+            .any(|line| line.starts_with("\x1b[1m") && line.contains("Linux-6.9.5")),
+        "{}",
+        output.stdout
+    );
+    assert!(
+        output.stdout.contains("\x1b[1;33mCONFIG_SMP\x1b[0m"),
+        "{}",
+        output.stdout
+    );
+}
+
+#[test]
+fn query_expands_wildcards_up_to_a_cap() {
+    let many: String = (0..60)
+        .map(|index| format!("config RISCV_ISA_EXT{}\n\tbool\n", index))
+        .collect();
+    let kernel = FakeKernelBuilder::riscv()
+        .file("arch/riscv/Kconfig.isa", &many)
+        .file(
+            "arch/riscv/Kconfig",
+            "source \"arch/riscv/Kconfig.isa\"\nconfig SMP\n\tbool\n",
+        )
+        .build();
+
+    let stdout = kernel.run_ok(&["kconfig", "--query", "CONFIG_RISCV_ISA_EXT5?,sm?"]);
+    let shown: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Component: "))
+        .collect();
+    let mut expected: Vec<String> = (50..60)
+        .map(|index| format!("RISCV_ISA_EXT{}", index))
+        .collect();
+    expected.push("SMP".into());
+    assert_eq!(shown, expected);
+
+    // stdin is not a terminal, a large expansion is refused instead of asking
+    let output = kernel.run(&["kconfig", "--query", "riscv_isa_*"]);
+    assert!(!output.success);
+    assert!(
+        output.stderr.contains("matches 60 components"),
+        "{}",
+        output.stderr
+    );
+    assert!(!output.stdout.contains("Component: "), "{}", output.stdout);
+
+    let output = kernel.run(&["kconfig", "--query", "NO_SUCH_*"]);
+    assert!(
+        output.stderr.contains("no component matches"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn localversion_files_and_config_decorate_the_version() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file("localversion-b", "-b\n")
+        .file("localversion-a", "-a\n")
+        .file("localversion-a~", "-backup\n")
+        .file("configs/custom.config", "CONFIG_LOCALVERSION=\"-cfg\"\n")
+        .build();
+
+    let version = fetch_kernel_release(&FsTree, kernel.root(), None).unwrap();
+    assert_eq!(version.to_string(), "6.9.5-a-b");
+    assert_eq!(version.plain(), "6.9.5");
+
+    let config = kernel.root().join("configs/custom.config");
+    let export = kernel.root().with_file_name("kconfig.json");
+    let stdout = kernel.run_ok(&[
+        "kconfig",
+        "--summary-only",
+        "--dotconfig",
+        config.to_str().unwrap(),
+        "--export-kconfig",
+        export.to_str().unwrap(),
+    ]);
+    assert!(
+        stdout.contains("Linux-6.9.5-a-b-cfg Arch RISCV"),
+        "{}",
+        stdout
+    );
+    let json = std::fs::read_to_string(&export).unwrap();
+    assert!(json.contains("\"6.9.5-a-b-cfg\""), "{}", json);
+}
+
+#[test]
+fn maintainers_own_the_components_they_cover() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "MAINTAINERS",
+            "RISC-V ARCHITECTURE\nM:\tPalmer Dabbelt <palmer@dabbelt.com>\nF:\tarch/riscv/\n\n\
+             RISC-V SMP\nM:\tSome One <some.one@example.com>\nF:\tarch/riscv/kernel/smp.c\n",
+        )
+        .build();
+
+    let export = kernel.root().with_file_name("kconfig.json");
+    let stdout = kernel.run_ok(&[
+        "analyze",
+        "--maintainers",
+        "--query",
+        "SMP",
+        "--export-kconfig",
+        export.to_str().unwrap(),
+    ]);
+    assert!(
+        stdout.contains(
+            "  Maintainers:\n    RISC-V SMP: Some One <some.one@example.com>\n    \
+             RISC-V ARCHITECTURE: Palmer Dabbelt <palmer@dabbelt.com>\n"
+        ),
+        "{}",
+        stdout
+    );
+    let json = std::fs::read_to_string(&export).unwrap();
+    assert!(json.contains("\"RISC-V SMP\""), "{}", json);
+
+    // without the flag neither the detail view nor the export mention owners
+    let stdout = kernel.run_ok(&[
+        "analyze",
+        "--query",
+        "SMP",
+        "--export-kconfig",
+        export.to_str().unwrap(),
+    ]);
+    assert!(!stdout.contains("Maintainers:"));
+    let json = std::fs::read_to_string(&export).unwrap();
+    assert!(!json.contains("maintainers"), "{}", json);
+
+    let missing = FakeKernelBuilder::riscv().build();
+    let output = missing.run(&["analyze", "--maintainers", "--summary-only"]);
+    assert!(!output.success);
+    assert!(output.stderr.contains("MAINTAINERS"), "{}", output.stderr);
+}
+
+#[test]
+fn by_dir_ranks_directories_by_their_components() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Makefile",
+            "obj-y += setup.o\nobj-$(CONFIG_SMP) += smpboot.o\nobj-$(CONFIG_FOO_DRV) += foo.o\n",
+        )
+        .file(
+            "arch/riscv/kernel/vdso/Makefile",
+            "obj-$(CONFIG_MMU) += vdso.o\n",
+        )
+        .file(
+            "arch/riscv/kernel/vdso/vdso.c",
+            "#ifdef CONFIG_NR_CPUS\nint cpus;\n#endif\n",
+        )
+        .build();
+
+    let mut kc = auto_script::KconfigCounter::builder()
+        .arch("riscv")
+        .version(common::VERSION)
+        .kconfig_path(kernel.root().join("arch/riscv/Kconfig"))
+        .build()
+        .unwrap();
+    kc.parse_kconfig().unwrap();
+    kc.analyze_code().unwrap();
+
+    let kernel_dir = std::path::PathBuf::from("arch/riscv/kernel");
+    let vdso = std::path::PathBuf::from("arch/riscv/kernel/vdso");
+    let dirs = kc.component_dirs();
+    assert_eq!(dirs["SMP"], [kernel_dir.clone()].into());
+    assert_eq!(dirs["MMU"], [vdso.clone()].into());
+    assert!(!dirs.contains_key("RISCV"));
+    let components = kc.dir_components();
+    assert_eq!(
+        components[&kernel_dir],
+        ["FOO_DRV", "SMP"].into_iter().collect()
+    );
+    assert_eq!(components[&vdso], ["MMU", "NR_CPUS"].into_iter().collect());
+
+    let report = kc.dir_report();
+    let rows: Vec<(&str, usize)> = report
+        .dirs
+        .iter()
+        .map(|row| (row.dir.as_str(), row.components.len()))
+        .collect();
+    assert_eq!(
+        rows,
+        [("arch/riscv/kernel", 2), ("arch/riscv/kernel/vdso", 2)]
+    );
+
+    let stdout = kernel.run_ok(&["analyze", "--by-dir"]);
+    assert!(stdout.contains("arch/riscv/kernel"), "{}", stdout);
+    assert!(stdout.contains("FOO_DRV, SMP"), "{}", stdout);
+
+    let export = kernel.root().with_file_name("kconfig.json");
+    kernel.run_ok(&[
+        "analyze",
+        "--list-components",
+        "--export-kconfig",
+        export.to_str().unwrap(),
+    ]);
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&export).unwrap()).unwrap();
+    assert_eq!(
+        json["arches"][0]["directories"]["arch/riscv/kernel/vdso"],
+        serde_json::json!(["MMU", "NR_CPUS"])
+    );
+}
+
+#[test]
+fn by_subsystem_splits_drivers_one_level_deeper() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "init/Kconfig",
+            "config INIT_ONLY\n\tbool\n\nsource \"drivers/net/Kconfig\"\nsource \"drivers/gpu/Kconfig\"\n",
+        )
+        .file(
+            "drivers/net/Kconfig",
+            "config NET_A\n\ttristate \"a\"\n\nconfig NET_B\n\tbool \"b\"\n",
+        )
+        .file("drivers/gpu/Kconfig", "config GPU\n\tbool \"gpu\"\n")
+        .file(
+            "drivers/net/a.c",
+            "#ifdef CONFIG_NET_A\nint a;\nint b;\n#endif\n",
+        )
+        .build();
+
+    let report = |args: &[&str]| -> serde_json::Value {
+        let args = [args, &["--full", "--by-subsystem", "--format", "json"]].concat();
+        serde_json::from_str(&kernel.run_ok(&args)).unwrap()
+    };
+    let json = report(&["kconfig"]);
+    let rows: Vec<(&str, u64)> = json["subsystems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| {
+            (
+                row["subsystem"].as_str().unwrap(),
+                row["components"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("arch", 5),
+            ("drivers/net", 2),
+            ("drivers/gpu", 1),
+            ("init", 1)
+        ]
+    );
+    let net = &json["subsystems"][1];
+    assert_eq!(net["types"][0], serde_json::json!(["bool", 1]));
+    assert_eq!(net["types"][1], serde_json::json!(["tristate", 1]));
+    assert!(net["code_lines"].is_null());
+
+    let json = report(&["analyze"]);
+    assert_eq!(json["subsystems"][1]["code_lines"], 3);
+    assert_eq!(json["subsystems"][2]["code_lines"], 0);
+
+    let stdout = kernel.run_ok(&["kconfig", "--full", "--by-subsystem"]);
+    assert!(stdout.contains("drivers/net"), "{}", stdout);
+    assert!(
+        stdout.contains("9 components in 4 subsystems"),
+        "{}",
+        stdout
+    );
+}
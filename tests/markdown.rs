@@ -0,0 +1,53 @@
+mod common;
+
+use common::FakeKernelBuilder;
+
+// set UPDATE_GOLDEN=1 to rewrite the golden files after an intended change
+fn assert_golden(name: &str, actual: &str) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(expected, actual, "golden file {:?} differs", path);
+}
+
+#[test]
+fn kconfig_diff_markdown() {
+    let old = FakeKernelBuilder::riscv().build();
+    let new = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\tbool \"foo driver\"\n\tdepends on MMU || SMP\n\nconfig BAR\n\tbool\n",
+        )
+        .build();
+    let stdout = old.run_ok(&["diff", new.root().to_str().unwrap(), "--format", "markdown"]);
+    assert_golden("kconfig_diff.md", &stdout);
+}
+
+#[test]
+fn defconfig_diff_markdown() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file(
+            "a.config",
+            "CONFIG_SMP=y\nCONFIG_FOO_DRV=m\nCONFIG_UNKNOWN=y\n",
+        )
+        .file(
+            "b.config",
+            "# CONFIG_SMP is not set\nCONFIG_FOO_DRV=y\nCONFIG_MMU=y\n",
+        )
+        .build();
+    let stdout = kernel.run_ok(&[
+        "diff",
+        "--guarded-code",
+        "--defconfig",
+        "linux-6.9.5/a.config",
+        "--compare-defconfig",
+        "linux-6.9.5/b.config",
+        "--format",
+        "markdown",
+    ]);
+    assert_golden("defconfig_diff.md", &stdout);
+}
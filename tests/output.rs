@@ -0,0 +1,55 @@
+mod common;
+
+use common::FakeKernelBuilder;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::Read;
+
+#[test]
+fn gz_reports_decompress_to_the_json_report() {
+    let old = FakeKernelBuilder::riscv().build();
+    let new = FakeKernelBuilder::riscv()
+        .file(
+            "arch/riscv/kernel/Kconfig",
+            "config FOO_DRV\n\ttristate \"foo driver\"\n\tdepends on MMU\n\nconfig BAR\n\tbool \"bar\"\n",
+        )
+        .build();
+    let report = old.root().with_file_name("report.json.gz");
+
+    let stdout = old.run_ok(&[
+        "diff",
+        new.root().to_str().unwrap(),
+        "--format",
+        "json",
+        "--output",
+        report.to_str().unwrap(),
+    ]);
+    assert!(!stdout.contains("\"added\""), "{}", stdout);
+
+    let mut json = String::new();
+    GzDecoder::new(File::open(&report).expect("open report"))
+        .read_to_string(&mut json)
+        .expect("gzip stream");
+    let diff: serde_json::Value = serde_json::from_str(&json).expect("json report");
+    assert_eq!(diff["added"], serde_json::json!(["BAR"]));
+    assert_eq!(diff["removed"], serde_json::json!([]));
+}
+
+#[test]
+fn the_code_export_is_written_to_the_report_file() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let report = kernel.root().with_file_name("count.json");
+
+    let stdout = kernel.run_ok(&[
+        "count",
+        "--format",
+        "json",
+        "--output",
+        report.to_str().unwrap(),
+    ]);
+    assert!(stdout.is_empty(), "{}", stdout);
+
+    let json = std::fs::read_to_string(&report).expect("read report");
+    let export: serde_json::Value = serde_json::from_str(&json).expect("json report");
+    assert_eq!(export["arches"][0]["arch"], "riscv");
+}
@@ -0,0 +1,715 @@
+#![cfg(feature = "cli")]
+
+use auto_script::core::file_counter::FileLines;
+use auto_script::core::interactive::ComponentCompleter;
+use auto_script::core::kconfig_expr::{Expr, Tristate};
+use auto_script::core::maintainers::{pattern_depth, Maintainers};
+use auto_script::core::utils::get_filed;
+use auto_script::core::version::{read_kernel_version, KernelVersion};
+use auto_script::{
+    ChoiceViolation, DotConfig, FileType, KconfigComponentType, KconfigCounter, KconfigStat,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const KCONFIG: &str = "\
+# SPDX-License-Identifier: GPL-2.0-only
+config BOOL_SYM
+\tbool \"a boolean\"
+\tdepends on MMU && !TINY
+\tselect GENERIC_BAR if SMP
+\tdefault y if SMP
+
+config TRI_SYM
+\ttristate \"a tristate\"
+\tdepends on BOOL_SYM
+\tdepends on PCI
+\thelp
+\t  depends on NOT_A_DEPENDENCY
+\t  config NOT_A_COMPONENT
+
+config INT_SYM
+\tint \"an integer\"
+\tdefault 8
+
+config HEX_SYM
+\thex
+\tdefault 0x1000
+
+config STRING_SYM
+\tstring \"a string\"
+\tdefault \"console=ttyS0\"
+
+config DEF_BOOL_SYM
+\tdef_bool y
+
+config DEF_TRI_SYM
+\tdef_tristate m
+
+config UNTYPED_SYM
+\tprompt \"no type\"
+
+choice
+\tprompt \"pick one\"
+\tdefault CHOICE_A
+
+config CHOICE_A
+\tbool \"a\"
+
+config CHOICE_B
+\tbool \"b\"
+
+endchoice
+";
+
+fn kconfig_counter(text: &str) -> KconfigCounter {
+    let kconfig = PathBuf::from("arch/riscv/Kconfig");
+    let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), kconfig.clone());
+    kc.parse_kconfig_reader(text.as_bytes(), &kconfig)
+        .expect("parse in-memory Kconfig");
+    kc
+}
+
+fn stat<'a>(kc: &'a KconfigCounter, name: &str) -> &'a KconfigStat {
+    kc.components()
+        .find(|(component, _)| *component == name)
+        .map(|(_, stat)| stat)
+        .unwrap_or_else(|| panic!("no component {}", name))
+}
+
+#[test]
+fn kconfig_types_follow_their_keyword() {
+    let kc = kconfig_counter(KCONFIG);
+    let types = [
+        ("BOOL_SYM", KconfigComponentType::Bool),
+        ("TRI_SYM", KconfigComponentType::Tristate),
+        ("INT_SYM", KconfigComponentType::Int),
+        ("HEX_SYM", KconfigComponentType::Hex),
+        ("STRING_SYM", KconfigComponentType::String),
+        ("DEF_BOOL_SYM", KconfigComponentType::Bool),
+        ("DEF_TRI_SYM", KconfigComponentType::Tristate),
+        ("UNTYPED_SYM", KconfigComponentType::Unknown),
+        ("CHOICE_A", KconfigComponentType::Bool),
+    ];
+    for (name, value_type) in types {
+        assert_eq!(stat(&kc, name).value_type(), value_type, "{}", name);
+    }
+    assert_eq!(kc.components().count(), 10);
+}
+
+#[test]
+fn kconfig_attributes_keep_their_expressions() {
+    let kc = kconfig_counter(KCONFIG);
+
+    let bool_sym = stat(&kc, "BOOL_SYM");
+    assert_eq!(bool_sym.depends(), ["MMU && !TINY"]);
+    assert_eq!(bool_sym.selects(), ["GENERIC_BAR if SMP"]);
+    assert_eq!(bool_sym.defaults(), ["y if SMP"]);
+
+    // repeated `depends on` lines are kept apart, help text is not parsed
+    assert_eq!(stat(&kc, "TRI_SYM").depends(), ["BOOL_SYM", "PCI"]);
+    assert_eq!(stat(&kc, "STRING_SYM").defaults(), ["\"console=ttyS0\""]);
+    assert_eq!(stat(&kc, "DEF_BOOL_SYM").defaults(), ["y"]);
+    assert_eq!(stat(&kc, "DEF_TRI_SYM").defaults(), ["m"]);
+    assert!(!kc.components().any(|(name, _)| name == "NOT_A_COMPONENT"));
+
+    let metrics = kc.metrics();
+    assert_eq!(metrics["help_lines"], 2);
+    assert_eq!(metrics["choices"], 1);
+    assert_eq!(
+        kc.find_dangling_depends(),
+        [
+            ("BOOL_SYM".to_string(), "MMU".to_string()),
+            ("BOOL_SYM".to_string(), "TINY".to_string()),
+            ("TRI_SYM".to_string(), "PCI".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn kconfig_def_bool_replaces_earlier_defaults() {
+    let kc = kconfig_counter("config SYM\n\tbool\n\tdefault n\n\tdef_bool y if SMP\n");
+    assert_eq!(stat(&kc, "SYM").defaults(), ["y if SMP"]);
+}
+
+#[test]
+fn repeated_unconditional_defaults_are_conflicts() {
+    let kc = kconfig_counter(
+        "\
+config SHADOWED
+\tint \"shadowed\"
+\tdefault 8
+\tdefault 16
+
+config GUARDED
+\tint \"guarded\"
+\tdefault 32 if 64BIT
+\tdefault 16 if SMP
+\tdefault 8
+
+config SAME_GUARD
+\tbool
+\tdefault y if SMP
+\tdefault n if SMP
+
+config QUOTED
+\tstring
+\tdefault \"boot if ready\"
+\tdefault \"fallback\" if EXPERT
+",
+    );
+    assert_eq!(kc.find_default_conflicts(), ["SAME_GUARD", "SHADOWED"]);
+    assert!(kconfig_counter(KCONFIG).find_default_conflicts().is_empty());
+}
+
+#[test]
+fn if_blocks_add_their_condition_to_enclosed_symbols() {
+    let kc = kconfig_counter(
+        "\
+if X
+
+config FIRST
+\tbool \"first\"
+\tdepends on MMU
+
+if Y || Z
+config NESTED
+\tbool
+endif
+
+config SECOND
+\ttristate \"second\"
+\tdefault y if SMP
+
+endif # X
+
+config OUTSIDE
+\tbool
+",
+    );
+    assert_eq!(stat(&kc, "FIRST").depends(), ["X", "MMU"]);
+    assert_eq!(stat(&kc, "SECOND").depends(), ["X"]);
+    assert_eq!(stat(&kc, "SECOND").defaults(), ["y if SMP"]);
+    assert_eq!(stat(&kc, "NESTED").effective_depends(), "X && (Y || Z)");
+    assert!(stat(&kc, "OUTSIDE").depends().is_empty());
+}
+
+#[test]
+fn type_lines_keep_their_prompt_and_visibility() {
+    let kc = kconfig_counter(
+        "config BAR\n\ttristate \"Bar\" if CONFIG_BAZ\n\nconfig QUX\n\tbool\n\tprompt \"say \\\"qux\\\"\"\n",
+    );
+    let bar = stat(&kc, "BAR");
+    assert_eq!(bar.value_type(), KconfigComponentType::Tristate);
+    assert_eq!(bar.prompt(), Some("Bar"));
+    assert_eq!(bar.visible_if(), Some("CONFIG_BAZ"));
+    // visibility does not constrain the value
+    assert!(bar.depends().is_empty());
+
+    assert_eq!(stat(&kc, "QUX").prompt(), Some("say \\\"qux\\\""));
+    assert_eq!(stat(&kc, "QUX").visible_if(), None);
+    assert_eq!(stat(&kconfig_counter(KCONFIG), "HEX_SYM").prompt(), None);
+}
+
+// reproducers from `fuzz/fuzz_targets/kconfig_line.rs`
+#[test]
+fn malformed_lines_have_empty_fields() {
+    assert_eq!(get_filed("so", "source"), "");
+    assert_eq!(get_filed("é", "i"), "");
+    assert_eq!(get_filed("source", "source"), "");
+    assert_eq!(get_filed("source \"Kconfig", "source"), "\"Kconfig");
+
+    let kc = kconfig_counter(
+        "config\nconfig FOO\n\tbool \"unterminated if BAR\n\tdefault\n\tdepends on\nif\nendif\nendif\n",
+    );
+    let foo = stat(&kc, "FOO");
+    assert_eq!(foo.prompt(), Some("unterminated if BAR"));
+    assert_eq!(foo.visible_if(), None);
+    assert_eq!(foo.depends(), [""]);
+}
+
+#[test]
+fn dotconfig_enabling_two_choice_members_is_reported() {
+    let kc = kconfig_counter(KCONFIG);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".config");
+
+    std::fs::write(&path, "CONFIG_CHOICE_A=y\n# CONFIG_CHOICE_B is not set\n").unwrap();
+    assert!(kc
+        .validate_dotconfig_choices(&DotConfig::load(&path).unwrap())
+        .is_empty());
+
+    std::fs::write(&path, "CONFIG_CHOICE_A=y\nCONFIG_CHOICE_B=y\n").unwrap();
+    assert_eq!(
+        kc.validate_dotconfig_choices(&DotConfig::load(&path).unwrap()),
+        [ChoiceViolation {
+            prompt: Some("pick one".into()),
+            enabled: vec!["CHOICE_A".into(), "CHOICE_B".into()],
+        }]
+    );
+}
+
+#[test]
+fn satisfiable_symbols_follow_the_enabled_set() {
+    let kc = kconfig_counter(
+        "config ON\n\tbool\n\nconfig OFF\n\tbool\n\nconfig MODULE\n\ttristate\n\n\
+         config BOTH\n\tbool\n\tdepends on ON && OFF\n\n\
+         config EITHER\n\tbool\n\tdepends on ON || OFF\n\n\
+         config NOT_OFF\n\tbool\n\tdepends on ON\n\tdepends on !(OFF || UNKNOWN)\n\n\
+         config WITH_MODULE\n\tbool\n\tdepends on MODULE && !n\n\n\
+         config COMPARED\n\tbool\n\tdepends on ON=y\n\n\
+         config MACRO\n\tbool\n\tdepends on $(success,true)\n",
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".config");
+    std::fs::write(
+        &path,
+        "CONFIG_ON=y\n# CONFIG_OFF is not set\nCONFIG_MODULE=m\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        kc.satisfiable_symbols(&DotConfig::load(&path).unwrap()),
+        [
+            "COMPARED",
+            "EITHER",
+            "MODULE",
+            "NOT_OFF",
+            "OFF",
+            "ON",
+            "WITH_MODULE"
+        ]
+    );
+}
+
+fn eval(expr: &str, values: &[(&str, &str)]) -> Tristate {
+    let values: HashMap<String, String> = values
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Expr::parse(expr).unwrap().eval(&values)
+}
+
+#[test]
+fn kconfig_expressions_bind_like_kconfig() {
+    let grouped = |expr: &str| Expr::parse(expr).unwrap().to_string();
+    assert_eq!(grouped("A || B && C"), "A || (B && C)");
+    assert_eq!(grouped("A && B || C"), "(A && B) || C");
+    assert_eq!(grouped("!A && B"), "!A && B");
+    assert_eq!(grouped("!(A && B)"), "!(A && B)");
+    assert_eq!(grouped("!A = y"), "!A=y");
+    assert_eq!(grouped("(A || B) && C"), "(A || B) && C");
+
+    let values = [("A", "y"), ("B", "n"), ("MOD", "m")];
+    assert_eq!(eval("A || B && C", &values), Tristate::Y);
+    assert_eq!(eval("(A || B) && C", &values), Tristate::N);
+    assert_eq!(eval("!B && !UNSET", &values), Tristate::Y);
+    assert_eq!(eval("!!A", &values), Tristate::Y);
+    // tristate logic: `m` survives `!` and caps `&&`
+    assert_eq!(eval("!MOD", &values), Tristate::M);
+    assert_eq!(eval("A && MOD", &values), Tristate::M);
+    assert_eq!(eval("B || MOD", &values), Tristate::M);
+    assert_eq!(eval("y && m", &values), Tristate::M);
+}
+
+#[test]
+fn kconfig_expressions_compare_numbers_and_strings() {
+    let values = [
+        ("VER", "6"),
+        ("BASE", "0x1000"),
+        ("NAME", "riscv"),
+        ("A", "y"),
+    ];
+    assert_eq!(eval("VER >= 6", &values), Tristate::Y);
+    assert_eq!(eval("VER > 6", &values), Tristate::N);
+    assert_eq!(eval("VER < 10", &values), Tristate::Y);
+    assert_eq!(eval("VER <= 5", &values), Tristate::N);
+    assert_eq!(eval("BASE = 4096", &values), Tristate::Y);
+    assert_eq!(eval("BASE != 0x1000", &values), Tristate::N);
+    assert_eq!(eval("NAME = \"riscv\"", &values), Tristate::Y);
+    assert_eq!(eval("NAME != 'arm64' && A=y", &values), Tristate::Y);
+    assert_eq!(eval("UNSET = n", &values), Tristate::Y);
+
+    let expr = Expr::parse("VER >= 6 && (A || !NAME = \"x\")").unwrap();
+    assert_eq!(expr.symbols(), ["VER", "A", "NAME"]);
+
+    for invalid in [
+        "",
+        "A &&",
+        "(A || B",
+        "A B",
+        "A = ",
+        "$(cc-option,-m64)",
+        "\"open",
+    ] {
+        let err = Expr::parse(invalid).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid Kconfig expression"),
+            "{}",
+            err
+        );
+    }
+}
+
+#[test]
+fn prompt_completes_component_names() {
+    let completer = ComponentCompleter::for_counter(&kconfig_counter(KCONFIG));
+    assert_eq!(
+        completer.candidates("CONFIG_choice_", 14),
+        (7, vec!["CHOICE_A", "CHOICE_B"])
+    );
+    // only the word before the cursor is completed
+    assert_eq!(
+        completer.candidates("BOOL_SYM DEF_T", 14),
+        (9, vec!["DEF_TRI_SYM"])
+    );
+    assert_eq!(
+        completer.candidates("DEF_TRI_SYM", 4),
+        (0, vec!["DEF_BOOL_SYM", "DEF_TRI_SYM"])
+    );
+    assert!(completer.candidates("NO_SUCH", 7).1.is_empty());
+    // valid UTF-8 never splits inside a character
+    assert!(completer.candidates("éééé", 8).1.is_empty());
+    assert_eq!(
+        completer.candidates("é\u{3000}CHOICE_", 12),
+        (5, vec!["CHOICE_A", "CHOICE_B"])
+    );
+}
+
+fn code_counter(source: &str) -> KconfigCounter {
+    let mut kc =
+        kconfig_counter("config OUTER\n\tbool\n\nconfig INNER\n\tbool\n\nconfig DRV\n\ttristate\n");
+    kc.parse_code_reader(source.as_bytes(), &PathBuf::from("drivers/drv.c"))
+        .expect("parse in-memory source");
+    kc
+}
+
+fn snippets(kc: &KconfigCounter, name: &str) -> Vec<(usize, bool)> {
+    stat(kc, name)
+        .code_snippets()
+        .iter()
+        .map(|snippet| (snippet.lines(), snippet.module()))
+        .collect()
+}
+
+#[test]
+fn ifdef_blocks_count_until_their_matching_endif() {
+    let kc = code_counter("#ifdef CONFIG_OUTER\nint a;\nint b;\n#endif\nint c;\n");
+    assert_eq!(snippets(&kc, "OUTER"), [(3, false)]);
+    let snippet = &stat(&kc, "OUTER").code_snippets()[0];
+    assert_eq!(snippet.code(), "#ifdef CONFIG_OUTER\nint a;\nint b;\n");
+    assert_eq!(snippet.file(), PathBuf::from("drivers/drv.c"));
+    assert_eq!(kc.total_code_lines(), 3);
+}
+
+#[test]
+fn nested_ifdef_belongs_to_the_outer_block() {
+    let kc = code_counter(
+        "#ifdef CONFIG_OUTER\nint a;\n#ifdef CONFIG_INNER\nint b;\n#endif\nint c;\n#endif\n",
+    );
+    assert_eq!(snippets(&kc, "OUTER"), [(6, false)]);
+    assert!(snippets(&kc, "INNER").is_empty());
+}
+
+#[test]
+fn unrelated_conditionals_do_not_close_a_block() {
+    let kc = code_counter(
+        "#ifdef CONFIG_OUTER\n#if defined(__KERNEL__)\nint a;\n#endif\n#ifndef CONFIG_INNER\nint b;\n#endif\n#endif\n",
+    );
+    assert_eq!(snippets(&kc, "OUTER"), [(7, false)]);
+    assert!(snippets(&kc, "INNER").is_empty());
+}
+
+#[test]
+fn block_inside_an_unknown_guard_is_still_counted() {
+    let kc = code_counter(
+        "#ifdef CONFIG_NOT_PARSED\n#ifdef CONFIG_INNER\nint a;\n#endif\nint b;\n#endif\n",
+    );
+    assert_eq!(snippets(&kc, "INNER"), [(2, false)]);
+    assert_eq!(kc.total_code_lines(), 2);
+}
+
+#[test]
+fn ifndef_and_stray_endif_are_ignored() {
+    let kc = code_counter("#endif\n#ifndef CONFIG_OUTER\nint a;\n#endif\n");
+    assert!(snippets(&kc, "OUTER").is_empty());
+    assert_eq!(kc.total_code_lines(), 0);
+}
+
+#[test]
+fn module_suffix_marks_module_only_code() {
+    let kc = code_counter(
+        "#if IS_ENABLED(CONFIG_DRV_MODULE)\nint a;\n#endif\n#ifdef CONFIG_DRV\nint b;\n#endif\n",
+    );
+    assert_eq!(snippets(&kc, "DRV"), [(2, true), (2, false)]);
+}
+
+#[test]
+fn snippets_record_the_line_of_their_guard() {
+    let kc = code_counter(
+        "int a;\n#ifdef CONFIG_NOT_PARSED\n#ifdef CONFIG_INNER\nint b;\n#endif\n#endif\n\n#ifdef CONFIG_INNER\nint c;\n#endif\n",
+    );
+    let lines: Vec<_> = stat(&kc, "INNER")
+        .code_snippets()
+        .iter()
+        .map(|snippet| snippet.line())
+        .collect();
+    assert_eq!(lines, [3, 8]);
+
+    let mut detail = Vec::new();
+    kc.write_component("INNER", &mut detail, false).unwrap();
+    let detail = String::from_utf8(detail).unwrap();
+    assert!(detail.contains("  drivers/drv.c:3:\n"), "{}", detail);
+    assert!(detail.contains("  drivers/drv.c:8:\n"), "{}", detail);
+}
+
+#[test]
+fn line_counts_follow_the_comment_syntax() {
+    let c = "/*\n * block\n */\nint a; // trailing\n\n// line\n";
+    let lines = FileLines::count(c.as_bytes(), FileType::TypeC).unwrap();
+    assert_eq!((lines.blank(), lines.comment(), lines.code()), (1, 4, 1));
+
+    let shell = "#!/bin/sh\n# comment\necho hi\n";
+    let lines = FileLines::count(shell.as_bytes(), FileType::TypeOther).unwrap();
+    assert_eq!(lines.shebang(), Some(FileType::TypeShell));
+    assert_eq!((lines.comment(), lines.code()), (2, 1));
+
+    let spdx = "# SPDX-License-Identifier: GPL-2.0 OR MIT\n";
+    let lines = FileLines::count(spdx.as_bytes(), FileType::TypePython).unwrap();
+    assert_eq!(lines.spdx(), Some("GPL-2.0 OR MIT"));
+}
+
+#[test]
+fn spdx_strips_only_parentheses_around_the_whole_expression() {
+    let spdx = |line: &str| {
+        FileLines::count(line.as_bytes(), FileType::TypeC)
+            .unwrap()
+            .spdx()
+            .map(str::to_string)
+    };
+    assert_eq!(
+        spdx("// SPDX-License-Identifier: ((GPL-2.0 OR MIT))\n").as_deref(),
+        Some("GPL-2.0 OR MIT")
+    );
+    assert_eq!(
+        spdx("/* SPDX-License-Identifier: (GPL-2.0 OR MIT) AND (BSD-3-Clause) */\n").as_deref(),
+        Some("(GPL-2.0 OR MIT) AND (BSD-3-Clause)")
+    );
+    assert_eq!(
+        spdx("// SPDX-License-Identifier: ((GPL-2.0 or MIT) and BSD-2-Clause)\n").as_deref(),
+        Some("(GPL-2.0 OR MIT) AND BSD-2-Clause")
+    );
+}
+
+fn makefile_version(header: &str) -> KernelVersion {
+    read_kernel_version(header.as_bytes(), Path::new("Makefile")).expect("parse Makefile header")
+}
+
+#[test]
+fn makefile_headers_tolerate_comments_and_spacing() {
+    let headers = [
+        "VERSION = 6 # major\nPATCHLEVEL = 9\t# minor\nSUBLEVEL = 5 # note\nEXTRAVERSION = # none\n",
+        "VERSION=6\nPATCHLEVEL=9\nSUBLEVEL=5\nEXTRAVERSION=\n",
+        "VERSION\t=\t6\n  PATCHLEVEL = 9  \nSUBLEVEL :=  5\nEXTRAVERSION ?=\n",
+        "# VERSION = 5\nVERSIONS = 5\nVERSION\nVERSION = 6\nPATCHLEVEL = 9\nSUBLEVEL = 5\n",
+    ];
+    for header in headers {
+        let version = makefile_version(header);
+        assert_eq!(version.to_string(), "6.9.5", "{:?}", header);
+        assert_eq!(version.extra, "");
+    }
+
+    let rc = makefile_version(
+        "VERSION = 6\nPATCHLEVEL = 10\nSUBLEVEL = 0\nEXTRAVERSION = -rc3 # test\n",
+    );
+    assert_eq!(rc.to_string(), "6.10.0-rc3");
+    // a line ending inside the key is not an assignment and must not panic
+    for header in ["V", "VERSION", "VERSION ", "VERSION :", "\u{feff}VERSION"] {
+        assert!(read_kernel_version(header.as_bytes(), Path::new("Makefile")).is_err());
+    }
+}
+
+#[test]
+fn makefile_headers_compose_the_kernel_version() {
+    let headers = [
+        (
+            "# SPDX-License-Identifier: GPL-2.0\nVERSION = 6\nPATCHLEVEL = 9\nSUBLEVEL = 5\n\
+             EXTRAVERSION =\nNAME = Hurr durr I'ma ninja sloth\n",
+            "6.9.5",
+            Some("Hurr durr I'ma ninja sloth"),
+        ),
+        (
+            "VERSION = 6\nPATCHLEVEL = 10\nSUBLEVEL = 0\nEXTRAVERSION = -rc3\n\
+             NAME = Baby Opossum Posse\n",
+            "6.10.0-rc3",
+            Some("Baby Opossum Posse"),
+        ),
+        (
+            "VERSION = 2\nPATCHLEVEL = 6\nSUBLEVEL = 32\nEXTRAVERSION = -358.el6\n\
+             NAME = Man-Eating Seals of Antiquity\n",
+            "2.6.32-358.el6",
+            Some("Man-Eating Seals of Antiquity"),
+        ),
+        // before SUBLEVEL was mandatory, and without NAME
+        (
+            "VERSION = 3\nPATCHLEVEL = 0\nSUBLEVEL =\nEXTRAVERSION = -rc1\n",
+            "3.0-rc1",
+            None,
+        ),
+    ];
+    for (header, release, name) in headers {
+        let version = makefile_version(header);
+        assert_eq!(version.to_string(), release);
+        assert_eq!(version.name.as_deref(), name);
+        // what an export stores reads back as the same version
+        assert_eq!(KernelVersion::from(release), version);
+    }
+
+    let rc = makefile_version(headers[1].0);
+    assert_eq!((rc.major, rc.patch, rc.sub), (6, 10, Some(0)));
+    assert_eq!(rc.extra, "-rc3");
+    assert_eq!(rc.tarball_dir(), "linux-6.10-rc3");
+    assert!(KernelVersion::from("6.9.5") < rc && rc < KernelVersion::from("6.10.0"));
+
+    // the KERNELVERSION rule further down is not the header
+    let later = makefile_version(
+        "VERSION = 6\nPATCHLEVEL = 1\nSUBLEVEL = 2\nKERNELVERSION = $(VERSION)\nVERSION = 7\n",
+    );
+    assert_eq!(later.to_string(), "6.1.2");
+    assert!(read_kernel_version(
+        "VERSION = six\nPATCHLEVEL = 1\nSUBLEVEL = 0\n".as_bytes(),
+        Path::new("Makefile")
+    )
+    .is_err());
+}
+
+const MAINTAINERS: &str = "\
+List of maintainers
+===================
+
+Descriptions of section entries and preferred order
+---------------------------------------------------
+
+\tM: *Mail* patches to: FullName <address@domain>
+\tF: *Files* and directories wildcard patterns.
+
+Maintainers List
+----------------
+
+RISC-V ARCHITECTURE
+M:\tPaul Walmsley <paul.walmsley@sifive.com>
+M:\tPalmer Dabbelt <palmer@dabbelt.com>
+L:\tlinux-riscv@lists.infradead.org
+S:\tSupported
+F:\tarch/riscv/
+X:\tarch/riscv/boot/dts/
+
+RISC-V SMP
+M:\tSome One <some.one@example.com>
+F:\tarch/riscv/kernel/smp*.c
+
+DEVICE TREE BINDINGS
+M:\tRob Herring <robh@kernel.org>
+F:\tarch/*/boot/dts/
+
+THE REST
+M:\tLinus Torvalds <torvalds@linux-foundation.org>
+F:\t*
+F:\t*/
+";
+
+#[test]
+fn maintainers_patterns_follow_get_maintainer_semantics() {
+    // a trailing `/` is the whole subtree, wildcards stay within one level
+    assert_eq!(
+        pattern_depth("arch/riscv/", "arch/riscv/kernel/smp.c"),
+        Some(2)
+    );
+    assert_eq!(pattern_depth("arch/riscv/", "arch/riscv"), None);
+    assert_eq!(
+        pattern_depth("arch/riscv/kernel/smp*.c", "arch/riscv/kernel/smpboot.c"),
+        Some(4)
+    );
+    assert_eq!(
+        pattern_depth("arch/riscv/kernel/*", "arch/riscv/kernel/vdso/vdso.c"),
+        None
+    );
+    assert_eq!(
+        pattern_depth("arch/*/boot/dts/", "arch/arm64/boot/dts/a/b.dts"),
+        Some(4)
+    );
+    assert_eq!(pattern_depth("*", "Makefile"), Some(1));
+    assert_eq!(pattern_depth("*", "init/main.c"), None);
+    assert_eq!(pattern_depth("*/", "init/main.c"), Some(1));
+    // a directory named without the `/` still covers what is below it
+    assert_eq!(
+        pattern_depth("drivers/net", "drivers/net/loopback.c"),
+        Some(2)
+    );
+    assert_eq!(pattern_depth("drivers/net", "drivers/network.c"), None);
+
+    let maintainers = Maintainers::parse(MAINTAINERS.as_bytes(), Path::new("MAINTAINERS")).unwrap();
+    let names = |paths: &[&str]| -> Vec<String> {
+        maintainers
+            .owners(paths.iter().copied())
+            .iter()
+            .map(|subsystem| subsystem.name.clone())
+            .collect()
+    };
+    assert_eq!(
+        names(&["arch/riscv/kernel/smp.c"]),
+        ["RISC-V SMP", "RISC-V ARCHITECTURE", "THE REST"]
+    );
+    // `X:` takes the device trees away from the arch maintainers
+    assert_eq!(
+        names(&["arch/riscv/boot/dts/sifive/board.dts"]),
+        ["DEVICE TREE BINDINGS", "THE REST"]
+    );
+    assert_eq!(
+        names(&["arch/riscv/Kconfig", "arch/riscv/kernel/smpboot.c"]),
+        ["RISC-V SMP", "RISC-V ARCHITECTURE", "THE REST"]
+    );
+    assert_eq!(names(&["init/main.c"]), ["THE REST"]);
+
+    let riscv = maintainers.owners(["arch/riscv/mm/init.c"])[0];
+    assert_eq!(
+        riscv.maintainers,
+        [
+            "Paul Walmsley <paul.walmsley@sifive.com>",
+            "Palmer Dabbelt <palmer@dabbelt.com>"
+        ]
+    );
+}
+
+#[test]
+fn maintainers_patterns_keep_the_case_of_paths() {
+    // the target and the match of the same netfilter module differ only in case
+    let excerpt = "\
+NETFILTER DSCP TARGET
+M:\tOne Person <one@example.com>
+F:\tnet/netfilter/xt_DSCP.c
+
+NETFILTER DSCP MATCH
+M:\tOther Person <other@example.com>
+F:\tnet/netfilter/xt_dscp.c
+";
+    assert_eq!(
+        pattern_depth("net/netfilter/xt_DSCP.c", "net/netfilter/xt_dscp.c"),
+        None
+    );
+    assert_eq!(
+        pattern_depth("net/netfilter/xt_D*.c", "net/netfilter/xt_DSCP.c"),
+        Some(3)
+    );
+
+    let maintainers = Maintainers::parse(excerpt.as_bytes(), Path::new("MAINTAINERS")).unwrap();
+    let names = |path: &str| -> Vec<String> {
+        maintainers
+            .owners([path])
+            .iter()
+            .map(|subsystem| subsystem.name.clone())
+            .collect()
+    };
+    assert_eq!(names("net/netfilter/xt_DSCP.c"), ["NETFILTER DSCP TARGET"]);
+    assert_eq!(names("net/netfilter/xt_dscp.c"), ["NETFILTER DSCP MATCH"]);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use auto_script::core::pipeline::{Options, Pipeline};
+use common::FakeKernelBuilder;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+fn fixture_options(arches: &[&str]) -> Options {
+    Options {
+        kernel_path: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/linux-6.9.5"),
+        arches: arches.iter().map(PathBuf::from).collect(),
+        sorted: true,
+        summarize: true,
+        ..Options::default()
+    }
+}
+
+#[test]
+fn code_analysis_retains_every_arch_and_the_common_tree() {
+    let mut pipeline = Pipeline::new(Options {
+        whole_tree: true,
+        ..fixture_options(&["riscv", "arm64"])
+    })
+    .unwrap();
+    assert_eq!(pipeline.version().to_string(), "6.9.5");
+    assert_eq!(
+        pipeline.version().name.as_deref(),
+        Some("Hurr durr I'ma ninja sloth")
+    );
+    pipeline.run_code_analysis().unwrap();
+    assert!(pipeline.warnings().is_empty());
+
+    let results = pipeline.results();
+    let arches: Vec<&str> = results.code.iter().map(|fc| fc.arch()).collect();
+    assert_eq!(arches, ["riscv", "arm64"]);
+
+    let total = |metrics: std::collections::BTreeMap<String, u64>| metrics["total.files"];
+    let merged = results.all_arches.unwrap();
+    assert_eq!(
+        total(merged.metrics()),
+        results
+            .code
+            .iter()
+            .map(|fc| total(fc.metrics()))
+            .sum::<u64>()
+    );
+    // only init/ and the top-level Makefile lie outside arch/
+    let common = results.common.unwrap();
+    assert_eq!(common.metrics()["C.files"], 1);
+    assert!(results.kconfig.is_empty());
+}
+
+#[test]
+fn single_arch_code_analysis_skips_the_merged_counter() {
+    let mut pipeline = Pipeline::new(fixture_options(&["riscv"])).unwrap();
+    pipeline.run_code_analysis().unwrap();
+
+    let results = pipeline.results();
+    assert_eq!(results.code.len(), 1);
+    assert!(results.all_arches.is_none());
+    assert!(results.common.is_none());
+}
+
+#[test]
+fn kconfig_analysis_counts_guarded_code_on_request() {
+    let mut pipeline = Pipeline::new(fixture_options(&["riscv"])).unwrap();
+    pipeline.run_kconfig_analysis(false).unwrap();
+    let kc = &pipeline.results().kconfig[0];
+    assert!(kc.components().any(|(name, _)| name == "SMP"));
+    assert_eq!(kc.total_code_lines(), 0);
+
+    let mut pipeline = Pipeline::new(fixture_options(&["riscv"])).unwrap();
+    pipeline.run_kconfig_analysis(true).unwrap();
+    assert!(pipeline.results().kconfig[0].total_code_lines() > 0);
+}
+
+#[test]
+fn fail_on_empty_rejects_an_arch_without_files() {
+    let mut pipeline = Pipeline::new(Options {
+        fail_on_empty: true,
+        ..fixture_options(&["x86"])
+    })
+    .unwrap();
+    let err = pipeline.run_code_analysis().unwrap_err();
+    assert!(err.to_string().contains("x86"), "{:#}", err);
+}
+
+#[test]
+fn kconfig_from_a_reader_needs_no_tree() {
+    let options = Options {
+        kernel_path: PathBuf::from("/no/such/kernel"),
+        ..Options::default()
+    };
+    let text = "config FOO\n\tbool \"foo\"\n\tdepends on BAR\n\nsource \"arch/riscv/kvm/Kconfig\"\n\nconfig BAR\n\tint\n";
+    let kc = options
+        .kconfig_counter_from_reader(Path::new("riscv"), &"6.9.5".into(), text.as_bytes())
+        .unwrap();
+
+    let mut names: Vec<&str> = kc.components().map(|(name, _)| name).collect();
+    names.sort();
+    assert_eq!(names, ["BAR", "FOO"]);
+    let (_, foo) = kc.components().find(|(name, _)| *name == "FOO").unwrap();
+    assert_eq!(foo.depends(), ["BAR"]);
+    // the missing tree is not consulted, the source is only recorded
+    assert_eq!(
+        kc.skipped_sources(),
+        [PathBuf::from("/no/such/kernel/arch/riscv/kvm/Kconfig")]
+    );
+}
+
+#[test]
+fn the_whole_pipeline_runs_on_an_in_memory_tree() {
+    let kernel_path = PathBuf::from("/no/such/linux-6.9.5");
+    let options = Options {
+        kernel_path: kernel_path.clone(),
+        whole_tree: true,
+        summarize: true,
+        source_tree: Rc::new(FakeKernelBuilder::riscv().tree(&kernel_path)),
+        ..Options::default()
+    };
+    assert!(!kernel_path.exists());
+
+    let mut pipeline = Pipeline::new(options).unwrap();
+    assert_eq!(pipeline.version().to_string(), "6.9.5");
+    pipeline.run_code_analysis().unwrap();
+    pipeline.run_kconfig_analysis(true).unwrap();
+    assert!(pipeline.warnings().is_empty());
+
+    let results = pipeline.results();
+    let metrics = results.code[0].metrics();
+    assert_eq!((metrics["C.files"], metrics["C Header.files"]), (1, 1));
+    // init/Kconfig and the Makefile
+    assert_eq!(results.common.unwrap().metrics()["total.files"], 2);
+
+    let kc = &results.kconfig[0];
+    assert_eq!(kc.components().count(), 5);
+    let (_, smp) = kc.components().find(|(name, _)| *name == "SMP").unwrap();
+    assert_eq!(smp.code_snippets().len(), 2);
+    assert!(kc.total_code_lines() > 0);
+}
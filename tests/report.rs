@@ -0,0 +1,327 @@
+mod common;
+
+use auto_script::core::baseline::Baseline;
+use auto_script::core::kconfig_counter::{DirRow, SubsystemReport, SubsystemRow};
+use auto_script::core::report::{AnalysisResult, Renderer, TextRenderer};
+use auto_script::{DirReport, FileCounter, KconfigComponentType, KconfigCounter};
+use common::FakeKernelBuilder;
+use std::collections::BTreeMap;
+
+fn render_with(renderer: TextRenderer, result: &AnalysisResult) -> String {
+    let mut out = Vec::new();
+    renderer.render(result, &mut out).expect("render to memory");
+    String::from_utf8(out).expect("utf-8 report")
+}
+
+fn render(result: &AnalysisResult) -> String {
+    render_with(TextRenderer::default(), result)
+}
+
+fn parsed_riscv(kconfig: &str) -> KconfigCounter {
+    let kernel = FakeKernelBuilder::riscv()
+        .file("arch/riscv/Kconfig", kconfig)
+        .build();
+    let mut kc = KconfigCounter::new(
+        "riscv".into(),
+        common::VERSION.into(),
+        kernel.root().join("arch/riscv/Kconfig"),
+    );
+    kc.parse_kconfig().expect("parse Kconfig");
+    kc
+}
+
+#[test]
+fn code_summary_renders_the_language_table() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let mut fc = FileCounter::new(
+        "riscv".into(),
+        common::VERSION.into(),
+        kernel.root().join("arch/riscv"),
+    );
+    fc.search().expect("scan arch");
+
+    let text = render(&fc.snapshot());
+    let rows = common::language_rows(&text);
+    assert!(rows.windows(2).all(|pair| pair[0].code >= pair[1].code));
+    assert_eq!(common::row(&rows, "C").files, 1);
+    assert_eq!(common::row(&rows, "C Header").files, 1);
+    assert_eq!(common::row(&rows, "kconfig").files, 2);
+    assert!(text.contains("Linux-6.9.5 Arch RISCV"), "{}", text);
+    assert!(!text.contains("Largest file"), "{}", text);
+}
+
+#[test]
+fn kconfig_summary_renders_components_and_types() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let mut kc = KconfigCounter::new(
+        "riscv".into(),
+        common::VERSION.into(),
+        kernel.root().join("arch/riscv/Kconfig"),
+    );
+    kc.parse_kconfig().expect("parse Kconfig");
+
+    let text = render(&kc.snapshot());
+    let sum = |label: &str| {
+        text.lines()
+            .find(|line| line.trim_end().ends_with(label))
+            .and_then(|line| line.split_whitespace().rev().nth(label.split(' ').count()))
+            .unwrap_or_else(|| panic!("no {} line in\n{}", label, text))
+            .to_string()
+    };
+    assert_eq!(sum("Components"), "5");
+    assert_eq!(sum("Total Code Lines"), "0");
+    assert!(text.lines().any(|line| line.contains("FOO_DRV")));
+    assert!(text
+        .lines()
+        .any(|line| line.split_whitespace().collect::<Vec<_>>() == ["boolean", "3"]));
+}
+
+#[test]
+fn kconfig_table_follows_the_requested_width() {
+    let long = "VERY_LONG_SYMBOL_NAME_THAT_DOES_NOT_FIT_INTO_A_NARROW_COLUMN";
+    let kc = parsed_riscv(&format!(
+        "config {}\n\tbool\n\nconfig SHORT\n\tbool\n",
+        long
+    ));
+    let widest = |text: &str| text.lines().map(|line| line.chars().count()).max().unwrap();
+
+    let narrow = render_with(TextRenderer::with_width(70), &kc.snapshot());
+    assert!(widest(&narrow) <= 70, "{}", narrow);
+    assert!(narrow.lines().any(|line| line.starts_with(&"-".repeat(70))));
+    assert!(!narrow.contains(long), "{}", narrow);
+    assert!(
+        narrow.contains("VERY_LONG_SYMBOL_NAME_THAT_DOES_~"),
+        "{}",
+        narrow
+    );
+
+    let wide = render_with(TextRenderer::with_width(160), &kc.snapshot());
+    assert!(wide.lines().any(|line| line == "-".repeat(160)));
+    assert!(wide.contains(long), "{}", wide);
+    assert!(widest(&wide) <= 160, "{}", wide);
+
+    // the type counts are right-aligned under their header
+    for text in [narrow, wide] {
+        let header = text
+            .lines()
+            .find(|line| line.trim_end().ends_with("Components") && line.contains("Type"))
+            .unwrap();
+        let boolean = text
+            .lines()
+            .find(|line| line.trim_start().starts_with("boolean"))
+            .unwrap();
+        assert_eq!(header.len(), boolean.len(), "{}", text);
+        assert!(boolean.ends_with(" 2"), "{}", text);
+    }
+}
+
+#[test]
+fn dir_report_follows_the_requested_width_and_color() {
+    let long = "drivers/a/rather/deeply/nested/directory/of/the/tree/with/a/long/name";
+    let report = || {
+        AnalysisResult::Dirs(DirReport {
+            version: common::VERSION.into(),
+            arch: "riscv".into(),
+            dirs: vec![
+                DirRow {
+                    dir: "arch/riscv/kernel".into(),
+                    components: ["FOO_DRV", "MMU", "NR_CPUS", "SMP"]
+                        .map(String::from)
+                        .into(),
+                },
+                DirRow {
+                    dir: long.into(),
+                    components: vec!["SMP".into()],
+                },
+            ],
+        })
+    };
+    let widest = |text: &str| text.lines().map(|line| line.chars().count()).max().unwrap();
+
+    let text = render(&report());
+    assert!(text.lines().any(|line| line == "-".repeat(90)), "{}", text);
+    assert!(widest(&text) <= 90, "{}", text);
+    let kernel_row = text
+        .lines()
+        .find(|line| line.starts_with("arch/riscv/kernel"))
+        .unwrap();
+    assert!(kernel_row.contains(" 4 "), "{}", text);
+    assert!(
+        kernel_row.ends_with("FOO_DRV, MMU, NR_CPUS +1 more"),
+        "{}",
+        text
+    );
+    assert!(!text.contains('\x1b'), "{}", text);
+
+    let narrow = render_with(TextRenderer::with_width(70), &report());
+    assert!(widest(&narrow) <= 70, "{}", narrow);
+    assert!(!narrow.contains(long), "{}", narrow);
+    assert!(
+        narrow.contains("drivers/a/rather/deeply/nes~"),
+        "{}",
+        narrow
+    );
+
+    let wide = render_with(TextRenderer::with_width(160), &report());
+    assert!(wide.contains(long), "{}", wide);
+    assert!(widest(&wide) <= 160, "{}", wide);
+
+    let colored = render_with(TextRenderer::default().with_color(true), &report());
+    assert!(colored.contains("\x1b[1m"), "{}", colored);
+    assert!(
+        colored.contains("Linux-6.9.5 riscv Directories"),
+        "{}",
+        colored
+    );
+}
+
+#[test]
+fn subsystem_report_follows_the_requested_width_and_color() {
+    let long = "drivers/a_subsystem_with_a_name_too_long_for_any_narrow_table";
+    let row = |subsystem: &str, components: usize| SubsystemRow {
+        subsystem: subsystem.into(),
+        components,
+        types: vec![
+            (KconfigComponentType::Bool, components),
+            (KconfigComponentType::Tristate, 0),
+            (KconfigComponentType::Int, 0),
+            (KconfigComponentType::Hex, 0),
+            (KconfigComponentType::String, 0),
+            (KconfigComponentType::Unknown, 0),
+        ],
+        code_lines: None,
+    };
+    let report = || {
+        AnalysisResult::Subsystems(SubsystemReport {
+            version: common::VERSION.into(),
+            arch: "riscv".into(),
+            subsystems: vec![row("arch/riscv", 3), row(long, 1)],
+        })
+    };
+    let widest = |text: &str| text.lines().map(|line| line.chars().count()).max().unwrap();
+
+    let text = render(&report());
+    assert!(text.lines().any(|line| line == "-".repeat(90)), "{}", text);
+    assert!(widest(&text) <= 90, "{}", text);
+    assert!(
+        text.lines()
+            .any(|line| line.split_whitespace().collect::<Vec<_>>()
+                == ["arch/riscv", "3", "3", "0", "0", "0", "0", "0"]),
+        "{}",
+        text
+    );
+    assert!(text.contains("4 components in 2 subsystems"), "{}", text);
+    assert!(!text.contains('\x1b'), "{}", text);
+
+    let narrow = render_with(TextRenderer::with_width(70), &report());
+    assert!(
+        narrow.lines().any(|line| line == "-".repeat(70)),
+        "{}",
+        narrow
+    );
+    assert!(!narrow.contains(long), "{}", narrow);
+    assert!(narrow.contains("drivers/a_s~"), "{}", narrow);
+
+    let wide = render_with(TextRenderer::with_width(160), &report());
+    assert!(wide.contains(long), "{}", wide);
+    assert!(widest(&wide) <= 160, "{}", wide);
+
+    let colored = render_with(TextRenderer::default().with_color(true), &report());
+    assert!(colored.contains("\x1b[1m"), "{}", colored);
+    assert!(
+        colored.contains("Linux-6.9.5 riscv Subsystems"),
+        "{}",
+        colored
+    );
+}
+
+#[test]
+fn baseline_check_renders_into_any_writer() {
+    let baseline = |version: &str, metrics: &[(&str, u64)]| {
+        let mut baseline = Baseline::new(version.into());
+        let metrics: BTreeMap<String, u64> = metrics
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        baseline.record("code.riscv", metrics);
+        baseline
+    };
+    let stored = baseline(
+        "6.9.4",
+        &[
+            ("C.code", 100),
+            ("Rust.code", 10),
+            ("C.files", 4),
+            ("Asm.files", 0),
+        ],
+    );
+    let current = baseline(
+        "6.9.5",
+        &[
+            ("C.code", 120),
+            ("Asm.code", 5),
+            ("C.files", 4),
+            ("Asm.files", 0),
+        ],
+    );
+
+    let report = stored.check(&current, 5.0);
+    assert_eq!(report.exceeded(), 1);
+    let text = render(&AnalysisResult::Baseline(report));
+    assert!(
+        text.contains("Baseline Linux-6.9.4 -> Linux-6.9.5"),
+        "{}",
+        text
+    );
+    let row = |name: &str| {
+        text.lines()
+            .find(|line| line.starts_with(name))
+            .map(|line| line.split_whitespace().skip(1).collect::<Vec<_>>())
+            .unwrap_or_else(|| panic!("no {} row in\n{}", name, text))
+    };
+    assert_eq!(row("code.riscv.C.code"), ["100", "120", "+20.00%", "!"]);
+    assert_eq!(row("code.riscv.Asm.code"), ["-", "5", "new"]);
+    assert_eq!(row("code.riscv.Rust.code"), ["10", "-", "not", "measured"]);
+    // unchanged metrics are listed too, a zero staying zero did not drift
+    assert_eq!(row("code.riscv.C.files"), ["4", "4", "+0.00%"]);
+    assert_eq!(row("code.riscv.Asm.files"), ["0", "0", "+0.00%"]);
+    assert!(
+        text.contains("1 metrics exceed the 5% tolerance"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn file_health_is_a_section_of_the_text_renderer() {
+    let kernel = FakeKernelBuilder::empty()
+        .file("arch/riscv/kernel/empty.c", "")
+        .file("arch/riscv/kernel/code.c", "int a;\n")
+        .file("arch/riscv/tools/doc.py", "# only a comment\n")
+        .file("arch/riscv/tools/stub.py", "\n")
+        .build();
+    let mut fc = FileCounter::new(
+        "riscv".into(),
+        common::VERSION.into(),
+        kernel.root().join("arch/riscv"),
+    );
+    fc.search().expect("scan arch");
+
+    let text = render(&AnalysisResult::FileHealth(fc.file_health_report()));
+    let rows: Vec<Vec<&str>> = text
+        .lines()
+        .filter(|line| !line.starts_with('-'))
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    // Python has two files without code, C only one
+    assert_eq!(
+        rows,
+        [
+            vec!["Language", "files", "empty", "comment-only"],
+            vec!["Python", "2", "1", "1"],
+            vec!["C", "2", "1", "0"],
+            vec!["SUM:", "4", "2", "1"],
+        ]
+    );
+    assert!(text.lines().any(|line| line == "-".repeat(70)), "{}", text);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use auto_script::SCHEMA_VERSION;
+use common::{FakeKernel, FakeKernelBuilder};
+use serde_json::Value;
+
+fn schema_version(json: &str) -> Value {
+    let document: Value = serde_json::from_str(json).expect("json document");
+    document["schema_version"].clone()
+}
+
+fn json_report(kernel: &FakeKernel, args: &[&str]) -> String {
+    kernel.run_ok(&[args, &["--format", "json"]].concat())
+}
+
+#[test]
+fn every_json_report_carries_the_schema_version() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file("arch/arm64/Kconfig", "config ARM64\n\tdef_bool y\n")
+        .file("a.config", "CONFIG_SMP=y\n")
+        .file("b.config", "# CONFIG_SMP is not set\n")
+        .build();
+    let other = FakeKernelBuilder::riscv().build();
+    let other_root = other.root().to_str().unwrap();
+
+    let reports = [
+        json_report(&kernel, &["count", "--arch", "riscv,arm64"]),
+        json_report(&kernel, &["diff", other_root]),
+        json_report(&kernel, &["diff", other_root, "--code-delta"]),
+        json_report(
+            &kernel,
+            &[
+                "diff",
+                "--defconfig",
+                "linux-6.9.5/a.config",
+                "--compare-defconfig",
+                "linux-6.9.5/b.config",
+            ],
+        ),
+        json_report(
+            &kernel,
+            &["kconfig", "--list-components", "--arch", "riscv,arm64"],
+        ),
+        json_report(&kernel, &["analyze", "--by-dir"]),
+        json_report(&kernel, &["analyze", "--by-subsystem"]),
+    ];
+    for report in &reports {
+        // the arch comparison follows the component list
+        let json = &report[report.find('{').unwrap()..];
+        assert_eq!(schema_version(json), SCHEMA_VERSION, "{}", report);
+    }
+}
+
+#[test]
+fn count_json_carries_the_schema_version_and_every_counter() {
+    let kernel = FakeKernelBuilder::riscv()
+        .file("arch/arm64/kernel/smp.c", "int smp;\n")
+        .build();
+    let stdout = json_report(&kernel, &["count", "--arch", "riscv,arm64", "--whole-tree"]);
+    assert_eq!(schema_version(&stdout), SCHEMA_VERSION);
+
+    let document: Value = serde_json::from_str(&stdout).unwrap();
+    let arches: Vec<_> = document["arches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|counter| counter["arch"].as_str().unwrap())
+        .collect();
+    assert_eq!(arches, ["riscv", "arm64"]);
+    assert_eq!(document["all_arches"]["total"]["files"], 5);
+    assert!(document["common"]["total"]["files"].is_u64());
+    assert!(document.get("docs").is_none());
+
+    let riscv = &document["arches"][0];
+    assert!(riscv["spdx"].is_object() && riscv["spdx_missing"].is_u64());
+    let languages = riscv["languages"].as_array().unwrap();
+    let c = languages.iter().find(|row| row["language"] == "C").unwrap();
+    assert_eq!((&c["files"], &c["code"]), (&1.into(), &10.into()));
+    for field in ["bytes", "average", "max_code", "max_path", "markers"] {
+        assert!(c.get(field).is_some(), "no {} in {}", field, c);
+    }
+}
+
+#[test]
+fn kconfig_exports_and_trends_carry_the_schema_version() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let export = kernel.root().with_file_name("kconfig.json");
+    kernel.run_ok(&[
+        "kconfig",
+        "--list-components",
+        "--export-kconfig",
+        export.to_str().unwrap(),
+    ]);
+    let json = std::fs::read_to_string(&export).unwrap();
+    assert_eq!(schema_version(&json), SCHEMA_VERSION);
+
+    // the export still reads back as the other side of a diff
+    let stdout = json_report(&kernel, &["diff", export.to_str().unwrap()]);
+    let diff: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(diff["changed"], serde_json::json!([]));
+
+    let trees = kernel.root().parent().unwrap().to_str().unwrap();
+    let stdout = kernel.run_ok(&["trend", trees, "--trend-format", "json"]);
+    assert_eq!(schema_version(&stdout), SCHEMA_VERSION);
+}
+
+#[test]
+fn exports_of_a_newer_schema_are_rejected() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let export = kernel.root().with_file_name("kconfig.json");
+    std::fs::write(
+        &export,
+        format!(
+            "{{\"schema_version\": {}, \"arches\": []}}",
+            SCHEMA_VERSION + 1
+        ),
+    )
+    .unwrap();
+
+    let output = kernel.run(&["diff", export.to_str().unwrap()]);
+    assert!(!output.success);
+    assert!(output.stderr.contains("newer"), "{}", output.stderr);
+}
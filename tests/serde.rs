@@ -0,0 +1,85 @@
+mod common;
+
+use auto_script::core::progress::PhaseCounts;
+use auto_script::{
+    FileCounter, FileStat, FileType, KconfigComponentType, KconfigCounter, KconfigStat,
+};
+use common::FakeKernelBuilder;
+use std::collections::HashMap;
+
+#[test]
+fn kconfig_stat_round_trips_through_json() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let mut kc = KconfigCounter::new(
+        "riscv".into(),
+        common::VERSION.into(),
+        kernel.root().join("arch/riscv/Kconfig"),
+    );
+    kc.parse_kconfig().expect("parse Kconfig");
+    kc.analyze_code().expect("analyze code");
+    let (_, nr_cpus) = kc
+        .components()
+        .find(|(name, _)| *name == "NR_CPUS")
+        .unwrap();
+    let (_, smp) = kc.components().find(|(name, _)| *name == "SMP").unwrap();
+
+    let json = serde_json::to_value(nr_cpus).unwrap();
+    assert_eq!(json["value_type"], "int");
+    let nr_cpus: KconfigStat = serde_json::from_value(json).unwrap();
+    assert_eq!(nr_cpus.value_type(), KconfigComponentType::Int);
+    assert_eq!(nr_cpus.depends(), ["SMP"]);
+    assert_eq!(nr_cpus.defaults(), ["\"8\""]);
+
+    let restored: KconfigStat = serde_json::from_str(&serde_json::to_string(smp).unwrap()).unwrap();
+    assert_eq!(restored.snippet_count(), smp.snippet_count());
+    assert_eq!(restored.code_lines(), smp.code_lines());
+    assert_eq!(restored.kconfig_file(), smp.kconfig_file());
+}
+
+#[test]
+fn file_stats_and_progress_round_trip_through_json() {
+    let kernel = FakeKernelBuilder::riscv().build();
+    let mut fc = FileCounter::new(
+        "riscv".into(),
+        common::VERSION.into(),
+        kernel.root().join("arch/riscv"),
+    );
+    let report = fc.search().expect("scan arch");
+    assert!(serde_json::to_value(&report).unwrap()["unreadable_files"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    let json = serde_json::to_string(fc.stats()).unwrap();
+    let restored: HashMap<FileType, FileStat> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.len(), fc.stats().len());
+    for (file_type, stat) in fc.stats() {
+        let other = &restored[file_type];
+        assert_eq!(
+            (
+                other.files(),
+                other.blank(),
+                other.comment(),
+                other.code(),
+                other.bytes()
+            ),
+            (
+                stat.files(),
+                stat.blank(),
+                stat.comment(),
+                stat.code(),
+                stat.bytes()
+            )
+        );
+    }
+
+    let progress = PhaseCounts {
+        kconfig_files: 3,
+        components: 6,
+        code_files: 5214,
+        skipped: 1,
+    };
+    let restored: PhaseCounts =
+        serde_json::from_str(&serde_json::to_string(&progress).unwrap()).unwrap();
+    assert_eq!(restored.to_string(), progress.to_string());
+}
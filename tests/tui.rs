@@ -0,0 +1,135 @@
+#![cfg(feature = "cli")]
+
+use auto_script::core::tui::Browser;
+use auto_script::KconfigCounter;
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Terminal;
+use std::path::PathBuf;
+
+const KCONFIG: &str = "\
+config SMP
+\tbool \"smp\"
+\tdepends on MMU
+
+config SMP_ON_UP
+\tbool
+\tdepends on SMP
+\tselect NR_CPUS
+
+config NR_CPUS
+\tint
+\tdefault 8 if SMP
+
+config MMU
+\tdef_bool y
+\tdepends on !NOMMU
+";
+
+fn counter() -> KconfigCounter {
+    let kconfig = PathBuf::from("arch/riscv/Kconfig");
+    let mut kc = KconfigCounter::new("riscv".into(), "6.9.5".into(), kconfig.clone());
+    kc.parse_kconfig_reader(KCONFIG.as_bytes(), &kconfig)
+        .expect("parse in-memory Kconfig");
+    kc
+}
+
+fn press(browser: &mut Browser, keys: &[KeyCode]) {
+    for key in keys {
+        assert!(browser.handle_key(KeyEvent::from(*key)), "{:?} quit", key);
+    }
+}
+
+fn type_text(browser: &mut Browser, text: &str) {
+    press(
+        browser,
+        &text.chars().map(KeyCode::Char).collect::<Vec<_>>(),
+    );
+}
+
+#[test]
+fn search_filters_the_component_list() {
+    let kc = counter();
+    let mut browser = Browser::new(&kc);
+    assert_eq!(browser.selected(), Some("MMU"));
+
+    press(&mut browser, &[KeyCode::Char('/')]);
+    type_text(&mut browser, "smp_*");
+    press(&mut browser, &[KeyCode::Enter]);
+    assert_eq!(browser.filter(), "smp_*");
+    assert_eq!(browser.selected(), Some("SMP_ON_UP"));
+
+    // the list only holds one match, moving down stays on it
+    press(&mut browser, &[KeyCode::Down]);
+    assert_eq!(browser.selected(), Some("SMP_ON_UP"));
+
+    press(&mut browser, &[KeyCode::Char('/'), KeyCode::Esc]);
+    assert_eq!(browser.filter(), "");
+    assert_eq!(browser.selected(), Some("SMP_ON_UP"));
+    assert!(!browser.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+}
+
+#[test]
+fn enter_jumps_along_the_dependency_tree() {
+    let kc = counter();
+    let mut browser = Browser::new(&kc);
+    press(&mut browser, &[KeyCode::Char('/')]);
+    type_text(&mut browser, "on_up");
+    press(&mut browser, &[KeyCode::Enter]);
+    assert_eq!(
+        browser.tree().collect::<Vec<_>>(),
+        [
+            (0, "NR_CPUS"),
+            (1, "SMP"),
+            (2, "MMU"),
+            (0, "SMP"),
+            (1, "MMU"),
+            (2, "NOMMU")
+        ]
+    );
+
+    // dependencies tab, open it, the first row is NR_CPUS outside the filter
+    press(
+        &mut browser,
+        &[KeyCode::Tab, KeyCode::Enter, KeyCode::Enter],
+    );
+    assert_eq!(browser.selected(), Some("NR_CPUS"));
+    assert_eq!(browser.filter(), "");
+
+    press(
+        &mut browser,
+        &[
+            KeyCode::Enter,
+            KeyCode::Enter,
+            KeyCode::Enter,
+            KeyCode::Enter,
+        ],
+    );
+    assert_eq!(browser.selected(), Some("MMU"));
+
+    // an undefined symbol leaves the selection alone
+    press(&mut browser, &[KeyCode::Enter, KeyCode::Enter]);
+    assert_eq!(browser.selected(), Some("MMU"));
+    assert_eq!(browser.tree().collect::<Vec<_>>(), [(0, "NOMMU")]);
+}
+
+#[test]
+fn draws_the_list_and_the_selected_tab() {
+    let kc = counter();
+    let mut browser = Browser::new(&kc);
+    press(&mut browser, &[KeyCode::Down, KeyCode::Down]);
+    let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+    terminal.draw(|frame| browser.draw(frame)).unwrap();
+
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("4 components"), "{}", screen);
+    assert!(screen.contains("Attributes"));
+    assert!(screen.contains("Prompt      smp"), "{}", screen);
+    assert!(screen.contains("Depends on  MMU"), "{}", screen);
+}